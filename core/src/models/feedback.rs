@@ -1,28 +1,126 @@
 //! Feedback model and related types
+//!
+//! `Feedback` is the result of the interactive feedback tool and the record
+//! of what gets persisted. On the wire it is wrapped in `VersionedFeedback`,
+//! which adds a `version` tag so consumers can deserialize results reliably
+//! as the format evolves.
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
-use std::time::{Duration, SystemTime};
+use std::time::Duration;
+
+use crate::models::AppError;
 
 /// Feedback data structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Feedback {
     pub project_directory: PathBuf,
+    /// Prompt the user responded to
     pub summary: String,
     pub command_logs: String,
     pub interactive_feedback: String,
-    pub conversation_history: String,
-    pub timestamp: SystemTime,
+    #[serde(default)]
+    pub conversation_history: Vec<ConversationHistoryEntry>,
+    /// Stored copies of the files the user attached
+    #[serde(default)]
+    pub attachments: Vec<PathBuf>,
+    pub timestamp: DateTime<Utc>,
     pub metadata: FeedbackMetadata,
 }
 
+/// Message of the conversation included with feedback
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversationHistoryEntry {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub role: String,
+    pub content: String,
+    #[serde(default)]
+    pub is_current: bool,
+}
+
 /// Feedback metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Durations are serialized as whole milliseconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FeedbackMetadata {
     pub tool_name: String,
+    #[serde(rename = "execution_time_ms", with = "duration_ms")]
     pub execution_time: Duration,
     pub commands_executed: Vec<String>,
+    /// Time from opening the feedback window until it was submitted or cancelled
+    #[serde(rename = "user_interaction_time_ms", with = "duration_ms")]
     pub user_interaction_time: Duration,
+    /// Time from opening the feedback window until the user started typing
+    #[serde(
+        rename = "time_to_first_keystroke_ms",
+        with = "option_duration_ms",
+        default
+    )]
+    pub time_to_first_keystroke: Option<Duration>,
+    /// Whether the user closed the window without giving feedback
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+/// Feedback tagged with the version of its format
+///
+/// Serializes as the feedback fields plus `"version": "1"`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "version")]
+pub enum VersionedFeedback {
+    #[serde(rename = "1")]
+    V1(Feedback),
+}
+
+impl From<Feedback> for VersionedFeedback {
+    fn from(feedback: Feedback) -> Self {
+        Self::V1(feedback)
+    }
+}
+
+impl VersionedFeedback {
+    /// Get the feedback in the current format
+    pub fn into_latest(self) -> Feedback {
+        match self {
+            Self::V1(feedback) => feedback,
+        }
+    }
+}
+
+mod duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+mod option_duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_some(&(duration.as_millis() as u64)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<u64>::deserialize(deserializer).map(|ms| ms.map(Duration::from_millis))
+    }
 }
 
 impl Default for FeedbackMetadata {
@@ -32,6 +130,8 @@ impl Default for FeedbackMetadata {
             execution_time: Duration::from_secs(0),
             commands_executed: vec![],
             user_interaction_time: Duration::from_secs(0),
+            time_to_first_keystroke: None,
+            cancelled: false,
         }
     }
 }
@@ -44,12 +144,31 @@ impl Feedback {
             summary,
             command_logs: String::new(),
             interactive_feedback: String::new(),
-            conversation_history: String::new(),
-            timestamp: SystemTime::now(),
+            conversation_history: Vec::new(),
+            attachments: Vec::new(),
+            timestamp: Utc::now(),
             metadata: FeedbackMetadata::default(),
         }
     }
 
+    /// Serialize with the version tag
+    pub fn to_versioned_json(&self) -> Result<String, AppError> {
+        serde_json::to_string(&VersionedFeedback::from(self.clone())).map_err(|e| {
+            AppError::SerializationError {
+                message: format!("Failed to serialize feedback: {}", e),
+            }
+        })
+    }
+
+    /// Deserialize feedback of any known version
+    pub fn from_versioned_json(json: &str) -> Result<Self, AppError> {
+        serde_json::from_str::<VersionedFeedback>(json)
+            .map(VersionedFeedback::into_latest)
+            .map_err(|e| AppError::DeserializationError {
+                message: format!("Failed to deserialize feedback: {}", e),
+            })
+    }
+
     /// Set command logs
     pub fn set_command_logs(&mut self, logs: String) {
         self.command_logs = logs;
@@ -65,6 +184,11 @@ impl Feedback {
         self.metadata.commands_executed.push(command);
     }
 
+    /// Add a stored attachment
+    pub fn add_attachment(&mut self, path: PathBuf) {
+        self.attachments.push(path);
+    }
+
     /// Set execution time
     pub fn set_execution_time(&mut self, duration: Duration) {
         self.metadata.execution_time = duration;
@@ -75,8 +199,20 @@ impl Feedback {
         self.metadata.user_interaction_time = duration;
     }
 
+    /// Record how long the user took to respond
+    pub fn set_response_timing(
+        &mut self,
+        time_to_first_keystroke: Option<Duration>,
+        time_to_submit: Duration,
+        cancelled: bool,
+    ) {
+        self.metadata.time_to_first_keystroke = time_to_first_keystroke;
+        self.metadata.user_interaction_time = time_to_submit;
+        self.metadata.cancelled = cancelled;
+    }
+
     /// Set conversation history
-    pub fn set_conversation_history(&mut self, history: String) {
+    pub fn set_conversation_history(&mut self, history: Vec<ConversationHistoryEntry>) {
         self.conversation_history = history;
     }
 }
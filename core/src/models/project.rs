@@ -1,9 +1,13 @@
 //! Project model and related types
 
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
+use crate::models::{AppSettings, RetentionPolicy};
+use crate::traits::SettingsError;
+
 /// Project representation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Project {
@@ -15,10 +19,14 @@ pub struct Project {
 
 /// Project-specific settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ProjectSettings {
-    pub auto_execute: bool,
+    /// Command prefilled in the command section; it only runs when the user
+    /// clicks Run, see `ProjectTrust` for running it automatically
     pub default_command: Option<String>,
     pub ui_state: UIState,
+    /// Conversation retention limits overriding the application settings
+    pub retention: RetentionPolicy,
 }
 
 /// UI state for the project
@@ -40,13 +48,185 @@ pub struct ProjectMetadata {
 impl Default for ProjectSettings {
     fn default() -> Self {
         Self {
-            auto_execute: false,
             default_command: None,
             ui_state: UIState {
                 window_size: (800, 600),
                 window_position: (100, 100),
                 show_command_section: false,
             },
+            retention: RetentionPolicy::default(),
+        }
+    }
+}
+
+impl ProjectSettings {
+    /// Get the settings file path for a project
+    pub fn settings_path(project_directory: &Path) -> PathBuf {
+        project_directory.join(".ifm-ruta").join("project.toml")
+    }
+
+    /// Load project settings, falling back to defaults if none are saved
+    pub fn load(project_directory: &Path) -> Result<Self, SettingsError> {
+        let settings_path = Self::settings_path(project_directory);
+        if !settings_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&settings_path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// Get the conversation retention policy of the project
+    ///
+    /// Limits set in the project override those of the application.
+    pub fn retention_policy(&self, app_settings: &AppSettings) -> RetentionPolicy {
+        app_settings.retention.with_overrides(&self.retention)
+    }
+
+    /// Save project settings
+    pub fn save(&self, project_directory: &Path) -> Result<(), SettingsError> {
+        let settings_path = Self::settings_path(project_directory);
+        if let Some(parent) = settings_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let content = toml::to_string_pretty(self)?;
+        std::fs::write(&settings_path, content)?;
+        Ok(())
+    }
+}
+
+/// Per-project permissions only the user can grant
+///
+/// Stored in the user config directory under the canonical project path,
+/// never inside the project: a cloned repository or the agent can write
+/// `.ifm-ruta/project.toml`, but must not be able to grant itself these.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectTrust {
+    /// Command run as soon as the feedback window opens
+    pub auto_execute_command: Option<String>,
+    /// Commands the user approved to run without asking again
+    pub approved_commands: Vec<String>,
+}
+
+/// Trust file contents, by canonical project path
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ProjectTrustFile {
+    projects: BTreeMap<String, ProjectTrust>,
+}
+
+impl ProjectTrustFile {
+    fn load(trust_path: &Path) -> Result<Self, SettingsError> {
+        if !trust_path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(trust_path)?;
+        Ok(toml::from_str(&content)?)
+    }
+}
+
+impl ProjectTrust {
+    /// Get the user-level trust file path
+    pub fn trust_path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("ifm-ruta")
+            .join("trusted_projects.toml")
+    }
+
+    /// Get the key of a project in the trust file
+    ///
+    /// Projects that cannot be resolved have no key and are never trusted.
+    fn project_key(project_directory: &Path) -> Option<String> {
+        std::fs::canonicalize(project_directory)
+            .ok()
+            .map(|path| path.to_string_lossy().into_owned())
+    }
+
+    /// Check whether a trust file lives inside the project tree, where the
+    /// project could write it
+    fn is_inside_project(trust_path: &Path, project_key: &str) -> bool {
+        let directory = trust_path.parent().unwrap_or(Path::new("."));
+        let directory =
+            std::fs::canonicalize(directory).unwrap_or_else(|_| directory.to_path_buf());
+        directory.starts_with(project_key)
+    }
+
+    /// Load what the user granted a project
+    pub fn load(project_directory: &Path) -> Result<Self, SettingsError> {
+        Self::load_from(&Self::trust_path(), project_directory)
+    }
+
+    /// Load what the user granted a project from a trust file
+    pub fn load_from(trust_path: &Path, project_directory: &Path) -> Result<Self, SettingsError> {
+        let Some(key) = Self::project_key(project_directory) else {
+            return Ok(Self::default());
+        };
+        if Self::is_inside_project(trust_path, &key) {
+            tracing::warn!(
+                "Ignoring trust file {} inside project {}",
+                trust_path.display(),
+                key
+            );
+            return Ok(Self::default());
+        }
+        Ok(ProjectTrustFile::load(trust_path)?
+            .projects
+            .remove(&key)
+            .unwrap_or_default())
+    }
+
+    /// Save what the user granted a project
+    pub fn save(&self, project_directory: &Path) -> Result<(), SettingsError> {
+        self.save_to(&Self::trust_path(), project_directory)
+    }
+
+    /// Save what the user granted a project to a trust file
+    pub fn save_to(
+        &self,
+        trust_path: &Path,
+        project_directory: &Path,
+    ) -> Result<(), SettingsError> {
+        let key = std::fs::canonicalize(project_directory)?
+            .to_string_lossy()
+            .into_owned();
+        if Self::is_inside_project(trust_path, &key) {
+            return Err(SettingsError::PermissionDenied {
+                message: format!(
+                    "Trust file {} is inside project {}",
+                    trust_path.display(),
+                    key
+                ),
+            });
+        }
+
+        let mut file = ProjectTrustFile::load(trust_path)?;
+        if *self == Self::default() {
+            file.projects.remove(&key);
+        } else {
+            file.projects.insert(key, self.clone());
+        }
+
+        if let Some(parent) = trust_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(trust_path, toml::to_string_pretty(&file)?)?;
+        Ok(())
+    }
+
+    /// Check whether a command line was approved before
+    pub fn is_command_approved(&self, command_line: &str) -> bool {
+        self.approved_commands
+            .iter()
+            .any(|approved| approved == command_line.trim())
+    }
+
+    /// Remember an approved command line
+    pub fn approve_command(&mut self, command_line: &str) {
+        if !self.is_command_approved(command_line) {
+            self.approved_commands.push(command_line.trim().to_string());
         }
     }
 }
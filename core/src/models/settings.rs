@@ -10,6 +10,55 @@ pub struct AppSettings {
     pub ui: UISettings,
     pub security: SecuritySettings,
     pub performance: PerformanceSettings,
+    #[serde(default)]
+    pub metrics: MetricsSettings,
+    #[serde(default)]
+    pub retention: RetentionPolicy,
+    #[serde(default)]
+    pub conversations: ConversationSettings,
+}
+
+/// Conversation storage settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConversationSettings {
+    /// Where conversations are stored
+    pub backend: ConversationBackend,
+    /// File format of new sessions of the file backend; existing sessions
+    /// keep theirs until migrated
+    pub format: SessionFormat,
+}
+
+/// Storage backend of conversations
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConversationBackend {
+    /// One file per session under `.ifm-ruta/conversations`
+    #[default]
+    Files,
+    /// Single-file embedded database at `.ifm-ruta/conversations.redb`
+    Database,
+}
+
+/// On-disk format of a conversation session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionFormat {
+    /// One pretty-printed JSON document, rewritten on every change
+    #[default]
+    Json,
+    /// Append-only log of one JSON record per line
+    Jsonl,
+}
+
+impl SessionFormat {
+    /// File extension of sessions stored in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Jsonl => "jsonl",
+        }
+    }
 }
 
 /// General application settings
@@ -32,17 +81,106 @@ pub struct UISettings {
 /// Security settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecuritySettings {
+    /// Commands that may be spawned; empty allows every command only when
+    /// sandbox mode is off
+    #[serde(default = "default_allowed_commands")]
     pub allowed_commands: Vec<String>,
     pub sandbox_mode: bool,
     pub max_process_time: Duration,
+    /// Address space limit in bytes for sandboxed processes
+    ///
+    /// Overrides `performance.max_memory_usage`; compilers and runtimes
+    /// reserve far more address space than they use, so keep it to several
+    /// gigabytes.
+    #[serde(default)]
+    pub max_process_memory: Option<u64>,
 }
 
 /// Performance settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformanceSettings {
+    /// Address space limit in bytes for sandboxed processes, unless
+    /// `security.max_process_memory` is set
     pub max_memory_usage: usize,
     pub log_rotation_size: usize,
     pub cache_size: usize,
+    /// Number of recent events kept in memory for troubleshooting
+    #[serde(default = "default_event_history_size")]
+    pub event_history_size: usize,
+}
+
+/// Commands allowed by default when sandbox mode is on
+pub const DEFAULT_ALLOWED_COMMANDS: &[&str] = &[
+    "cargo", "rustc", "rustfmt", "git", "make", "npm", "yarn", "pnpm", "go", "python3", "pytest",
+    "ls", "cat", "echo", "pwd", "grep", "rg",
+];
+
+fn default_allowed_commands() -> Vec<String> {
+    DEFAULT_ALLOWED_COMMANDS
+        .iter()
+        .map(|command| command.to_string())
+        .collect()
+}
+
+/// Number of recent events kept in memory unless configured
+pub const DEFAULT_EVENT_HISTORY_SIZE: usize = 1000;
+
+fn default_event_history_size() -> usize {
+    DEFAULT_EVENT_HISTORY_SIZE
+}
+
+/// Metrics export settings
+///
+/// Both exporters are off unless configured.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsSettings {
+    /// Address of the local HTTP listener serving `/metrics` (e.g.
+    /// `127.0.0.1:9464`); no listener when unset
+    pub http_listen_address: Option<String>,
+    /// Interval for writing `.ifm-ruta/metrics.prom` in the directory the
+    /// server runs in; zero (the default) disables it
+    pub textfile_interval: Duration,
+}
+
+/// Retention policy for stored conversations
+///
+/// Unset limits keep everything. Sessions are removed oldest (least recently
+/// updated) first; the most recent session is never removed to satisfy
+/// `max_total_bytes`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Most sessions kept per project
+    pub max_sessions: Option<usize>,
+    /// Sessions not updated for longer than this are removed
+    pub max_age: Option<Duration>,
+    /// Total size of the sessions and their attachments per project
+    pub max_total_bytes: Option<u64>,
+    /// Oldest messages beyond this are dropped from a session
+    pub max_messages_per_session: Option<usize>,
+}
+
+impl RetentionPolicy {
+    /// Whether the policy never removes anything
+    pub fn is_unlimited(&self) -> bool {
+        self.max_sessions.is_none()
+            && self.max_age.is_none()
+            && self.max_total_bytes.is_none()
+            && self.max_messages_per_session.is_none()
+    }
+
+    /// Apply the limits set in `overrides` on top of this policy
+    pub fn with_overrides(&self, overrides: &RetentionPolicy) -> Self {
+        Self {
+            max_sessions: overrides.max_sessions.or(self.max_sessions),
+            max_age: overrides.max_age.or(self.max_age),
+            max_total_bytes: overrides.max_total_bytes.or(self.max_total_bytes),
+            max_messages_per_session: overrides
+                .max_messages_per_session
+                .or(self.max_messages_per_session),
+        }
+    }
 }
 
 /// Log level enumeration
@@ -78,15 +216,20 @@ impl Default for AppSettings {
                 show_command_section: false,
             },
             security: SecuritySettings {
-                allowed_commands: vec![],
+                allowed_commands: default_allowed_commands(),
                 sandbox_mode: true,
                 max_process_time: Duration::from_secs(60),
+                max_process_memory: None,
             },
             performance: PerformanceSettings {
-                max_memory_usage: 100 * 1024 * 1024, // 100MB
+                max_memory_usage: 4 << 30,           // 4GB
                 log_rotation_size: 10 * 1024 * 1024, // 10MB
                 cache_size: 50 * 1024 * 1024,        // 50MB
+                event_history_size: default_event_history_size(),
             },
+            metrics: MetricsSettings::default(),
+            retention: RetentionPolicy::default(),
+            conversations: ConversationSettings::default(),
         }
     }
 }
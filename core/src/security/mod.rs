@@ -2,6 +2,8 @@
 
 pub mod input_validator;
 pub mod path_validator;
+pub mod process_policy;
 
 pub use input_validator::InputValidator;
 pub use path_validator::PathValidator;
+pub use process_policy::ProcessPolicy;
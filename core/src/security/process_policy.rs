@@ -0,0 +1,424 @@
+//! Process spawning policy
+//!
+//! Decides which processes may be spawned and sandboxes the ones that are
+//! allowed, based on the security settings
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::models::{AppSettings, SecuritySettings};
+use crate::security::InputValidator;
+use crate::traits::{ProcessSpec, ResourceLimits};
+
+/// Substrings of environment variable names that usually hold secrets
+const SECRET_ENV_PATTERNS: &[&str] = &[
+    "SECRET",
+    "TOKEN",
+    "PASSWORD",
+    "PASSWD",
+    "API_KEY",
+    "APIKEY",
+    "PRIVATE_KEY",
+    "CREDENTIAL",
+    "AUTH",
+];
+
+/// Prefixes of environment variables belonging to cloud provider credentials
+const SECRET_ENV_PREFIXES: &[&str] = &["AWS_", "AZURE_", "GOOGLE_APPLICATION_", "GCP_"];
+
+/// Shells, which run the script given with `-c` (alone or in a flag cluster
+/// such as `-ec`)
+const SHELLS: &[&str] = &[
+    "sh", "bash", "zsh", "dash", "ksh", "mksh", "fish", "csh", "tcsh", "ash", "busybox",
+];
+
+/// Interpreters and the options that make them run code given as an argument
+const INLINE_CODE_OPTIONS: &[(&str, &[&str])] = &[
+    ("pwsh", &["-c", "-command", "-encodedcommand"]),
+    ("powershell", &["-c", "-command", "-encodedcommand"]),
+    ("cmd", &["/c", "/k"]),
+    ("python", &["-c"]),
+    ("python3", &["-c"]),
+    ("node", &["-e", "-p", "--eval", "--print"]),
+    ("perl", &["-e"]),
+    ("ruby", &["-e"]),
+    ("php", &["-r"]),
+];
+
+/// Open file descriptors allowed per sandboxed process
+const SANDBOX_OPEN_FILES: u64 = 1024;
+
+/// Processes a sandboxed process may start on top of those the user already
+/// runs
+const SANDBOX_PROCESS_HEADROOM: u64 = 256;
+
+/// Count the processes and threads of the current user, which is what the
+/// kernel compares with `RLIMIT_NPROC`
+#[cfg(target_os = "linux")]
+fn user_process_count() -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    let uid = unsafe { libc::getuid() };
+    let mut count = 0;
+    for entry in std::fs::read_dir("/proc").ok()?.flatten() {
+        let is_process = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.bytes().all(|b| b.is_ascii_digit()));
+        if !is_process || entry.metadata().map_or(true, |m| m.uid() != uid) {
+            continue;
+        }
+        // A process that exited meanwhile still counts as one
+        count +=
+            std::fs::read_dir(entry.path().join("task")).map_or(1, |tasks| tasks.count() as u64);
+    }
+    Some(count)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn user_process_count() -> Option<u64> {
+    None
+}
+
+/// Policy applied to every process before it is spawned
+///
+/// # Checks
+/// - Command is on the allowlist (an empty allowlist allows every command
+///   only outside sandbox mode)
+/// - Shells and interpreters are not given inline code (sandbox mode)
+/// - Arguments pass input validation (sandbox mode)
+/// - Working directory is inside the project directory (sandbox mode)
+/// - Inherited environment is scrubbed of secrets (sandbox mode)
+/// - Resource limits are applied (sandbox mode)
+#[derive(Debug, Clone)]
+pub struct ProcessPolicy {
+    allowed_commands: HashSet<String>,
+    sandbox_mode: bool,
+    project_dir: PathBuf,
+    limits: ResourceLimits,
+}
+
+impl ProcessPolicy {
+    /// Build a policy from the application settings for a project
+    ///
+    /// Address space is limited by `max_process_memory`, or by
+    /// `max_memory_usage` when that is not set.
+    pub fn from_settings(settings: &AppSettings, project_dir: &Path) -> Self {
+        let mut policy = Self::from_security_settings(&settings.security, project_dir);
+        policy.limits.address_space = settings
+            .security
+            .max_process_memory
+            .or(Some(settings.performance.max_memory_usage as u64));
+        policy
+    }
+
+    /// Build a policy from the security settings for a project
+    ///
+    /// CPU time is limited by `max_process_time`, and address space by
+    /// `max_process_memory` only when it is set. The kernel counts every
+    /// process of the user against the process limit, so it allows a fixed
+    /// number of processes on top of those the user runs now.
+    pub fn from_security_settings(settings: &SecuritySettings, project_dir: &Path) -> Self {
+        if settings.sandbox_mode && settings.allowed_commands.is_empty() {
+            tracing::warn!(
+                "security.allowed_commands is empty, so sandbox mode denies every command"
+            );
+        }
+        Self {
+            allowed_commands: settings.allowed_commands.iter().cloned().collect(),
+            sandbox_mode: settings.sandbox_mode,
+            project_dir: project_dir
+                .canonicalize()
+                .unwrap_or_else(|_| project_dir.to_path_buf()),
+            limits: ResourceLimits {
+                cpu_time: Some(settings.max_process_time),
+                address_space: settings.max_process_memory,
+                open_files: Some(SANDBOX_OPEN_FILES),
+                processes: user_process_count().map(|count| count + SANDBOX_PROCESS_HEADROOM),
+            },
+        }
+    }
+
+    /// Replace the resource limits applied in sandbox mode
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Get the resource limits applied in sandbox mode
+    pub fn limits(&self) -> &ResourceLimits {
+        &self.limits
+    }
+
+    /// Allow an additional command
+    pub fn allow_command(&mut self, command: impl Into<String>) {
+        self.allowed_commands.insert(command.into());
+    }
+
+    /// Whether sandbox restrictions are enforced
+    pub fn sandbox_mode(&self) -> bool {
+        self.sandbox_mode
+    }
+
+    /// Get the project directory processes are confined to
+    pub fn project_dir(&self) -> &Path {
+        &self.project_dir
+    }
+
+    /// Check a process specification against the policy
+    ///
+    /// # Returns
+    /// - `Ok(ProcessSpec)` with the sandboxed specification to spawn
+    /// - `Err(String)` with the reason the process was denied
+    pub fn apply(&self, spec: &ProcessSpec) -> Result<ProcessSpec, String> {
+        let allowed = self.allowed_commands.contains(&spec.command)
+            || (self.allowed_commands.is_empty() && !self.sandbox_mode);
+        if !allowed {
+            return Err(format!("Command not allowed: {}", spec.command));
+        }
+
+        if !self.sandbox_mode {
+            return Ok(spec.clone());
+        }
+
+        if let Some(option) = Self::inline_code_option(spec) {
+            return Err(format!(
+                "Inline code is not allowed: {} {}",
+                spec.command, option
+            ));
+        }
+
+        InputValidator::validate_input(&spec.command)
+            .map_err(|e| format!("Invalid command '{}': {}", spec.command, e))?;
+        for arg in spec.args.iter().filter(|arg| !arg.is_empty()) {
+            InputValidator::validate_input(arg)
+                .map_err(|e| format!("Invalid argument '{}': {}", arg, e))?;
+        }
+
+        let cwd = spec.cwd.as_deref().unwrap_or(&self.project_dir);
+        let cwd = cwd
+            .canonicalize()
+            .map_err(|e| format!("Invalid working directory {}: {}", cwd.display(), e))?;
+        if !cwd.starts_with(&self.project_dir) {
+            return Err(format!(
+                "Working directory {} is outside the project directory {}",
+                cwd.display(),
+                self.project_dir.display()
+            ));
+        }
+
+        let mut env: HashMap<String, String> = std::env::vars()
+            .filter(|(name, _)| !Self::is_secret_env(name))
+            .collect();
+        env.extend(spec.env.clone());
+
+        let mut sandboxed = spec.clone();
+        sandboxed.cwd = Some(cwd);
+        sandboxed.env = env;
+        sandboxed.env_clear = true;
+        if sandboxed.limits.is_none() {
+            sandboxed.limits = Some(self.limits.clone());
+        }
+        Ok(sandboxed)
+    }
+
+    /// Find an argument that makes a shell or interpreter run inline code
+    fn inline_code_option(spec: &ProcessSpec) -> Option<&str> {
+        let program = Path::new(&spec.command)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&spec.command)
+            .to_ascii_lowercase();
+        let program = program.strip_suffix(".exe").unwrap_or(&program);
+
+        if SHELLS.contains(&program) {
+            return spec
+                .args
+                .iter()
+                .find(|arg| {
+                    arg.starts_with('-')
+                        && !arg.starts_with("--")
+                        && arg[1..].chars().all(|c| c.is_ascii_alphabetic())
+                        && arg.contains('c')
+                })
+                .map(String::as_str);
+        }
+
+        let (_, options) = INLINE_CODE_OPTIONS
+            .iter()
+            .find(|(interpreter, _)| *interpreter == program)?;
+        spec.args
+            .iter()
+            .find(|arg| {
+                let arg = arg.to_ascii_lowercase();
+                options.iter().any(|option| {
+                    arg == *option
+                        || arg.starts_with(&format!("{}=", option))
+                        // Short options can carry the code directly, as in `-cprint(1)`
+                        || (option.len() == 2 && arg.starts_with(option))
+                })
+            })
+            .map(String::as_str)
+    }
+
+    /// Check whether an environment variable likely holds a secret
+    pub fn is_secret_env(name: &str) -> bool {
+        let name = name.to_ascii_uppercase();
+        SECRET_ENV_PATTERNS
+            .iter()
+            .any(|pattern| name.contains(pattern))
+            || SECRET_ENV_PREFIXES
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn policy(allowed: &[&str], sandbox_mode: bool) -> ProcessPolicy {
+        let settings = SecuritySettings {
+            allowed_commands: allowed.iter().map(|c| c.to_string()).collect(),
+            sandbox_mode,
+            max_process_time: Duration::from_secs(60),
+            max_process_memory: None,
+        };
+        ProcessPolicy::from_security_settings(&settings, &std::env::temp_dir())
+    }
+
+    #[test]
+    fn test_allowlist() {
+        let policy = policy(&["echo"], false);
+        assert!(policy.apply(&ProcessSpec::new("echo")).is_ok());
+        assert!(policy.apply(&ProcessSpec::new("rm")).is_err());
+        assert!(policy.apply(&ProcessSpec::new("/bin/echo")).is_err());
+    }
+
+    #[test]
+    fn test_empty_allowlist_denies_in_sandbox_mode() {
+        assert!(policy(&[], true).apply(&ProcessSpec::new("ls")).is_err());
+        assert!(policy(&[], false).apply(&ProcessSpec::new("ls")).is_ok());
+    }
+
+    #[test]
+    fn test_default_settings_allow_common_tools() {
+        let policy = ProcessPolicy::from_settings(&AppSettings::default(), &std::env::temp_dir());
+        assert!(policy.sandbox_mode());
+        assert!(policy.apply(&ProcessSpec::new("cargo").arg("test")).is_ok());
+        assert!(policy.apply(&ProcessSpec::new("sh")).is_err());
+    }
+
+    #[test]
+    fn test_sandbox_rejects_inline_code() {
+        let policy = policy(&["sh", "/bin/bash", "python3", "node"], true);
+        let denied = [
+            ProcessSpec::new("sh").args(["-c", "rm -rf ~"]),
+            ProcessSpec::new("/bin/bash").args(["-ec", "whoami"]),
+            ProcessSpec::new("python3").arg("-cprint(1)"),
+            ProcessSpec::new("node").arg("--eval=1"),
+        ];
+        for spec in denied {
+            assert!(policy.apply(&spec).is_err(), "{:?}", spec.args);
+        }
+        assert!(policy
+            .apply(&ProcessSpec::new("sh").args(["-e", "build.sh"]))
+            .is_ok());
+        assert!(policy
+            .apply(&ProcessSpec::new("python3").arg("script.py"))
+            .is_ok());
+
+        // Outside sandbox mode only the allowlist applies
+        assert!(ProcessPolicy {
+            sandbox_mode: false,
+            ..policy
+        }
+        .apply(&ProcessSpec::new("sh").args(["-c", "true"]))
+        .is_ok());
+    }
+
+    #[test]
+    fn test_sandbox_rejects_dangerous_arguments() {
+        let policy = policy(&["echo"], true);
+        assert!(policy
+            .apply(&ProcessSpec::new("echo").arg("$(whoami)"))
+            .is_err());
+        assert!(policy
+            .apply(&ProcessSpec::new("echo").args(["a b", ""]))
+            .is_ok());
+        assert!(ProcessPolicy {
+            sandbox_mode: false,
+            ..policy
+        }
+        .apply(&ProcessSpec::new("echo").arg("$(whoami)"))
+        .is_ok());
+    }
+
+    #[test]
+    fn test_sandbox_confines_working_directory() {
+        let policy = policy(&["ls"], true);
+        let spec = policy.apply(&ProcessSpec::new("ls")).unwrap();
+        assert_eq!(spec.cwd.as_deref(), Some(policy.project_dir()));
+        assert!(policy.apply(&ProcessSpec::new("ls").cwd("/")).is_err());
+    }
+
+    #[test]
+    fn test_limits_from_settings() {
+        let settings = AppSettings::default();
+        let policy = ProcessPolicy::from_settings(&settings, &std::env::temp_dir());
+        let limits = policy
+            .apply(&ProcessSpec::new("ls"))
+            .unwrap()
+            .limits
+            .unwrap();
+
+        assert_eq!(limits.cpu_time, Some(settings.security.max_process_time));
+        assert_eq!(
+            limits.address_space,
+            Some(settings.performance.max_memory_usage as u64)
+        );
+        assert_eq!(limits.open_files, Some(SANDBOX_OPEN_FILES));
+        #[cfg(target_os = "linux")]
+        assert!(limits
+            .processes
+            .is_some_and(|processes| processes > SANDBOX_PROCESS_HEADROOM));
+
+        let mut configured = settings.clone();
+        configured.security.max_process_memory = Some(8 << 30);
+        let policy_with_memory = ProcessPolicy::from_settings(&configured, &std::env::temp_dir());
+        assert_eq!(policy_with_memory.limits().address_space, Some(8 << 30));
+
+        let unsandboxed = ProcessPolicy {
+            sandbox_mode: false,
+            ..policy
+        };
+        assert!(unsandboxed
+            .apply(&ProcessSpec::new("ls"))
+            .unwrap()
+            .limits
+            .is_none());
+    }
+
+    #[test]
+    fn test_secret_env_detection() {
+        assert!(ProcessPolicy::is_secret_env("GITHUB_TOKEN"));
+        assert!(ProcessPolicy::is_secret_env("openai_api_key"));
+        assert!(ProcessPolicy::is_secret_env("AWS_REGION"));
+        assert!(!ProcessPolicy::is_secret_env("PATH"));
+        assert!(!ProcessPolicy::is_secret_env("HOME"));
+    }
+
+    #[test]
+    fn test_sandbox_keeps_explicit_env() {
+        let spec = policy(&["env"], true)
+            .apply(&ProcessSpec::new("env").env("BUILD_TOKEN", "abc"))
+            .unwrap();
+        assert!(spec.env_clear);
+        assert_eq!(spec.env.get("BUILD_TOKEN").map(String::as_str), Some("abc"));
+        assert!(spec
+            .env
+            .keys()
+            .all(|name| name == "BUILD_TOKEN" || !ProcessPolicy::is_secret_env(name)));
+    }
+}
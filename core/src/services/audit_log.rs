@@ -0,0 +1,672 @@
+//! Tamper-evident audit log of tool calls
+//!
+//! Records are appended as JSONL to `.ifm-ruta/audit/audit.jsonl` in the
+//! project directory. Every record carries the hash of the previous one,
+//! so editing or removing a record breaks the chain and is detected by
+//! [`AuditLog::verify`]. Full files are rotated to `audit-<seq>.jsonl`,
+//! named after the first sequence number of the next file.
+//!
+//! Appends hold a lock on the audit directory, so several processes (e.g.
+//! servers of different clients) can share a log without forking the chain.
+//! Records keep the call arguments and result only up to
+//! [`AUDIT_CONTENT_LIMIT`] bytes each; longer content is cut, and the
+//! arguments hash still covers all of it.
+
+use async_trait::async_trait;
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::models::AppError;
+use crate::traits::{Event, EventError, EventListener, EventType};
+
+/// Previous hash of the first record
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Name of the file records are appended to
+const CURRENT_FILE: &str = "audit.jsonl";
+
+/// Name of the file appends are serialized on
+const LOCK_FILE: &str = ".lock";
+
+/// Maximum length of the result summary in characters
+const RESULT_SUMMARY_LENGTH: usize = 200;
+
+/// Maximum length of the arguments and of the result kept in a record, in
+/// bytes
+pub const AUDIT_CONTENT_LIMIT: usize = 4096;
+
+/// Bytes read from the end of a log file to find its last record
+const TAIL_SIZE: u64 = 64 * 1024;
+
+/// Hash a JSON value with SHA-256
+pub fn hash_json(value: &Value) -> String {
+    hex::encode(Sha256::digest(value.to_string().as_bytes()))
+}
+
+/// Shorten a tool result for the audit log
+pub fn summarize_result(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(RESULT_SUMMARY_LENGTH) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Cut a tool result to [`AUDIT_CONTENT_LIMIT`] bytes, marking the cut
+pub fn cap_content(text: &str) -> String {
+    if text.len() <= AUDIT_CONTENT_LIMIT {
+        return text.to_string();
+    }
+    let mut end = AUDIT_CONTENT_LIMIT;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}…", &text[..end])
+}
+
+/// Cap tool arguments to [`AUDIT_CONTENT_LIMIT`] bytes
+///
+/// Arguments too long to keep are replaced by their cut serialization.
+pub fn cap_arguments(arguments: &Value) -> Value {
+    let text = arguments.to_string();
+    if text.len() <= AUDIT_CONTENT_LIMIT {
+        arguments.clone()
+    } else {
+        Value::String(cap_content(&text))
+    }
+}
+
+/// A single audit log record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub seq: u64,
+    pub timestamp: String,
+    pub event_type: EventType,
+    pub tool: Option<String>,
+    pub arguments_hash: Option<String>,
+    /// Arguments of the call, e.g. the prompt shown to the user, capped to
+    /// [`AUDIT_CONTENT_LIMIT`] bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Value>,
+    pub result_summary: Option<String>,
+    /// Result text, e.g. the feedback the user gave, capped to
+    /// [`AUDIT_CONTENT_LIMIT`] bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    pub success: Option<bool>,
+    pub user: String,
+    pub duration_ms: Option<u64>,
+    pub prev_hash: String,
+    #[serde(default)]
+    pub hash: String,
+}
+
+impl AuditRecord {
+    /// Build an unchained record from an event
+    pub fn from_event(event: &Event) -> Self {
+        let data = &event.data;
+        let text = |field: &str| data.get(field).and_then(|v| v.as_str()).map(String::from);
+
+        Self {
+            seq: 0,
+            timestamp: chrono::DateTime::<chrono::Utc>::from(event.timestamp)
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            event_type: event.event_type.clone(),
+            tool: text("tool"),
+            arguments_hash: text("arguments_hash"),
+            arguments: data
+                .get("arguments")
+                .filter(|v| !v.is_null())
+                .map(cap_arguments),
+            result_summary: text("result_summary").or_else(|| text("error")),
+            result: text("result").map(|result| cap_content(&result)),
+            success: data.get("success").and_then(|v| v.as_bool()),
+            user: current_user(),
+            duration_ms: data.get("duration_ms").and_then(|v| v.as_u64()),
+            prev_hash: String::new(),
+            hash: String::new(),
+        }
+    }
+
+    /// Compute the hash of the record, covering every field but the hash
+    pub fn compute_hash(&self) -> String {
+        let unhashed = Self {
+            hash: String::new(),
+            ..self.clone()
+        };
+        hash_json(&serde_json::to_value(unhashed).unwrap_or_default())
+    }
+}
+
+/// Name of the user running the server
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+fn storage_error(context: &str, path: &Path, error: impl std::fmt::Display) -> AppError {
+    AppError::StorageError {
+        message: format!("{} {}: {}", context, path.display(), error),
+    }
+}
+
+/// Where and why verification found the chain broken
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainBreak {
+    pub file: PathBuf,
+    pub line: usize,
+    pub reason: String,
+}
+
+/// Result of verifying an audit log
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditReport {
+    pub files: usize,
+    pub records: u64,
+    /// First break in the chain, if any
+    pub broken: Option<ChainBreak>,
+}
+
+impl AuditReport {
+    /// Whether the whole chain is intact
+    pub fn is_valid(&self) -> bool {
+        self.broken.is_none()
+    }
+}
+
+/// Append-only, hash-chained audit log of a project
+pub struct AuditLog {
+    directory: PathBuf,
+    rotation_size: u64,
+}
+
+impl AuditLog {
+    /// Get the audit directory of a project
+    pub fn audit_dir(project_directory: &Path) -> PathBuf {
+        project_directory.join(".ifm-ruta").join("audit")
+    }
+
+    /// Open the audit log of a project, continuing its existing chain
+    ///
+    /// Files are rotated once they would grow beyond `rotation_size`
+    /// bytes; zero disables rotation.
+    pub fn open(project_directory: &Path, rotation_size: u64) -> Result<Self, AppError> {
+        let directory = Self::audit_dir(project_directory);
+        fs::create_dir_all(&directory)
+            .map_err(|e| storage_error("Failed to create audit directory", &directory, e))?;
+
+        Ok(Self {
+            directory,
+            rotation_size,
+        })
+    }
+
+    /// Get the directory the log is written to
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Chain a record to the log and append it
+    ///
+    /// Blocks while another process or thread appends to the same log.
+    pub fn append(&self, mut record: AuditRecord) -> Result<AuditRecord, AppError> {
+        let lock_path = self.directory.join(LOCK_FILE);
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|e| storage_error("Failed to open audit lock", &lock_path, e))?;
+        lock.lock_exclusive()
+            .map_err(|e| storage_error("Failed to lock audit log", &lock_path, e))?;
+        let appended = self.append_locked(&mut record);
+        let _ = FileExt::unlock(&lock);
+        appended.map(|_| record)
+    }
+
+    /// Append a record while holding the lock of the log
+    fn append_locked(&self, record: &mut AuditRecord) -> Result<(), AppError> {
+        // Other processes may have appended since the last call, so the
+        // chain continues from the last record on disk
+        record.seq = 0;
+        record.prev_hash = GENESIS_HASH.to_string();
+        for file in Self::log_files(&self.directory)?.iter().rev() {
+            if let Some(last) = Self::last_record(file)? {
+                record.seq = last.seq + 1;
+                record.prev_hash = last.hash;
+                break;
+            }
+        }
+        record.hash = record.compute_hash();
+
+        let mut line = serde_json::to_string(record).map_err(|e| AppError::SerializationError {
+            message: format!("Failed to serialize audit record: {}", e),
+        })?;
+        line.push('\n');
+
+        let path = self.directory.join(CURRENT_FILE);
+        self.rotate_if_full(&path, line.len() as u64, record.seq)?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| storage_error("Failed to open audit log", &path, e))?;
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.sync_data())
+            .map_err(|e| storage_error("Failed to write audit log", &path, e))
+    }
+
+    /// Verify the hash chain of a project's audit log
+    pub fn verify(project_directory: &Path) -> Result<AuditReport, AppError> {
+        let directory = Self::audit_dir(project_directory);
+        let files = if directory.exists() {
+            Self::log_files(&directory)?
+        } else {
+            Vec::new()
+        };
+
+        let mut report = AuditReport {
+            files: files.len(),
+            records: 0,
+            broken: None,
+        };
+        let mut expected_hash = GENESIS_HASH.to_string();
+
+        for path in &files {
+            let file =
+                File::open(path).map_err(|e| storage_error("Failed to open audit log", path, e))?;
+            for (index, line) in BufReader::new(file).lines().enumerate() {
+                let line = line.map_err(|e| storage_error("Failed to read audit log", path, e))?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let broken = |reason: String| ChainBreak {
+                    file: path.clone(),
+                    line: index + 1,
+                    reason,
+                };
+
+                let record: AuditRecord = match serde_json::from_str(&line) {
+                    Ok(record) => record,
+                    Err(e) => {
+                        report.broken = Some(broken(format!("Unreadable record: {}", e)));
+                        return Ok(report);
+                    }
+                };
+
+                let reason = if record.seq != report.records {
+                    Some(format!(
+                        "Expected record {} but found {}",
+                        report.records, record.seq
+                    ))
+                } else if record.prev_hash != expected_hash {
+                    Some("Previous hash does not match the preceding record".to_string())
+                } else if record.hash != record.compute_hash() {
+                    Some("Record hash does not match its contents".to_string())
+                } else {
+                    None
+                };
+                if let Some(reason) = reason {
+                    report.broken = Some(broken(reason));
+                    return Ok(report);
+                }
+
+                expected_hash = record.hash;
+                report.records += 1;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// List the log files, oldest first
+    fn log_files(directory: &Path) -> Result<Vec<PathBuf>, AppError> {
+        let entries = fs::read_dir(directory)
+            .map_err(|e| storage_error("Failed to read audit directory", directory, e))?;
+
+        // Rotated files are named after zero-padded sequence numbers, so
+        // they sort chronologically by name
+        let mut rotated: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("audit-") && name.ends_with(".jsonl"))
+            })
+            .collect();
+        rotated.sort();
+
+        let current = directory.join(CURRENT_FILE);
+        if current.exists() {
+            rotated.push(current);
+        }
+        Ok(rotated)
+    }
+
+    /// Read the last record of a log file
+    ///
+    /// Only the end of the file is read, unless the last record is longer.
+    fn last_record(path: &Path) -> Result<Option<AuditRecord>, AppError> {
+        let read_error = |e| storage_error("Failed to read audit log", path, e);
+        let mut file = File::open(path).map_err(read_error)?;
+        let length = file.metadata().map_err(read_error)?.len();
+
+        let mut start = length.saturating_sub(TAIL_SIZE);
+        let line = loop {
+            let mut tail = Vec::new();
+            file.seek(SeekFrom::Start(start))
+                .and_then(|_| file.read_to_end(&mut tail))
+                .map_err(read_error)?;
+            let tail = tail.trim_ascii_end();
+            match tail.iter().rposition(|b| *b == b'\n') {
+                Some(newline) => break tail[newline + 1..].to_vec(),
+                None if start == 0 => break tail.to_vec(),
+                // The last record starts before the tail
+                None => start = 0,
+            }
+        };
+
+        match String::from_utf8_lossy(&line).trim() {
+            "" => Ok(None),
+            line => {
+                serde_json::from_str(line)
+                    .map(Some)
+                    .map_err(|e| AppError::DeserializationError {
+                        message: format!("Corrupt audit record in {}: {}", path.display(), e),
+                    })
+            }
+        }
+    }
+
+    /// Rotate the current file if appending `incoming` bytes would exceed
+    /// the rotation size
+    fn rotate_if_full(&self, path: &Path, incoming: u64, next_seq: u64) -> Result<(), AppError> {
+        if self.rotation_size == 0 {
+            return Ok(());
+        }
+
+        let size = match fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return Ok(()),
+        };
+        if size == 0 || size + incoming <= self.rotation_size {
+            return Ok(());
+        }
+
+        let rotated = self.directory.join(format!("audit-{:020}.jsonl", next_seq));
+        fs::rename(path, &rotated).map_err(|e| storage_error("Failed to rotate audit log", path, e))
+    }
+}
+
+/// Writes tool calls to the audit log of their project
+///
+/// Events without a `project_directory`, or whose project directory does
+/// not exist, are logged to the default directory (usually the directory
+/// the server was started in); audit directories are never created for
+/// paths that are not already directories.
+///
+/// As an event bus listener it misses events when it lags behind, so the
+/// MCP server calls [`AuditListener::record`] directly instead.
+pub struct AuditListener {
+    default_directory: PathBuf,
+    rotation_size: u64,
+    logs: Mutex<HashMap<PathBuf, Arc<AuditLog>>>,
+}
+
+impl AuditListener {
+    /// Create a new audit listener
+    pub fn new(default_directory: PathBuf, rotation_size: u64) -> Self {
+        Self {
+            default_directory,
+            rotation_size,
+            logs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get or open the audit log of a project
+    fn log_for(&self, project_directory: &Path) -> Result<Arc<AuditLog>, AppError> {
+        let mut logs = self.logs.lock().unwrap();
+        if let Some(log) = logs.get(project_directory) {
+            return Ok(log.clone());
+        }
+
+        let log = Arc::new(AuditLog::open(project_directory, self.rotation_size)?);
+        logs.insert(project_directory.to_path_buf(), log.clone());
+        Ok(log)
+    }
+
+    /// Write a tool call event to the audit log of its project
+    pub fn record(&self, event: &Event) -> Result<AuditRecord, AppError> {
+        let project_directory = event
+            .data
+            .get("project_directory")
+            .and_then(|v| v.as_str())
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_dir())
+            .unwrap_or_else(|| self.default_directory.clone());
+
+        self.log_for(&project_directory)?
+            .append(AuditRecord::from_event(event))
+    }
+}
+
+#[async_trait]
+impl EventListener for AuditListener {
+    async fn handle_event(&self, event: &Event) -> Result<(), EventError> {
+        self.record(event)
+            .map(|_| ())
+            .map_err(|e| EventError::HandlingFailed {
+                message: format!("Failed to write audit record: {}", e),
+            })
+    }
+
+    fn listener_id(&self) -> &str {
+        "audit_log"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn project_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("ifm-ruta-audit-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn record(tool: &str) -> AuditRecord {
+        AuditRecord::from_event(&Event::new(
+            EventType::ToolExecuted,
+            "test",
+            json!({
+                "tool": tool,
+                "arguments_hash": hash_json(&json!({"q": tool})),
+                "result_summary": "ok",
+                "success": true,
+                "duration_ms": 5,
+            }),
+        ))
+    }
+
+    #[test]
+    fn test_chain_survives_reopen() {
+        let dir = project_dir();
+        let log = AuditLog::open(&dir, 0).unwrap();
+        let first = log.append(record("a")).unwrap();
+        assert_eq!(first.prev_hash, GENESIS_HASH);
+        drop(log);
+
+        let second = AuditLog::open(&dir, 0)
+            .unwrap()
+            .append(record("b"))
+            .unwrap();
+        assert_eq!(second.seq, 1);
+        assert_eq!(second.prev_hash, first.hash);
+
+        let report = AuditLog::verify(&dir).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.records, 2);
+    }
+
+    #[test]
+    fn test_edit_is_detected() {
+        let dir = project_dir();
+        let log = AuditLog::open(&dir, 0).unwrap();
+        for tool in ["a", "b", "c"] {
+            log.append(record(tool)).unwrap();
+        }
+
+        let path = log.directory().join(CURRENT_FILE);
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(
+            &path,
+            content.replacen("\"tool\":\"b\"", "\"tool\":\"x\"", 1),
+        )
+        .unwrap();
+
+        let broken = AuditLog::verify(&dir).unwrap().broken.unwrap();
+        assert_eq!(broken.line, 2);
+        assert!(broken.reason.contains("hash"));
+    }
+
+    #[test]
+    fn test_removed_record_is_detected() {
+        let dir = project_dir();
+        let log = AuditLog::open(&dir, 0).unwrap();
+        for tool in ["a", "b", "c"] {
+            log.append(record(tool)).unwrap();
+        }
+
+        let path = log.directory().join(CURRENT_FILE);
+        let content = fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = content.lines().collect();
+        fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+
+        assert_eq!(AuditLog::verify(&dir).unwrap().broken.unwrap().line, 2);
+    }
+
+    #[test]
+    fn test_rotation_keeps_chain() {
+        let dir = project_dir();
+        let log = AuditLog::open(&dir, 600).unwrap();
+        for n in 0..10 {
+            log.append(record(&format!("tool-{}", n))).unwrap();
+        }
+
+        let report = AuditLog::verify(&dir).unwrap();
+        assert!(report.files > 1);
+        assert_eq!(report.records, 10);
+        assert!(report.is_valid());
+
+        for file in AuditLog::log_files(log.directory()).unwrap() {
+            assert!(fs::metadata(file).unwrap().len() <= 600);
+        }
+    }
+
+    #[test]
+    fn test_logs_sharing_a_directory_keep_one_chain() {
+        let dir = project_dir();
+        // Stand-ins for the servers of two clients in the same project
+        let first = AuditLog::open(&dir, 0).unwrap();
+        let second = AuditLog::open(&dir, 0).unwrap();
+        for n in 0..3 {
+            first.append(record(&format!("first-{}", n))).unwrap();
+            second.append(record(&format!("second-{}", n))).unwrap();
+        }
+
+        let report = AuditLog::verify(&dir).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.records, 6);
+    }
+
+    #[test]
+    fn test_content_is_capped() {
+        let dir = project_dir();
+        let log = AuditLog::open(&dir, 0).unwrap();
+        let event = Event::new(
+            EventType::ToolExecuted,
+            "test",
+            json!({
+                "tool": "read_file",
+                "arguments": {"content": "é".repeat(AUDIT_CONTENT_LIMIT)},
+                "result": "x".repeat(10 * AUDIT_CONTENT_LIMIT),
+            }),
+        );
+        let appended = log.append(AuditRecord::from_event(&event)).unwrap();
+
+        let arguments = appended.arguments.unwrap();
+        assert!(arguments.as_str().unwrap().len() <= AUDIT_CONTENT_LIMIT + '…'.len_utf8());
+        assert!(appended.result.unwrap().len() <= AUDIT_CONTENT_LIMIT + '…'.len_utf8());
+        assert!(AuditLog::verify(&dir).unwrap().is_valid());
+    }
+
+    #[test]
+    fn test_summarize_result() {
+        assert_eq!(summarize_result("  short  "), "short");
+        let long = "x".repeat(500);
+        assert_eq!(
+            summarize_result(&long).chars().count(),
+            RESULT_SUMMARY_LENGTH + 1
+        );
+    }
+
+    #[test]
+    fn test_feedback_content_is_recorded() {
+        let dir = project_dir();
+        let log = AuditLog::open(&dir, 0).unwrap();
+        let event = Event::new(
+            EventType::ToolExecuted,
+            "test",
+            json!({
+                "tool": "interactive_feedback",
+                "arguments": {"prompt": "Ship it?"},
+                "result_summary": "yes",
+                "result": "{\"interactive_feedback\":\"yes, ship it\"}",
+                "success": true,
+            }),
+        );
+        log.append(AuditRecord::from_event(&event)).unwrap();
+        log.append(record("b")).unwrap();
+
+        let path = log.directory().join(CURRENT_FILE);
+        let content = fs::read_to_string(&path).unwrap();
+        let first: AuditRecord = serde_json::from_str(content.lines().next().unwrap()).unwrap();
+        assert_eq!(first.arguments.unwrap()["prompt"], "Ship it?");
+        assert!(first.result.unwrap().contains("yes, ship it"));
+        // Records without content leave the fields out
+        assert!(!content.lines().nth(1).unwrap().contains("\"arguments\""));
+
+        // The content is covered by the hash chain
+        fs::write(&path, content.replacen("Ship it?", "Delete it?", 1)).unwrap();
+        assert_eq!(AuditLog::verify(&dir).unwrap().broken.unwrap().line, 1);
+    }
+
+    #[tokio::test]
+    async fn test_listener_does_not_create_project_directories() {
+        let default_dir = project_dir();
+        fs::create_dir_all(&default_dir).unwrap();
+        let listener = AuditListener::new(default_dir.clone(), 0);
+
+        let missing = project_dir();
+        let event = Event::new(
+            EventType::ToolExecuted,
+            "test",
+            json!({"tool": "a", "project_directory": missing}),
+        );
+        listener.handle_event(&event).await.unwrap();
+
+        assert!(!missing.exists());
+        assert_eq!(AuditLog::verify(&default_dir).unwrap().records, 1);
+    }
+}
@@ -1,16 +1,69 @@
 //! Conversation storage service for managing user-agent conversation history
 
-use crate::models::AppError;
+use crate::models::{
+    AppError, Feedback, FeedbackMetadata, RetentionPolicy, SessionFormat, VersionedFeedback,
+};
+use crate::services::{
+    append_session_log, read_session_log, render_session_log, session_log_growth,
+    write_session_log, SessionLogRecord,
+};
+use crate::traits::ConversationStore;
+use crate::utils::{is_temp_file, write_atomic};
+use fs2::FileExt;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Temporary files older than this are left over from a crash
+const STALE_TEMP_FILE_AGE: Duration = Duration::from_secs(60);
+
+/// How long to wait for the lock of a session by default
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delay between attempts to take a contended session lock
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(10);
 
 /// Conversation message
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ConversationMessage {
+    /// Stable message id; messages stored without one get `<session>-<index>`
+    #[serde(default)]
+    pub id: String,
     pub role: String, // "user" or "assistant"
     pub content: String,
     pub timestamp: String, // Use string for easier serialization
+    /// How the user responded to this (assistant) message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<ResponseTiming>,
+    /// Complete feedback the user gave on this (assistant) message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feedback: Option<VersionedFeedback>,
+}
+
+/// How long the user took to respond to a feedback request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseTiming {
+    /// Milliseconds until the user started typing, if they did
+    #[serde(default)]
+    pub time_to_first_keystroke_ms: Option<u64>,
+    /// Milliseconds until the user submitted or cancelled
+    pub time_to_submit_ms: u64,
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+impl From<&FeedbackMetadata> for ResponseTiming {
+    fn from(metadata: &FeedbackMetadata) -> Self {
+        Self {
+            time_to_first_keystroke_ms: metadata
+                .time_to_first_keystroke
+                .map(|duration| duration.as_millis() as u64),
+            time_to_submit_ms: metadata.user_interaction_time.as_millis() as u64,
+            cancelled: metadata.cancelled,
+        }
+    }
 }
 
 /// Conversation session
@@ -23,24 +76,400 @@ pub struct ConversationSession {
     pub last_updated: String, // Use string for easier serialization
 }
 
+impl ConversationMessage {
+    /// Create a message with a new id, timestamped now
+    pub fn new(role: &str, content: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            response: None,
+            feedback: None,
+        }
+    }
+
+    /// User message holding the text of feedback, if there is any
+    pub fn from_feedback(feedback: &Feedback) -> Option<Self> {
+        (!feedback.interactive_feedback.is_empty()).then(|| Self {
+            timestamp: feedback.timestamp.to_rfc3339(),
+            ..Self::new("user", &feedback.interactive_feedback)
+        })
+    }
+}
+
+impl ConversationSession {
+    /// Create an empty session
+    pub fn new(session_id: &str, project_directory: PathBuf) -> Self {
+        let now = chrono::Utc::now().to_rfc3339();
+        Self {
+            session_id: session_id.to_string(),
+            project_directory,
+            messages: Vec::new(),
+            created_at: now.clone(),
+            last_updated: now,
+        }
+    }
+
+    /// Attach feedback and its response timing to the latest assistant
+    /// message, and append non-empty feedback as a user message
+    pub fn apply_feedback(&mut self, feedback: &Feedback) -> Result<(), AppError> {
+        let prompt = self
+            .messages
+            .iter_mut()
+            .rev()
+            .find(|message| message.role == "assistant")
+            .ok_or_else(|| AppError::StorageError {
+                message: format!("Session {} has no assistant message", self.session_id),
+            })?;
+        prompt.response = Some(ResponseTiming::from(&feedback.metadata));
+        prompt.feedback = Some(VersionedFeedback::from(feedback.clone()));
+
+        if let Some(message) = ConversationMessage::from_feedback(feedback) {
+            self.messages.push(message);
+        }
+        self.last_updated = chrono::Utc::now().to_rfc3339();
+        Ok(())
+    }
+
+    /// Get the size of the session serialized as JSON
+    pub fn json_size(&self) -> u64 {
+        serde_json::to_vec(self).map_or(0, |json| json.len() as u64)
+    }
+}
+
+/// Get a unique, sanitized file name for an attachment
+///
+/// The name is prefixed with a short unique id so repeated attachments with
+/// the same name never overwrite each other.
+pub fn attachment_file_name(file_name: &str) -> String {
+    let sanitized: String = file_name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let sanitized = sanitized.trim_start_matches('.');
+    let sanitized = if sanitized.is_empty() {
+        "attachment"
+    } else {
+        sanitized
+    };
+
+    let unique_id = uuid::Uuid::new_v4().simple().to_string();
+    format!("{}-{}", &unique_id[..8], sanitized)
+}
+
+/// Render sessions as the plain-text conversation history
+pub fn render_conversations(sessions: &[ConversationSession]) -> String {
+    if sessions.is_empty() {
+        return "No conversation history found for this project.".to_string();
+    }
+
+    let mut history = String::new();
+    history.push_str(&format!(
+        "=== LATEST {} CONVERSATIONS ===\n\n",
+        sessions.len()
+    ));
+
+    for (session_index, session) in sessions.iter().enumerate() {
+        history.push_str(&format!("--- SESSION {} ---\n", session_index + 1));
+        history.push_str(&format!("Session ID: {}\n", session.session_id));
+        history.push_str(&format!("Created: {:?}\n", session.created_at));
+        history.push_str(&format!("Last Updated: {:?}\n", session.last_updated));
+        history.push_str(&format!("Messages: {}\n\n", session.messages.len()));
+
+        for (i, message) in session.messages.iter().enumerate() {
+            history.push_str(&format!("  Message {}:\n", i + 1));
+            history.push_str(&format!("  Role: {}\n", message.role));
+            history.push_str(&format!("  Content: {}\n\n", message.content));
+        }
+
+        history.push('\n');
+    }
+
+    history
+}
+
 /// Storage statistics
 #[derive(Debug, Clone)]
 pub struct StorageStats {
     pub total_sessions: usize,
     pub total_messages: usize,
     pub total_size_bytes: u64,
+    /// Corrupt session files moved aside by recovery
+    pub quarantined_sessions: usize,
+}
+
+/// Session file that could not be read and was moved to the quarantine
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuarantinedSession {
+    pub session_id: String,
+    /// Where the corrupt file now lives
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// Result of a recovery pass
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RecoveryReport {
+    pub quarantined: Vec<QuarantinedSession>,
+    /// Temporary files of interrupted writes that were deleted
+    pub removed_temp_files: usize,
+}
+
+impl RecoveryReport {
+    /// Whether the storage was already consistent
+    pub fn is_clean(&self) -> bool {
+        self.quarantined.is_empty() && self.removed_temp_files == 0
+    }
+}
+
+/// Why the compactor removes a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionReason {
+    MaxAge,
+    MaxSessions,
+    MaxTotalBytes,
+}
+
+/// Session removed by the compactor
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionRemoval {
+    pub session_id: String,
+    pub reason: RetentionReason,
+    /// Size of the session file and its attachments
+    pub bytes: u64,
+}
+
+/// Session whose oldest messages were dropped by the compactor
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionTrim {
+    pub session_id: String,
+    pub removed_messages: usize,
+    pub bytes: u64,
+}
+
+/// What a compaction removed, or would remove in a dry run
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CompactionReport {
+    pub dry_run: bool,
+    pub removed_sessions: Vec<SessionRemoval>,
+    pub trimmed_sessions: Vec<SessionTrim>,
+}
+
+impl CompactionReport {
+    /// Whether nothing was (or would be) removed
+    pub fn is_empty(&self) -> bool {
+        self.removed_sessions.is_empty() && self.trimmed_sessions.is_empty()
+    }
+
+    /// Bytes freed by the compaction
+    pub fn bytes_freed(&self) -> u64 {
+        self.removed_sessions.iter().map(|s| s.bytes).sum::<u64>()
+            + self.trimmed_sessions.iter().map(|s| s.bytes).sum::<u64>()
+    }
+
+    /// Render one line per removed or trimmed session
+    pub fn render(&self) -> String {
+        let verb = if self.dry_run {
+            "Would remove"
+        } else {
+            "Removed"
+        };
+        let mut report = String::new();
+        for removal in &self.removed_sessions {
+            report.push_str(&format!(
+                "{} session {} ({:?}, {} bytes)\n",
+                verb, removal.session_id, removal.reason, removal.bytes
+            ));
+        }
+        for trim in &self.trimmed_sessions {
+            report.push_str(&format!(
+                "{} {} messages from session {} ({} bytes)\n",
+                verb, trim.removed_messages, trim.session_id, trim.bytes
+            ));
+        }
+        report.push_str(&format!(
+            "{} sessions, {} trimmed, {} bytes{}\n",
+            self.removed_sessions.len(),
+            self.trimmed_sessions.len(),
+            self.bytes_freed(),
+            if self.dry_run { " (dry run)" } else { "" }
+        ));
+        report
+    }
+}
+
+/// Decide what a retention policy removes
+///
+/// `sessions` come newest first with their stored size. Sessions older than
+/// `max_age` and those beyond `max_sessions` are removed, the remaining ones
+/// are trimmed to `max_messages_per_session`, and then the oldest sessions
+/// are removed until `max_total_bytes` is met; the most recent session is
+/// never removed for size. `freed` gives the bytes freed by trimming a
+/// session, from the trimmed session and its stored size.
+///
+/// Returns the report and the ids of the sessions to trim.
+pub fn plan_compaction(
+    sessions: Vec<(ConversationSession, u64)>,
+    policy: &RetentionPolicy,
+    dry_run: bool,
+    freed: impl Fn(&ConversationSession, u64) -> u64,
+) -> (CompactionReport, Vec<String>) {
+    let mut report = CompactionReport {
+        dry_run,
+        ..Default::default()
+    };
+    let now = chrono::Utc::now();
+
+    let mut kept: Vec<(ConversationSession, u64)> = Vec::new();
+    for (session, bytes) in sessions {
+        let expired = policy.max_age.is_some_and(|max_age| {
+            chrono::DateTime::parse_from_rfc3339(&session.last_updated)
+                .ok()
+                .and_then(|updated| (now - updated.to_utc()).to_std().ok())
+                .is_some_and(|age| age > max_age)
+        });
+        let reason = if expired {
+            Some(RetentionReason::MaxAge)
+        } else if policy.max_sessions.is_some_and(|max| kept.len() >= max) {
+            Some(RetentionReason::MaxSessions)
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => report.removed_sessions.push(SessionRemoval {
+                session_id: session.session_id,
+                reason,
+                bytes,
+            }),
+            None => kept.push((session, bytes)),
+        }
+    }
+
+    let mut trimmed = Vec::new();
+    if let Some(max) = policy.max_messages_per_session {
+        for (session, bytes) in kept.iter_mut() {
+            let excess = session.messages.len().saturating_sub(max);
+            if excess == 0 {
+                continue;
+            }
+            session.messages.drain(..excess);
+            let freed = freed(session, *bytes);
+            *bytes -= freed.min(*bytes);
+            report.trimmed_sessions.push(SessionTrim {
+                session_id: session.session_id.clone(),
+                removed_messages: excess,
+                bytes: freed,
+            });
+            trimmed.push(session.session_id.clone());
+        }
+    }
+
+    if let Some(max_total) = policy.max_total_bytes {
+        let mut total: u64 = kept.iter().map(|(_, bytes)| bytes).sum();
+        while total > max_total && kept.len() > 1 {
+            let (session, bytes) = kept.pop().unwrap();
+            total -= bytes;
+            // A removed session is no longer reported as trimmed
+            let trimmed_bytes: u64 = report
+                .trimmed_sessions
+                .iter()
+                .filter(|trim| trim.session_id == session.session_id)
+                .map(|trim| trim.bytes)
+                .sum();
+            report
+                .trimmed_sessions
+                .retain(|trim| trim.session_id != session.session_id);
+            trimmed.retain(|id| id != &session.session_id);
+            report.removed_sessions.push(SessionRemoval {
+                session_id: session.session_id,
+                reason: RetentionReason::MaxTotalBytes,
+                bytes: bytes + trimmed_bytes,
+            });
+        }
+    }
+
+    (report, trimmed)
+}
+
+/// Exclusive advisory lock on a session, released when dropped
+///
+/// The lock is held on a file under `conversations/locks`, so it excludes
+/// writers in other processes (the MCP server and the GUI) as well as other
+/// threads.
+pub struct SessionLock {
+    file: fs::File,
+}
+
+impl Drop for SessionLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
 }
 
 /// Conversation storage service
 pub struct ConversationStorage {
     storage_dir: PathBuf,
+    /// Policy enforced on write, if any
+    retention: Option<RetentionPolicy>,
+    lock_timeout: Duration,
+    /// Format of new sessions
+    format: SessionFormat,
 }
 
 impl ConversationStorage {
     /// Create a new conversation storage service
     pub fn new(project_directory: &Path) -> Self {
         let storage_dir = project_directory.join(".ifm-ruta").join("conversations");
-        Self { storage_dir }
+        Self {
+            storage_dir,
+            retention: None,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            format: SessionFormat::Json,
+        }
+    }
+
+    /// Set the format of new sessions
+    ///
+    /// Existing JSON sessions are converted to a JSONL log the next time a
+    /// message is added to them; JSONL logs stay JSONL.
+    pub fn with_format(mut self, format: SessionFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set how long read-modify-write operations wait for a session lock
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// Enforce a retention policy on every write
+    ///
+    /// Messages beyond the per-session limit are dropped when a message is
+    /// added; a JSONL log is only rewritten once it exceeds the limit. The
+    /// other limits are enforced when a new session is created.
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.retention = (!policy.is_unlimited()).then_some(policy);
+        self
+    }
+
+    /// Check that a session id is safe to use as a file name
+    pub fn is_valid_session_id(session_id: &str) -> bool {
+        !session_id.is_empty()
+            && session_id.len() <= 128
+            && !session_id.starts_with('.')
+            && session_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
     }
 
     /// Initialize storage directory
@@ -53,82 +482,393 @@ impl ConversationStorage {
         Ok(())
     }
 
+    /// Take the exclusive lock of a session
+    ///
+    /// `add_message`, `record_feedback` and `compact` take it around their
+    /// read-modify-write; callers doing their own with `load_session` and
+    /// `save_session` should too. Fails once the lock timeout has passed.
+    pub fn lock_session(&self, session_id: &str) -> Result<SessionLock, AppError> {
+        let locks_dir = self.storage_dir.join("locks");
+        fs::create_dir_all(&locks_dir).map_err(|e| AppError::StorageError {
+            message: format!("Failed to create locks directory: {}", e),
+        })?;
+
+        let lock_file = locks_dir.join(format!("{}.lock", session_id));
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_file)
+            .map_err(|e| AppError::StorageError {
+                message: format!("Failed to open session lock: {}", e),
+            })?;
+
+        let deadline = Instant::now() + self.lock_timeout;
+        loop {
+            match file.try_lock_exclusive() {
+                Ok(()) => return Ok(SessionLock { file }),
+                Err(e) if e.kind() == fs2::lock_contended_error().kind() => {
+                    if Instant::now() >= deadline {
+                        return Err(AppError::StorageError {
+                            message: format!(
+                                "Timed out after {:?} waiting for the lock of session {}",
+                                self.lock_timeout, session_id
+                            ),
+                        });
+                    }
+                    std::thread::sleep(LOCK_RETRY_INTERVAL);
+                }
+                Err(e) => {
+                    return Err(AppError::StorageError {
+                        message: format!("Failed to lock session {}: {}", session_id, e),
+                    })
+                }
+            }
+        }
+    }
+
+    fn session_path(&self, session_id: &str, format: SessionFormat) -> PathBuf {
+        self.storage_dir
+            .join(format!("{}.{}", session_id, format.extension()))
+    }
+
+    /// Get the file of a stored session and its format
+    ///
+    /// A JSONL log wins over a JSON file of the same session, which can only
+    /// be left behind by an interrupted conversion.
+    fn session_file(&self, session_id: &str) -> Option<(PathBuf, SessionFormat)> {
+        [SessionFormat::Jsonl, SessionFormat::Json]
+            .into_iter()
+            .map(|format| (self.session_path(session_id, format), format))
+            .find(|(path, _)| path.exists())
+    }
+
+    /// Get the session id and format of a session file
+    fn parse_session_file(path: &Path) -> Option<(String, SessionFormat)> {
+        let format = match path.extension()?.to_str()? {
+            "json" => SessionFormat::Json,
+            "jsonl" => SessionFormat::Jsonl,
+            _ => return None,
+        };
+        Some((path.file_stem()?.to_str()?.to_string(), format))
+    }
+
+    /// List the ids and formats of the stored sessions
+    fn session_files(&self) -> Result<Vec<(String, SessionFormat)>, AppError> {
+        let entries = fs::read_dir(&self.storage_dir).map_err(|e| AppError::StorageError {
+            message: format!("Failed to read storage directory: {}", e),
+        })?;
+
+        let mut files = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| AppError::StorageError {
+                message: format!("Failed to read directory entry: {}", e),
+            })?;
+            tracing::debug!("Found file: {:?}", entry.path());
+            if entry.path().is_file() {
+                files.extend(Self::parse_session_file(&entry.path()));
+            }
+        }
+        Ok(files)
+    }
+
     /// Save a conversation session
+    ///
+    /// The session keeps the format it is stored in; new sessions get the
+    /// configured format.
     pub fn save_session(&self, session: &ConversationSession) -> Result<(), AppError> {
+        let format = self
+            .session_file(&session.session_id)
+            .map_or(self.format, |(_, format)| format);
+        self.write_session(session, format)
+    }
+
+    /// Write a complete session in a format, removing its file in the other
+    /// format if there is one
+    fn write_session(
+        &self,
+        session: &ConversationSession,
+        format: SessionFormat,
+    ) -> Result<(), AppError> {
         self.initialize()?;
 
-        let session_file = self
-            .storage_dir
-            .join(format!("{}.json", session.session_id));
-        let content =
-            serde_json::to_string_pretty(session).map_err(|e| AppError::SerializationError {
-                message: format!("Failed to serialize session: {}", e),
-            })?;
+        let session_file = self.session_path(&session.session_id, format);
+        match format {
+            SessionFormat::Json => {
+                let content = serde_json::to_string_pretty(session).map_err(|e| {
+                    AppError::SerializationError {
+                        message: format!("Failed to serialize session: {}", e),
+                    }
+                })?;
+                write_atomic(&session_file, content.as_bytes()).map_err(|e| {
+                    AppError::StorageError {
+                        message: format!("Failed to write session file: {}", e),
+                    }
+                })?;
+            }
+            SessionFormat::Jsonl => write_session_log(&session_file, session)?,
+        }
 
-        fs::write(&session_file, content).map_err(|e| AppError::StorageError {
-            message: format!("Failed to write session file: {}", e),
-        })?;
+        let other = match format {
+            SessionFormat::Json => SessionFormat::Jsonl,
+            SessionFormat::Jsonl => SessionFormat::Json,
+        };
+        let other_file = self.session_path(&session.session_id, other);
+        if other_file.exists() {
+            fs::remove_file(&other_file).map_err(|e| AppError::StorageError {
+                message: format!("Failed to remove converted session file: {}", e),
+            })?;
+        }
 
         Ok(())
     }
 
-    /// Load a conversation session
-    pub fn load_session(&self, session_id: &str) -> Result<Option<ConversationSession>, AppError> {
-        let session_file = self.storage_dir.join(format!("{}.json", session_id));
+    /// Rewrite the log of a JSONL session with its response records folded
+    /// into their messages, unreadable records dropped and messages beyond
+    /// the per-session limit removed
+    ///
+    /// Returns whether the session has a log to compact.
+    pub fn compact_session_log(&self, session_id: &str) -> Result<bool, AppError> {
+        let _lock = self.lock_session(session_id)?;
+        match self.session_file(session_id) {
+            Some((path, SessionFormat::Jsonl)) => {
+                self.rewrite_session_log(&path)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
 
-        if !session_file.exists() {
-            return Ok(None);
+    /// Compact a session log; the caller holds the session lock
+    fn rewrite_session_log(&self, path: &Path) -> Result<(), AppError> {
+        let mut session = read_session_log(path)?;
+        self.trim_messages(&mut session);
+        write_session_log(path, &session)
+    }
+
+    /// Convert every JSON session to a JSONL log
+    ///
+    /// Returns the number of sessions converted; corrupt files are left in
+    /// place for `recover` to quarantine.
+    pub fn migrate_to_jsonl(&self) -> Result<usize, AppError> {
+        self.initialize()?;
+
+        let mut migrated = 0;
+        for (session_id, format) in self.session_files()? {
+            if format != SessionFormat::Json {
+                continue;
+            }
+            let _lock = self.lock_session(&session_id)?;
+            if let Some(session) = self.load_readable(&session_id)? {
+                self.write_session(&session, SessionFormat::Jsonl)?;
+                migrated += 1;
+            }
         }
+        Ok(migrated)
+    }
 
-        let content = fs::read_to_string(&session_file).map_err(|e| AppError::StorageError {
-            message: format!("Failed to read session file: {}", e),
+    /// Get the directory holding corrupt session files
+    pub fn quarantine_dir(&self) -> PathBuf {
+        self.storage_dir.join("quarantine")
+    }
+
+    /// Move a corrupt session file to the quarantine
+    ///
+    /// The file keeps its name plus the time it was quarantined, so repeated
+    /// corruption of the same session never overwrites earlier evidence.
+    fn quarantine_session(
+        &self,
+        session_id: &str,
+        reason: &str,
+    ) -> Result<QuarantinedSession, AppError> {
+        let quarantine_dir = self.quarantine_dir();
+        fs::create_dir_all(&quarantine_dir).map_err(|e| AppError::StorageError {
+            message: format!("Failed to create quarantine directory: {}", e),
         })?;
 
-        let session: ConversationSession =
-            serde_json::from_str(&content).map_err(|e| AppError::DeserializationError {
-                message: format!("Failed to deserialize session: {}", e),
-            })?;
+        let Some((session_file, format)) = self.session_file(session_id) else {
+            return Err(AppError::StorageError {
+                message: format!("Session not found: {}", session_id),
+            });
+        };
+        let path = quarantine_dir.join(format!(
+            "{}.{}.{}",
+            session_id,
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%.6fZ"),
+            format.extension()
+        ));
+        fs::rename(&session_file, &path).map_err(|e| AppError::StorageError {
+            message: format!("Failed to quarantine session file: {}", e),
+        })?;
 
-        Ok(Some(session))
+        tracing::warn!(
+            "Quarantined corrupt session {} to {}: {}",
+            session_id,
+            path.display(),
+            reason
+        );
+        Ok(QuarantinedSession {
+            session_id: session_id.to_string(),
+            path,
+            reason: reason.to_string(),
+        })
     }
 
-    /// Get all conversation sessions for a project
-    pub fn get_project_sessions(&self) -> Result<Vec<ConversationSession>, AppError> {
-        self.initialize()?;
-
-        let mut sessions = Vec::new();
+    /// Load a session, skipping it with a warning if its file is corrupt
+    fn load_readable(&self, session_id: &str) -> Result<Option<ConversationSession>, AppError> {
+        match self.load_session(session_id) {
+            Err(AppError::DeserializationError { message }) => {
+                tracing::warn!("Skipping corrupt session {}: {}", session_id, message);
+                Ok(None)
+            }
+            result => result,
+        }
+    }
 
-        println!("Looking for conversations in: {:?}", self.storage_dir);
+    /// Load a session, quarantining its file if it is corrupt
+    fn load_or_quarantine(
+        &self,
+        session_id: &str,
+    ) -> Result<(Option<ConversationSession>, Option<QuarantinedSession>), AppError> {
+        match self.load_session(session_id) {
+            Ok(session) => Ok((session, None)),
+            Err(AppError::DeserializationError { message }) => {
+                match self.quarantine_session(session_id, &message) {
+                    Ok(quarantined) => Ok((None, Some(quarantined))),
+                    // Another reader quarantined it first
+                    Err(_) if self.session_file(session_id).is_none() => Ok((None, None)),
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
 
+    /// Detect and quarantine corrupt session files
+    ///
+    /// Also deletes temporary files left by writes that were interrupted by
+    /// a crash. Sessions that load fine are untouched.
+    pub fn recover(&self) -> Result<RecoveryReport, AppError> {
+        let mut report = RecoveryReport::default();
         if !self.storage_dir.exists() {
-            println!("Storage directory does not exist: {:?}", self.storage_dir);
-            return Ok(sessions);
+            return Ok(report);
         }
 
         let entries = fs::read_dir(&self.storage_dir).map_err(|e| AppError::StorageError {
             message: format!("Failed to read storage directory: {}", e),
         })?;
+        for entry in entries {
+            let path = entry
+                .map_err(|e| AppError::StorageError {
+                    message: format!("Failed to read directory entry: {}", e),
+                })?
+                .path();
+
+            if is_temp_file(&path) {
+                let stale = path
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| modified.elapsed().ok())
+                    .is_some_and(|age| age > STALE_TEMP_FILE_AGE);
+                if stale && fs::remove_file(&path).is_ok() {
+                    report.removed_temp_files += 1;
+                }
+            } else if let Some((session_id, _)) = Self::parse_session_file(&path) {
+                if let (_, Some(quarantined)) = self.load_or_quarantine(&session_id)? {
+                    report.quarantined.push(quarantined);
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// List the session files moved to the quarantine, oldest first
+    pub fn quarantined_sessions(&self) -> Result<Vec<PathBuf>, AppError> {
+        let quarantine_dir = self.quarantine_dir();
+        if !quarantine_dir.exists() {
+            return Ok(Vec::new());
+        }
 
+        let entries = fs::read_dir(&quarantine_dir).map_err(|e| AppError::StorageError {
+            message: format!("Failed to read quarantine directory: {}", e),
+        })?;
+        let mut files = Vec::new();
         for entry in entries {
             let entry = entry.map_err(|e| AppError::StorageError {
                 message: format!("Failed to read directory entry: {}", e),
             })?;
+            if entry.path().is_file() {
+                files.push(entry.path());
+            }
+        }
+        files.sort();
 
-            println!("Found file: {:?}", entry.path());
+        Ok(files)
+    }
 
-            if entry.path().extension().and_then(|s| s.to_str()) == Some("json") {
-                let path = entry.path();
-                let session_id = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
-                println!("Loading session: {}", session_id);
+    /// Load a conversation session
+    pub fn load_session(&self, session_id: &str) -> Result<Option<ConversationSession>, AppError> {
+        let Some((session_file, format)) = self.session_file(session_id) else {
+            return Ok(None);
+        };
 
-                if let Ok(session) = self.load_session(session_id) {
-                    if let Some(session) = session {
-                        println!("Loaded session with {} messages", session.messages.len());
-                        sessions.push(session);
+        let mut session = match format {
+            SessionFormat::Json => {
+                let content =
+                    fs::read_to_string(&session_file).map_err(|e| AppError::StorageError {
+                        message: format!("Failed to read session file: {}", e),
+                    })?;
+                serde_json::from_str::<ConversationSession>(&content).map_err(|e| {
+                    AppError::DeserializationError {
+                        message: format!("Failed to deserialize session: {}", e),
                     }
-                } else {
-                    println!("Failed to load session: {}", session_id);
-                }
+                })?
+            }
+            SessionFormat::Jsonl => {
+                // Logs may hold more messages until they are compacted
+                let mut session = read_session_log(&session_file)?;
+                self.trim_messages(&mut session);
+                session
+            }
+        };
+
+        for (index, message) in session.messages.iter_mut().enumerate() {
+            if message.id.is_empty() {
+                message.id = format!("{}-{}", session.session_id, index);
+            }
+        }
+
+        Ok(Some(session))
+    }
+
+    /// Get all conversation sessions for a project
+    ///
+    /// Corrupt session files are skipped and logged; `recover` moves them to
+    /// the quarantine.
+    pub fn get_project_sessions(&self) -> Result<Vec<ConversationSession>, AppError> {
+        self.initialize()?;
+
+        let mut sessions = Vec::new();
+
+        tracing::debug!("Looking for conversations in: {:?}", self.storage_dir);
+
+        if !self.storage_dir.exists() {
+            tracing::debug!("Storage directory does not exist: {:?}", self.storage_dir);
+            return Ok(sessions);
+        }
+
+        let mut seen = HashSet::new();
+        for (session_id, _) in self.session_files()? {
+            if !seen.insert(session_id.clone()) {
+                continue;
+            }
+            tracing::debug!("Loading session: {}", session_id);
+
+            if let Some(session) = self.load_readable(&session_id)? {
+                tracing::debug!("Loaded session with {} messages", session.messages.len());
+                sessions.push(session);
             }
         }
 
@@ -138,53 +878,147 @@ impl ConversationStorage {
         Ok(sessions)
     }
 
-    /// Get latest 5 conversation sessions (keep only 5 most recent)
-    pub fn get_latest_5_sessions(&self) -> Result<Vec<ConversationSession>, AppError> {
+    /// Get the most recently updated sessions, newest first
+    ///
+    /// Like every read, this never deletes anything; older sessions are
+    /// only removed by an explicit cleanup.
+    pub fn get_latest_sessions(&self, limit: usize) -> Result<Vec<ConversationSession>, AppError> {
         let mut sessions = self.get_project_sessions()?;
+        sessions.truncate(limit);
+        Ok(sessions)
+    }
+
+    /// Get latest 5 conversation sessions
+    pub fn get_latest_5_sessions(&self) -> Result<Vec<ConversationSession>, AppError> {
+        self.get_latest_sessions(5)
+    }
+
+    /// Add a message to a conversation session - append to existing or create new
+    ///
+    /// Returns the id of the new message. Fails if the session file is
+    /// corrupt, rather than replacing it; once `recover` has quarantined the
+    /// file, the session starts over.
+    pub fn add_message(
+        &self,
+        session_id: &str,
+        role: &str,
+        content: &str,
+    ) -> Result<String, AppError> {
+        self.initialize()?;
+        let lock = self.lock_session(session_id)?;
 
-        // Keep only the latest 5 sessions
-        if sessions.len() > 5 {
-            // Delete older sessions beyond the 5 most recent
-            let sessions_to_delete = sessions.split_off(5);
-
-            for session in sessions_to_delete {
-                let session_file = self
-                    .storage_dir
-                    .join(format!("{}.json", session.session_id));
-                if session_file.exists() {
-                    let _ = fs::remove_file(&session_file);
-                    // Ignoring removal errors - file may have been deleted
+        let message = ConversationMessage::new(role, content);
+        let id = message.id.clone();
+
+        let is_new = match self.session_file(session_id) {
+            // Only the new message is written to a log. With a message
+            // limit the log is trimmed once it grew by half since it was
+            // last written, so the limit costs a rewrite every so many
+            // messages rather than on every append.
+            Some((path, SessionFormat::Jsonl)) => {
+                append_session_log(&path, &[SessionLogRecord::Message(message)])?;
+                if self.max_messages().is_some() {
+                    let (written, appended) = session_log_growth(&path)?;
+                    if appended > written / 2 {
+                        self.rewrite_session_log(&path)?;
+                    }
                 }
+                false
+            }
+            _ => {
+                let existing = self.load_session(session_id)?;
+                let is_new = existing.is_none();
+                let mut session = existing.unwrap_or_else(|| {
+                    // Create new session only if it doesn't exist
+                    ConversationSession::new(
+                        session_id,
+                        self.storage_dir.parent().unwrap().to_path_buf(),
+                    )
+                });
+
+                session.messages.push(message);
+                session.last_updated = chrono::Utc::now().to_rfc3339();
+                self.trim_messages(&mut session);
+
+                self.write_session(&session, self.format)?;
+                is_new
+            }
+        };
+        drop(lock);
+
+        if let (true, Some(policy)) = (is_new, &self.retention) {
+            match self.compact(policy, false) {
+                Ok(report) if !report.is_empty() => {
+                    tracing::debug!("Compacted conversations:\n{}", report.render())
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Failed to compact conversations: {}", e),
             }
         }
+        Ok(id)
+    }
 
-        Ok(sessions)
+    /// Message limit per session of the retention policy
+    fn max_messages(&self) -> Option<usize> {
+        self.retention
+            .as_ref()
+            .and_then(|policy| policy.max_messages_per_session)
     }
 
-    /// Add a message to a conversation session - append to existing or create new
-    pub fn add_message(&self, session_id: &str, role: &str, content: &str) -> Result<(), AppError> {
-        let mut session = self.load_session(session_id)?.unwrap_or_else(|| {
-            // Create new session only if it doesn't exist
-            ConversationSession {
-                session_id: session_id.to_string(),
-                project_directory: self.storage_dir.parent().unwrap().to_path_buf(),
-                messages: Vec::new(),
-                created_at: chrono::Utc::now().to_rfc3339(),
-                last_updated: chrono::Utc::now().to_rfc3339(),
-            }
-        });
+    /// Drop the oldest messages beyond the per-session limit
+    fn trim_messages(&self, session: &mut ConversationSession) {
+        let excess = self
+            .max_messages()
+            .map_or(0, |max| session.messages.len().saturating_sub(max));
+        session.messages.drain(..excess);
+    }
 
-        let message = ConversationMessage {
-            role: role.to_string(),
-            content: content.to_string(),
-            timestamp: chrono::Utc::now().to_rfc3339(),
-        };
+    /// Get the last `limit` messages of a session, oldest first
+    pub fn recent_messages(
+        &self,
+        session_id: &str,
+        limit: usize,
+    ) -> Result<Vec<ConversationMessage>, AppError> {
+        let mut messages = self
+            .load_session(session_id)?
+            .map(|session| session.messages)
+            .unwrap_or_default();
+        let skip = messages.len().saturating_sub(limit);
+        Ok(messages.split_off(skip))
+    }
 
-        session.messages.push(message);
-        session.last_updated = chrono::Utc::now().to_rfc3339();
+    /// Record the user's feedback on the latest assistant message
+    ///
+    /// The feedback and its response timing are attached to the assistant
+    /// message, so cancelled requests are counted too; non-empty feedback is
+    /// appended as a user message. For JSONL sessions both are appended as
+    /// records, and the response goes to the latest assistant message when
+    /// the log is read.
+    pub fn record_feedback(&self, session_id: &str, feedback: &Feedback) -> Result<(), AppError> {
+        self.initialize()?;
+        let _lock = self.lock_session(session_id)?;
 
-        self.save_session(&session)?;
-        Ok(())
+        if let Some((path, SessionFormat::Jsonl)) = self.session_file(session_id) {
+            let mut records = vec![SessionLogRecord::Response {
+                message_id: None,
+                response: ResponseTiming::from(&feedback.metadata),
+                feedback: Some(VersionedFeedback::from(feedback.clone())),
+                at: chrono::Utc::now().to_rfc3339(),
+            }];
+            records.extend(
+                ConversationMessage::from_feedback(feedback).map(SessionLogRecord::Message),
+            );
+            return append_session_log(&path, &records);
+        }
+
+        let Some(mut session) = self.load_session(session_id)? else {
+            return Err(AppError::StorageError {
+                message: format!("Session not found: {}", session_id),
+            });
+        };
+        session.apply_feedback(feedback)?;
+
+        self.save_session(&session)
     }
 
     /// Get conversation history as formatted string
@@ -217,32 +1051,7 @@ impl ConversationStorage {
 
     /// Get conversation history from latest 5 sessions
     pub fn get_latest_5_conversations(&self) -> Result<String, AppError> {
-        let sessions = self.get_latest_5_sessions()?;
-
-        if sessions.is_empty() {
-            return Ok("No conversation history found for this project.".to_string());
-        }
-
-        let mut history = String::new();
-        history.push_str("=== LATEST 5 CONVERSATIONS ===\n\n");
-
-        for (session_index, session) in sessions.iter().enumerate() {
-            history.push_str(&format!("--- SESSION {} ---\n", session_index + 1));
-            history.push_str(&format!("Session ID: {}\n", session.session_id));
-            history.push_str(&format!("Created: {:?}\n", session.created_at));
-            history.push_str(&format!("Last Updated: {:?}\n", session.last_updated));
-            history.push_str(&format!("Messages: {}\n\n", session.messages.len()));
-
-            for (i, message) in session.messages.iter().enumerate() {
-                history.push_str(&format!("  Message {}:\n", i + 1));
-                history.push_str(&format!("  Role: {}\n", message.role));
-                history.push_str(&format!("  Content: {}\n\n", message.content));
-            }
-
-            history.push('\n');
-        }
-
-        Ok(history)
+        Ok(render_conversations(&self.get_latest_5_sessions()?))
     }
 
     /// Get the latest conversation session
@@ -260,21 +1069,200 @@ impl ConversationStorage {
         // Calculate total storage size
         let mut total_size = 0;
         for session in &sessions {
-            let session_file = self
-                .storage_dir
-                .join(format!("{}.json", session.session_id));
-            if let Ok(metadata) = session_file.metadata() {
-                total_size += metadata.len();
-            }
+            total_size += self.session_file_size(&session.session_id);
         }
 
         Ok(StorageStats {
             total_sessions,
             total_messages,
             total_size_bytes: total_size,
+            quarantined_sessions: self.quarantined_sessions()?.len(),
         })
     }
 
+    /// Get the directory holding attachments for a session
+    pub fn attachments_dir(&self, session_id: &str) -> PathBuf {
+        self.storage_dir.join(format!("{}.attachments", session_id))
+    }
+
+    /// Save an attachment next to a conversation session
+    ///
+    /// The file name is sanitized and prefixed with a short unique id so
+    /// repeated attachments with the same name never overwrite each other.
+    pub fn save_attachment(
+        &self,
+        session_id: &str,
+        file_name: &str,
+        data: &[u8],
+    ) -> Result<PathBuf, AppError> {
+        let attachments_dir = self.attachments_dir(session_id);
+        fs::create_dir_all(&attachments_dir).map_err(|e| AppError::StorageError {
+            message: format!("Failed to create attachments directory: {}", e),
+        })?;
+
+        let attachment_file = attachments_dir.join(attachment_file_name(file_name));
+
+        write_atomic(&attachment_file, data).map_err(|e| AppError::StorageError {
+            message: format!("Failed to write attachment file: {}", e),
+        })?;
+
+        Ok(attachment_file)
+    }
+
+    /// List attachments saved for a session
+    pub fn list_attachments(&self, session_id: &str) -> Result<Vec<PathBuf>, AppError> {
+        let attachments_dir = self.attachments_dir(session_id);
+        if !attachments_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let entries = fs::read_dir(&attachments_dir).map_err(|e| AppError::StorageError {
+            message: format!("Failed to read attachments directory: {}", e),
+        })?;
+
+        let mut attachments = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| AppError::StorageError {
+                message: format!("Failed to read directory entry: {}", e),
+            })?;
+            if entry.path().is_file() {
+                attachments.push(entry.path());
+            }
+        }
+        attachments.sort();
+
+        Ok(attachments)
+    }
+
+    /// Size of the file of a session
+    fn session_file_size(&self, session_id: &str) -> u64 {
+        self.session_file(session_id)
+            .and_then(|(path, _)| path.metadata().ok())
+            .map_or(0, |metadata| metadata.len())
+    }
+
+    /// Size of a session file and its attachments
+    fn session_size(&self, session_id: &str) -> u64 {
+        let file_size = self.session_file_size(session_id);
+        let attachments_size = self
+            .list_attachments(session_id)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|path| path.metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum::<u64>();
+        file_size + attachments_size
+    }
+
+    /// Delete a session file and its attachments
+    fn delete_session_files(&self, session_id: &str) -> Result<(), AppError> {
+        for format in [SessionFormat::Json, SessionFormat::Jsonl] {
+            let session_file = self.session_path(session_id, format);
+            if session_file.exists() {
+                fs::remove_file(&session_file).map_err(|e| AppError::StorageError {
+                    message: format!("Failed to delete session file: {}", e),
+                })?;
+            }
+        }
+        let attachments_dir = self.attachments_dir(session_id);
+        if attachments_dir.exists() {
+            fs::remove_dir_all(&attachments_dir).map_err(|e| AppError::StorageError {
+                message: format!("Failed to delete attachments directory: {}", e),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Enforce a retention policy
+    ///
+    /// See `plan_compaction` for what is removed; sizes are those of the
+    /// session files and their attachments. With `dry_run` nothing is
+    /// changed and the report tells what would have been removed.
+    pub fn compact(
+        &self,
+        policy: &RetentionPolicy,
+        dry_run: bool,
+    ) -> Result<CompactionReport, AppError> {
+        let mut sessions = Vec::new();
+        let mut planned_updates = HashMap::new();
+        for session in self.get_project_sessions()? {
+            let bytes = self.session_size(&session.session_id);
+            planned_updates.insert(session.session_id.clone(), session.last_updated.clone());
+            sessions.push((session, bytes));
+        }
+        let (mut report, trimmed) = plan_compaction(sessions, policy, dry_run, |session, _| {
+            let new_size = match self.session_file(&session.session_id) {
+                Some((_, SessionFormat::Jsonl)) => render_session_log(session).ok(),
+                _ => serde_json::to_string_pretty(session).ok(),
+            }
+            .map_or(0, |content| content.len() as u64);
+            self.session_file_size(&session.session_id)
+                .saturating_sub(new_size)
+        });
+
+        if dry_run {
+            return Ok(report);
+        }
+
+        let mut kept = HashSet::new();
+        for removal in &report.removed_sessions {
+            let _lock = self.lock_session(&removal.session_id)?;
+            // A session written since it was planned for removal is no
+            // longer the one the policy picked, so it is kept
+            let current = self.load_readable(&removal.session_id)?;
+            if current.map(|session| session.last_updated)
+                != planned_updates.get(&removal.session_id).cloned()
+            {
+                tracing::debug!(
+                    "Keeping session {} updated during compaction",
+                    removal.session_id
+                );
+                kept.insert(removal.session_id.clone());
+                continue;
+            }
+            self.delete_session_files(&removal.session_id)?;
+            tracing::debug!(
+                "Removed session {} ({:?})",
+                removal.session_id,
+                removal.reason
+            );
+        }
+        report
+            .removed_sessions
+            .retain(|removal| !kept.contains(&removal.session_id));
+        // Trim again under the lock so messages added meanwhile are kept
+        let max_messages = policy.max_messages_per_session.unwrap_or(usize::MAX);
+        for session_id in &trimmed {
+            self.trim_session(session_id, max_messages)?;
+        }
+
+        Ok(report)
+    }
+
+    /// Drop the oldest messages of a session beyond `max_messages`
+    pub fn trim_session(&self, session_id: &str, max_messages: usize) -> Result<(), AppError> {
+        let _lock = self.lock_session(session_id)?;
+        if let Some(mut session) = self.load_session(session_id)? {
+            let excess = session.messages.len().saturating_sub(max_messages);
+            if excess > 0 {
+                session.messages.drain(..excess);
+                self.save_session(&session)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert JSON sessions to JSONL logs and compact every log
+    ///
+    /// Returns the number of sessions converted.
+    pub fn migrate(&self) -> Result<usize, AppError> {
+        let migrated = self.migrate_to_jsonl()?;
+        for session in self.get_project_sessions()? {
+            self.compact_session_log(&session.session_id)?;
+        }
+        Ok(migrated)
+    }
+
     /// Clean up old sessions (keep only the most recent N sessions)
     pub fn cleanup_old_sessions(&self, keep_count: usize) -> Result<usize, AppError> {
         let mut sessions = self.get_project_sessions()?;
@@ -290,18 +1278,77 @@ impl ConversationStorage {
         let mut deleted_count = 0;
 
         for session in sessions.iter().take(to_delete) {
-            let session_file = self
-                .storage_dir
-                .join(format!("{}.json", session.session_id));
-            if session_file.exists() {
-                fs::remove_file(&session_file).map_err(|e| AppError::StorageError {
-                    message: format!("Failed to delete old session file: {}", e),
-                })?;
+            let _lock = self.lock_session(&session.session_id)?;
+            if self.session_file(&session.session_id).is_some() {
+                self.delete_session_files(&session.session_id)?;
                 deleted_count += 1;
-                println!("Deleted old session: {}", session.session_id);
+                tracing::debug!("Deleted old session: {}", session.session_id);
             }
         }
 
         Ok(deleted_count)
     }
 }
+
+impl ConversationStore for ConversationStorage {
+    fn load_session(&self, session_id: &str) -> Result<Option<ConversationSession>, AppError> {
+        ConversationStorage::load_session(self, session_id)
+    }
+
+    fn save_session(&self, session: &ConversationSession) -> Result<(), AppError> {
+        let _lock = self.lock_session(&session.session_id)?;
+        ConversationStorage::save_session(self, session)
+    }
+
+    fn remove_session(&self, session_id: &str) -> Result<bool, AppError> {
+        let _lock = self.lock_session(session_id)?;
+        let existed = self.session_file(session_id).is_some();
+        self.delete_session_files(session_id)?;
+        Ok(existed)
+    }
+
+    fn list_sessions(&self) -> Result<Vec<ConversationSession>, AppError> {
+        self.get_project_sessions()
+    }
+
+    fn add_message(&self, session_id: &str, role: &str, content: &str) -> Result<String, AppError> {
+        ConversationStorage::add_message(self, session_id, role, content)
+    }
+
+    fn record_feedback(&self, session_id: &str, feedback: &Feedback) -> Result<(), AppError> {
+        ConversationStorage::record_feedback(self, session_id, feedback)
+    }
+
+    fn save_attachment(
+        &self,
+        session_id: &str,
+        file_name: &str,
+        data: &[u8],
+    ) -> Result<PathBuf, AppError> {
+        ConversationStorage::save_attachment(self, session_id, file_name, data)
+    }
+
+    fn trim_session(&self, session_id: &str, max_messages: usize) -> Result<(), AppError> {
+        ConversationStorage::trim_session(self, session_id, max_messages)
+    }
+
+    fn compact(
+        &self,
+        policy: &RetentionPolicy,
+        dry_run: bool,
+    ) -> Result<CompactionReport, AppError> {
+        ConversationStorage::compact(self, policy, dry_run)
+    }
+
+    fn recover(&self) -> Result<RecoveryReport, AppError> {
+        ConversationStorage::recover(self)
+    }
+
+    fn quarantined_sessions(&self) -> Result<Vec<PathBuf>, AppError> {
+        ConversationStorage::quarantined_sessions(self)
+    }
+
+    fn migrate(&self) -> Result<usize, AppError> {
+        ConversationStorage::migrate(self)
+    }
+}
@@ -0,0 +1,192 @@
+//! Conversation store backends
+//!
+//! `open_conversation_store` picks the backend configured in the settings:
+//! the JSON files of `ConversationStorage` or the embedded database of
+//! `DatabaseConversationStore`. `MemoryConversationStore` keeps everything in
+//! memory for tests.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::models::{
+    AppError, ConversationBackend, ConversationSettings, Feedback, RetentionPolicy,
+};
+use crate::services::{
+    attachment_file_name, plan_compaction, CompactionReport, ConversationMessage,
+    ConversationSession, ConversationStorage, DatabaseConversationStore,
+};
+use crate::traits::ConversationStore;
+
+/// Open the conversation store of a project selected by the settings
+///
+/// The retention policy is enforced on write.
+pub fn open_conversation_store(
+    project_directory: &Path,
+    settings: &ConversationSettings,
+    retention: RetentionPolicy,
+) -> Arc<dyn ConversationStore> {
+    match settings.backend {
+        ConversationBackend::Files => Arc::new(
+            ConversationStorage::new(project_directory)
+                .with_format(settings.format)
+                .with_retention(retention),
+        ),
+        ConversationBackend::Database => {
+            Arc::new(DatabaseConversationStore::new(project_directory).with_retention(retention))
+        }
+    }
+}
+
+/// Conversation store keeping everything in memory
+pub struct MemoryConversationStore {
+    project_directory: PathBuf,
+    sessions: Mutex<HashMap<String, ConversationSession>>,
+    attachments: Mutex<HashMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemoryConversationStore {
+    /// Create an empty store
+    pub fn new(project_directory: &Path) -> Self {
+        Self {
+            project_directory: project_directory.to_path_buf(),
+            sessions: Mutex::new(HashMap::new()),
+            attachments: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Directory the attachments of a session would have in the file backend
+    fn attachments_dir(&self, session_id: &str) -> PathBuf {
+        self.project_directory
+            .join(".ifm-ruta")
+            .join("conversations")
+            .join(format!("{}.attachments", session_id))
+    }
+
+    /// Get the contents of a stored attachment
+    pub fn attachment(&self, path: &Path) -> Option<Vec<u8>> {
+        self.attachments.lock().unwrap().get(path).cloned()
+    }
+
+    /// Remove the attachments of a session
+    fn remove_attachments(&self, session_id: &str) {
+        let prefix = self.attachments_dir(session_id);
+        self.attachments
+            .lock()
+            .unwrap()
+            .retain(|path, _| !path.starts_with(&prefix));
+    }
+}
+
+impl ConversationStore for MemoryConversationStore {
+    fn load_session(&self, session_id: &str) -> Result<Option<ConversationSession>, AppError> {
+        Ok(self.sessions.lock().unwrap().get(session_id).cloned())
+    }
+
+    fn save_session(&self, session: &ConversationSession) -> Result<(), AppError> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session.session_id.clone(), session.clone());
+        Ok(())
+    }
+
+    fn remove_session(&self, session_id: &str) -> Result<bool, AppError> {
+        let existed = self.sessions.lock().unwrap().remove(session_id).is_some();
+        self.remove_attachments(session_id);
+        Ok(existed)
+    }
+
+    fn list_sessions(&self) -> Result<Vec<ConversationSession>, AppError> {
+        let mut sessions: Vec<ConversationSession> =
+            self.sessions.lock().unwrap().values().cloned().collect();
+        sessions.sort_by(|a, b| b.last_updated.cmp(&a.last_updated));
+        Ok(sessions)
+    }
+
+    fn add_message(&self, session_id: &str, role: &str, content: &str) -> Result<String, AppError> {
+        let message = ConversationMessage::new(role, content);
+        let id = message.id.clone();
+
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.entry(session_id.to_string()).or_insert_with(|| {
+            ConversationSession::new(session_id, self.project_directory.clone())
+        });
+        session.last_updated = message.timestamp.clone();
+        session.messages.push(message);
+        Ok(id)
+    }
+
+    fn record_feedback(&self, session_id: &str, feedback: &Feedback) -> Result<(), AppError> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::StorageError {
+                message: format!("Session not found: {}", session_id),
+            })?
+            .apply_feedback(feedback)
+    }
+
+    fn save_attachment(
+        &self,
+        session_id: &str,
+        file_name: &str,
+        data: &[u8],
+    ) -> Result<PathBuf, AppError> {
+        let path = self
+            .attachments_dir(session_id)
+            .join(attachment_file_name(file_name));
+        self.attachments
+            .lock()
+            .unwrap()
+            .insert(path.clone(), data.to_vec());
+        Ok(path)
+    }
+
+    fn trim_session(&self, session_id: &str, max_messages: usize) -> Result<(), AppError> {
+        if let Some(session) = self.sessions.lock().unwrap().get_mut(session_id) {
+            let excess = session.messages.len().saturating_sub(max_messages);
+            session.messages.drain(..excess);
+        }
+        Ok(())
+    }
+
+    /// Enforce a retention policy while holding the sessions, with sizes of
+    /// the sessions serialized as JSON
+    fn compact(
+        &self,
+        policy: &RetentionPolicy,
+        dry_run: bool,
+    ) -> Result<CompactionReport, AppError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let mut listed: Vec<ConversationSession> = sessions.values().cloned().collect();
+        listed.sort_by(|a, b| b.last_updated.cmp(&a.last_updated));
+        let listed = listed
+            .into_iter()
+            .map(|session| {
+                let bytes = session.json_size();
+                (session, bytes)
+            })
+            .collect();
+        let (report, trimmed) = plan_compaction(listed, policy, dry_run, |session, bytes| {
+            bytes.saturating_sub(session.json_size())
+        });
+        if dry_run {
+            return Ok(report);
+        }
+
+        for removal in &report.removed_sessions {
+            sessions.remove(&removal.session_id);
+            self.remove_attachments(&removal.session_id);
+        }
+        let max_messages = policy.max_messages_per_session.unwrap_or(usize::MAX);
+        for session_id in &trimmed {
+            if let Some(session) = sessions.get_mut(session_id) {
+                let excess = session.messages.len().saturating_sub(max_messages);
+                session.messages.drain(..excess);
+            }
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,539 @@
+//! Conversation store in a single-file embedded database
+//!
+//! Sessions live in `<project>/.ifm-ruta/conversations.redb`: one table of
+//! session metadata and one of messages keyed by session and sequence
+//! number, so adding a message is a single insert however long the session
+//! is. A store opens the database on first use and keeps it open until it is
+//! dropped. redb locks the file while it is open, so only one process uses
+//! the database at a time: stores must be short-lived (the MCP server opens
+//! one per tool call), and another process waits for the lock timeout
+//! before failing. Projects shared by several clients at once should use
+//! the file backend.
+
+use redb::{Database, DatabaseError, ReadableTable, Table, TableDefinition, TableError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::models::{AppError, Feedback, RetentionPolicy, VersionedFeedback};
+use crate::services::{
+    plan_compaction, CompactionReport, ConversationMessage, ConversationSession,
+    ConversationStorage, ResponseTiming, DEFAULT_LOCK_TIMEOUT,
+};
+use crate::traits::ConversationStore;
+
+/// Session metadata by session id
+const SESSIONS: TableDefinition<&str, &[u8]> = TableDefinition::new("sessions");
+
+/// Messages by session id and sequence number
+const MESSAGES: TableDefinition<(&str, u64), &[u8]> = TableDefinition::new("messages");
+
+/// Delay between attempts to open a database held by another process
+const OPEN_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+type SessionsTable<'txn> = Table<'txn, &'static str, &'static [u8]>;
+type MessagesTable<'txn> = Table<'txn, (&'static str, u64), &'static [u8]>;
+
+/// Stored metadata of a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionRow {
+    project_directory: PathBuf,
+    created_at: String,
+    last_updated: String,
+}
+
+fn storage_error(e: impl std::fmt::Display) -> AppError {
+    AppError::StorageError {
+        message: format!("Conversation database error: {}", e),
+    }
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, AppError> {
+    serde_json::to_vec(value).map_err(|e| AppError::SerializationError {
+        message: format!("Failed to serialize conversation record: {}", e),
+    })
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, AppError> {
+    serde_json::from_slice(bytes).map_err(|e| AppError::DeserializationError {
+        message: format!("Failed to deserialize conversation record: {}", e),
+    })
+}
+
+/// Read a session from the tables of a transaction
+fn read_session(
+    sessions: &impl ReadableTable<&'static str, &'static [u8]>,
+    messages: &impl ReadableTable<(&'static str, u64), &'static [u8]>,
+    session_id: &str,
+) -> Result<Option<ConversationSession>, AppError> {
+    let Some(row) = sessions.get(session_id).map_err(storage_error)? else {
+        return Ok(None);
+    };
+    let row: SessionRow = decode(row.value())?;
+
+    let mut session = ConversationSession {
+        session_id: session_id.to_string(),
+        project_directory: row.project_directory,
+        messages: Vec::new(),
+        created_at: row.created_at,
+        last_updated: row.last_updated,
+    };
+    for entry in messages
+        .range((session_id, 0)..=(session_id, u64::MAX))
+        .map_err(storage_error)?
+    {
+        let (_, value) = entry.map_err(storage_error)?;
+        session.messages.push(decode(value.value())?);
+    }
+    Ok(Some(session))
+}
+
+/// Sequence numbers of the messages of a session
+fn message_keys(messages: &MessagesTable, session_id: &str) -> Result<Vec<u64>, AppError> {
+    messages
+        .range((session_id, 0)..=(session_id, u64::MAX))
+        .map_err(storage_error)?
+        .map(|entry| entry.map(|(key, _)| key.value().1).map_err(storage_error))
+        .collect()
+}
+
+/// Remove the oldest messages of a session beyond `max_messages`
+fn trim_messages(
+    messages: &mut MessagesTable,
+    session_id: &str,
+    max_messages: usize,
+) -> Result<(), AppError> {
+    let keys = message_keys(messages, session_id)?;
+    let excess = keys.len().saturating_sub(max_messages);
+    for seq in &keys[..excess] {
+        messages.remove((session_id, *seq)).map_err(storage_error)?;
+    }
+    Ok(())
+}
+
+/// Store a session in the tables of a transaction, replacing any stored
+/// version
+fn write_session(
+    sessions: &mut SessionsTable,
+    messages: &mut MessagesTable,
+    session: &ConversationSession,
+) -> Result<(), AppError> {
+    let session_id = session.session_id.as_str();
+    for seq in message_keys(messages, session_id)? {
+        messages.remove((session_id, seq)).map_err(storage_error)?;
+    }
+    for (seq, message) in session.messages.iter().enumerate() {
+        messages
+            .insert((session_id, seq as u64), encode(message)?.as_slice())
+            .map_err(storage_error)?;
+    }
+    let row = SessionRow {
+        project_directory: session.project_directory.clone(),
+        created_at: session.created_at.clone(),
+        last_updated: session.last_updated.clone(),
+    };
+    sessions
+        .insert(session_id, encode(&row)?.as_slice())
+        .map_err(storage_error)?;
+    Ok(())
+}
+
+/// Sequence number following the last message of a session
+fn next_seq(messages: &MessagesTable, session_id: &str) -> Result<u64, AppError> {
+    let last = messages
+        .range((session_id, 0)..=(session_id, u64::MAX))
+        .map_err(storage_error)?
+        .next_back()
+        .transpose()
+        .map_err(storage_error)?;
+    Ok(last.map_or(0, |(key, _)| key.value().1 + 1))
+}
+
+/// Conversation store in an embedded database
+pub struct DatabaseConversationStore {
+    path: PathBuf,
+    project_directory: PathBuf,
+    /// Attachments are files next to those of the file backend
+    files: ConversationStorage,
+    lock_timeout: Duration,
+    retention: Option<RetentionPolicy>,
+    /// Database, opened on first use
+    database: OnceLock<Database>,
+    /// Held while opening, as a second open in the same process fails
+    opening: Mutex<()>,
+}
+
+impl DatabaseConversationStore {
+    /// Get the database file of a project
+    pub fn database_path(project_directory: &Path) -> PathBuf {
+        project_directory
+            .join(".ifm-ruta")
+            .join("conversations.redb")
+    }
+
+    /// Create the store of a project; the database is created on first use
+    pub fn new(project_directory: &Path) -> Self {
+        Self {
+            path: Self::database_path(project_directory),
+            project_directory: project_directory.to_path_buf(),
+            files: ConversationStorage::new(project_directory),
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            retention: None,
+            database: OnceLock::new(),
+            opening: Mutex::new(()),
+        }
+    }
+
+    /// Set how long to wait while another process has the database open
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// Enforce a retention policy on every write
+    ///
+    /// Messages beyond the per-session limit are dropped when a message or
+    /// feedback is added; the other limits are enforced when a new session
+    /// is created.
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.retention = (!policy.is_unlimited()).then_some(policy);
+        self
+    }
+
+    /// Get the database, opening it on first use
+    fn database(&self) -> Result<&Database, AppError> {
+        if let Some(database) = self.database.get() {
+            return Ok(database);
+        }
+        let _opening = self.opening.lock().unwrap();
+        if let Some(database) = self.database.get() {
+            return Ok(database);
+        }
+        let database = self.open()?;
+        Ok(self.database.get_or_init(|| database))
+    }
+
+    /// Open the database, waiting while another process has it open
+    fn open(&self) -> Result<Database, AppError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| AppError::StorageError {
+                message: format!("Failed to create storage directory: {}", e),
+            })?;
+        }
+
+        let deadline = Instant::now() + self.lock_timeout;
+        loop {
+            match Database::create(&self.path) {
+                Ok(database) => return Ok(database),
+                Err(DatabaseError::DatabaseAlreadyOpen) if Instant::now() < deadline => {
+                    std::thread::sleep(OPEN_RETRY_INTERVAL)
+                }
+                Err(DatabaseError::DatabaseAlreadyOpen) => {
+                    return Err(AppError::StorageError {
+                        message: format!(
+                            "Timed out after {:?} waiting for {}",
+                            self.lock_timeout,
+                            self.path.display()
+                        ),
+                    })
+                }
+                Err(e) => return Err(storage_error(e)),
+            }
+        }
+    }
+
+    /// Run a function in a read transaction; `None` if nothing was stored yet
+    fn read<T>(
+        &self,
+        f: impl FnOnce(
+            &redb::ReadOnlyTable<&'static str, &'static [u8]>,
+            &redb::ReadOnlyTable<(&'static str, u64), &'static [u8]>,
+        ) -> Result<T, AppError>,
+    ) -> Result<Option<T>, AppError> {
+        let transaction = self.database()?.begin_read().map_err(storage_error)?;
+        let (sessions, messages) = match (
+            transaction.open_table(SESSIONS),
+            transaction.open_table(MESSAGES),
+        ) {
+            (Ok(sessions), Ok(messages)) => (sessions, messages),
+            (Err(TableError::TableDoesNotExist(_)), _)
+            | (_, Err(TableError::TableDoesNotExist(_))) => return Ok(None),
+            (Err(e), _) | (_, Err(e)) => return Err(storage_error(e)),
+        };
+        f(&sessions, &messages).map(Some)
+    }
+
+    /// Delete the attachments directory of a session
+    fn remove_attachments(&self, session_id: &str) -> Result<(), AppError> {
+        let attachments_dir = self.files.attachments_dir(session_id);
+        if attachments_dir.exists() {
+            std::fs::remove_dir_all(&attachments_dir).map_err(|e| AppError::StorageError {
+                message: format!("Failed to delete attachments directory: {}", e),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Run a function in a write transaction, committed if it succeeds
+    fn write<T>(
+        &self,
+        f: impl FnOnce(&mut SessionsTable, &mut MessagesTable) -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        let transaction = self.database()?.begin_write().map_err(storage_error)?;
+        let result = {
+            let mut sessions = transaction.open_table(SESSIONS).map_err(storage_error)?;
+            let mut messages = transaction.open_table(MESSAGES).map_err(storage_error)?;
+            f(&mut sessions, &mut messages)?
+        };
+        transaction.commit().map_err(storage_error)?;
+        Ok(result)
+    }
+}
+
+impl ConversationStore for DatabaseConversationStore {
+    fn load_session(&self, session_id: &str) -> Result<Option<ConversationSession>, AppError> {
+        Ok(self
+            .read(|sessions, messages| read_session(sessions, messages, session_id))?
+            .flatten())
+    }
+
+    fn save_session(&self, session: &ConversationSession) -> Result<(), AppError> {
+        self.write(|sessions, messages| write_session(sessions, messages, session))
+    }
+
+    fn remove_session(&self, session_id: &str) -> Result<bool, AppError> {
+        let existed = self.write(|sessions, messages| {
+            for seq in message_keys(messages, session_id)? {
+                messages.remove((session_id, seq)).map_err(storage_error)?;
+            }
+            Ok(sessions
+                .remove(session_id)
+                .map_err(storage_error)?
+                .is_some())
+        })?;
+
+        self.remove_attachments(session_id)?;
+        Ok(existed)
+    }
+
+    fn list_sessions(&self) -> Result<Vec<ConversationSession>, AppError> {
+        let mut sessions = self
+            .read(|sessions, messages| {
+                let mut loaded = Vec::new();
+                for entry in sessions.iter().map_err(storage_error)? {
+                    let (key, _) = entry.map_err(storage_error)?;
+                    loaded.extend(read_session(sessions, messages, key.value())?);
+                }
+                Ok(loaded)
+            })?
+            .unwrap_or_default();
+        sessions.sort_by(|a, b| b.last_updated.cmp(&a.last_updated));
+        Ok(sessions)
+    }
+
+    fn add_message(&self, session_id: &str, role: &str, content: &str) -> Result<String, AppError> {
+        let message = ConversationMessage::new(role, content);
+        let max_messages = self
+            .retention
+            .as_ref()
+            .and_then(|policy| policy.max_messages_per_session);
+        let (id, is_new) = self.write(|sessions, messages| {
+            let existing = sessions
+                .get(session_id)
+                .map_err(storage_error)?
+                .map(|row| decode::<SessionRow>(row.value()))
+                .transpose()?;
+            let is_new = existing.is_none();
+            let mut row = existing.unwrap_or_else(|| SessionRow {
+                project_directory: self.project_directory.clone(),
+                created_at: message.timestamp.clone(),
+                last_updated: message.timestamp.clone(),
+            });
+            row.last_updated = message.timestamp.clone();
+
+            let next = next_seq(messages, session_id)?;
+            messages
+                .insert((session_id, next), encode(&message)?.as_slice())
+                .map_err(storage_error)?;
+            if let Some(max) = max_messages {
+                trim_messages(messages, session_id, max)?;
+            }
+            sessions
+                .insert(session_id, encode(&row)?.as_slice())
+                .map_err(storage_error)?;
+            Ok((message.id.clone(), is_new))
+        })?;
+
+        if let (true, Some(policy)) = (is_new, &self.retention) {
+            match self.compact(policy, false) {
+                Ok(report) if !report.is_empty() => {
+                    tracing::debug!("Compacted conversations:\n{}", report.render())
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Failed to compact conversations: {}", e),
+            }
+        }
+        Ok(id)
+    }
+
+    fn record_feedback(&self, session_id: &str, feedback: &Feedback) -> Result<(), AppError> {
+        let max_messages = self
+            .retention
+            .as_ref()
+            .and_then(|policy| policy.max_messages_per_session);
+        self.write(|sessions, messages| {
+            let Some(row) = sessions
+                .get(session_id)
+                .map_err(storage_error)?
+                .map(|row| decode::<SessionRow>(row.value()))
+                .transpose()?
+            else {
+                return Err(AppError::StorageError {
+                    message: format!("Session not found: {}", session_id),
+                });
+            };
+
+            // Only the prompt and the new message are written
+            let mut last_seq = None;
+            let mut prompt = None;
+            for entry in messages
+                .range((session_id, 0)..=(session_id, u64::MAX))
+                .map_err(storage_error)?
+                .rev()
+            {
+                let (key, value) = entry.map_err(storage_error)?;
+                let seq = key.value().1;
+                last_seq.get_or_insert(seq);
+                let message: ConversationMessage = decode(value.value())?;
+                if message.role == "assistant" {
+                    prompt = Some((seq, message));
+                    break;
+                }
+            }
+            let Some((prompt_seq, mut prompt)) = prompt else {
+                return Err(AppError::StorageError {
+                    message: format!("Session {} has no assistant message", session_id),
+                });
+            };
+
+            prompt.response = Some(ResponseTiming::from(&feedback.metadata));
+            prompt.feedback = Some(VersionedFeedback::from(feedback.clone()));
+            messages
+                .insert((session_id, prompt_seq), encode(&prompt)?.as_slice())
+                .map_err(storage_error)?;
+            if let Some(message) = ConversationMessage::from_feedback(feedback) {
+                let next = last_seq.map_or(0, |seq| seq + 1);
+                messages
+                    .insert((session_id, next), encode(&message)?.as_slice())
+                    .map_err(storage_error)?;
+            }
+            if let Some(max) = max_messages {
+                trim_messages(messages, session_id, max)?;
+            }
+
+            let row = SessionRow {
+                last_updated: chrono::Utc::now().to_rfc3339(),
+                ..row
+            };
+            sessions
+                .insert(session_id, encode(&row)?.as_slice())
+                .map_err(storage_error)?;
+            Ok(())
+        })
+    }
+
+    fn save_attachment(
+        &self,
+        session_id: &str,
+        file_name: &str,
+        data: &[u8],
+    ) -> Result<PathBuf, AppError> {
+        self.files.save_attachment(session_id, file_name, data)
+    }
+
+    fn trim_session(&self, session_id: &str, max_messages: usize) -> Result<(), AppError> {
+        self.write(|_, messages| trim_messages(messages, session_id, max_messages))
+    }
+
+    /// Enforce a retention policy in a single transaction, with sizes of
+    /// the sessions serialized as JSON
+    fn compact(
+        &self,
+        policy: &RetentionPolicy,
+        dry_run: bool,
+    ) -> Result<CompactionReport, AppError> {
+        let report = self.write(|sessions, messages| {
+            let mut ids = Vec::new();
+            for entry in sessions.iter().map_err(storage_error)? {
+                let (key, _) = entry.map_err(storage_error)?;
+                ids.push(key.value().to_string());
+            }
+            let mut listed = Vec::new();
+            for session_id in &ids {
+                listed.extend(read_session(sessions, messages, session_id)?);
+            }
+            listed.sort_by(|a, b| b.last_updated.cmp(&a.last_updated));
+            let listed = listed
+                .into_iter()
+                .map(|session| {
+                    let bytes = session.json_size();
+                    (session, bytes)
+                })
+                .collect();
+
+            let (report, trimmed) = plan_compaction(listed, policy, dry_run, |session, bytes| {
+                bytes.saturating_sub(session.json_size())
+            });
+            if dry_run {
+                return Ok(report);
+            }
+
+            for removal in &report.removed_sessions {
+                let session_id = removal.session_id.as_str();
+                for seq in message_keys(messages, session_id)? {
+                    messages.remove((session_id, seq)).map_err(storage_error)?;
+                }
+                sessions.remove(session_id).map_err(storage_error)?;
+            }
+            let max_messages = policy.max_messages_per_session.unwrap_or(usize::MAX);
+            for session_id in &trimmed {
+                trim_messages(messages, session_id, max_messages)?;
+            }
+            Ok(report)
+        })?;
+
+        // Attachments are files, removed once the sessions are gone
+        if !dry_run {
+            for removal in &report.removed_sessions {
+                self.remove_attachments(&removal.session_id)?;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Import the sessions of the file backend that are not in the database
+    ///
+    /// The session files are left in place.
+    fn migrate(&self) -> Result<usize, AppError> {
+        let mut imported = 0;
+        for session in self.files.get_project_sessions()? {
+            let stored = self.write(|sessions, messages| {
+                if sessions
+                    .get(session.session_id.as_str())
+                    .map_err(storage_error)?
+                    .is_some()
+                {
+                    return Ok(false);
+                }
+                write_session(sessions, messages, &session)?;
+                Ok(true)
+            })?;
+            if stored {
+                imported += 1;
+            }
+        }
+        Ok(imported)
+    }
+}
@@ -1,18 +1,138 @@
 //! Event bus implementation
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tokio::sync::broadcast;
+use tokio::task::AbortHandle;
 
+use crate::models::DEFAULT_EVENT_HISTORY_SIZE;
 use crate::traits::{Event, EventBus, EventError, EventListener, EventType};
 
-/// Type alias for complex listener map
-type ListenerMap = HashMap<EventType, Vec<Box<dyn EventListener>>>;
+/// Number of events buffered for listeners that fall behind
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Delivery task of a subscribed listener
+struct Subscription {
+    listener_id: String,
+    task: AbortHandle,
+}
+
+/// Type alias for the subscription map
+type SubscriptionMap = HashMap<EventType, Vec<Subscription>>;
+
+/// Ring buffer of the most recent events, with counts of all events
+struct EventHistory {
+    events: VecDeque<Event>,
+    capacity: usize,
+    published: HashMap<EventType, u64>,
+}
+
+impl EventHistory {
+    fn push(&mut self, event: Event) {
+        *self.published.entry(event.event_type.clone()).or_default() += 1;
+        if self.capacity == 0 {
+            return;
+        }
+        while self.events.len() >= self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+}
+
+/// Filter over the event history
+///
+/// All criteria are optional; an empty query matches every event.
+#[derive(Debug, Clone, Default)]
+pub struct EventQuery {
+    pub event_type: Option<EventType>,
+    pub source: Option<String>,
+    /// Only events at or after this time
+    pub since: Option<SystemTime>,
+    /// Only events before this time
+    pub until: Option<SystemTime>,
+    /// Number of matching events to skip, newest first
+    pub offset: usize,
+    /// Maximum number of events to return
+    pub limit: Option<usize>,
+}
+
+impl EventQuery {
+    /// Create a query matching every event
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match events of a type
+    pub fn event_type(mut self, event_type: EventType) -> Self {
+        self.event_type = Some(event_type);
+        self
+    }
+
+    /// Only match events from a source
+    pub fn source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Only match events at or after a time
+    pub fn since(mut self, since: SystemTime) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Only match events before a time
+    pub fn until(mut self, until: SystemTime) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    /// Skip matching events, newest first
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Return at most `limit` events
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Check whether an event matches the filter criteria
+    pub fn matches(&self, event: &Event) -> bool {
+        self.event_type
+            .as_ref()
+            .is_none_or(|event_type| &event.event_type == event_type)
+            && self
+                .source
+                .as_ref()
+                .is_none_or(|source| &event.source == source)
+            && self.since.is_none_or(|since| event.timestamp >= since)
+            && self.until.is_none_or(|until| event.timestamp < until)
+    }
+}
+
+/// A page of query results
+#[derive(Debug, Clone)]
+pub struct EventPage {
+    /// Matching events, newest first
+    pub events: Vec<Event>,
+    /// Number of matching events before pagination
+    pub total: usize,
+}
 
 /// Event bus implementation
-#[allow(clippy::type_complexity, clippy::arc_with_non_send_sync)]
+///
+/// Events are fanned out over a tokio broadcast channel. Every listener
+/// runs in its own task, so a slow or failing listener never delays or
+/// stops delivery to the others. The most recent events are kept in a
+/// bounded history that can be queried.
 pub struct EventBusImpl {
-    listeners: Arc<Mutex<ListenerMap>>,
-    event_queue: Arc<Mutex<Vec<Event>>>,
+    sender: broadcast::Sender<Event>,
+    subscriptions: Arc<Mutex<SubscriptionMap>>,
+    history: Mutex<EventHistory>,
 }
 
 impl Default for EventBusImpl {
@@ -23,45 +143,160 @@ impl Default for EventBusImpl {
 
 impl EventBusImpl {
     /// Create a new event bus
-    #[allow(clippy::arc_with_non_send_sync)]
     pub fn new() -> Self {
+        Self::with_history_capacity(DEFAULT_EVENT_HISTORY_SIZE)
+    }
+
+    /// Create a new event bus keeping the last `capacity` events
+    pub fn with_history_capacity(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
         Self {
-            listeners: Arc::new(Mutex::new(HashMap::new())),
-            event_queue: Arc::new(Mutex::new(Vec::new())),
+            sender,
+            subscriptions: Arc::new(Mutex::new(HashMap::new())),
+            history: Mutex::new(EventHistory {
+                events: VecDeque::with_capacity(capacity.min(EVENT_CHANNEL_CAPACITY)),
+                capacity,
+                published: HashMap::new(),
+            }),
         }
     }
-}
 
-impl EventBus for EventBusImpl {
-    fn subscribe(&mut self, event_type: EventType, listener: Box<dyn EventListener>) {
-        let mut listeners = self.listeners.lock().unwrap();
-        listeners.entry(event_type).or_default().push(listener);
+    /// Change the number of events kept, dropping the oldest ones if needed
+    pub fn set_history_capacity(&self, capacity: usize) {
+        let mut history = self.history.lock().unwrap();
+        history.capacity = capacity;
+        let excess = history.events.len().saturating_sub(capacity);
+        history.events.drain(..excess);
+    }
+
+    /// Get the number of events in the history
+    pub fn history_len(&self) -> usize {
+        self.history.lock().unwrap().events.len()
+    }
+
+    /// Get the number of events published per type since the bus was created
+    pub fn published_counts(&self) -> HashMap<EventType, u64> {
+        self.history.lock().unwrap().published.clone()
     }
 
-    fn unsubscribe(&mut self, event_type: EventType, listener_id: &str) {
-        let mut listeners = self.listeners.lock().unwrap();
-        if let Some(listener_list) = listeners.get_mut(&event_type) {
-            listener_list.retain(|listener| listener.listener_id() != listener_id);
+    /// Query the event history
+    pub fn query(&self, query: &EventQuery) -> EventPage {
+        let history = self.history.lock().unwrap();
+        let matching: Vec<&Event> = history
+            .events
+            .iter()
+            .rev()
+            .filter(|event| query.matches(event))
+            .collect();
+
+        EventPage {
+            total: matching.len(),
+            events: matching
+                .into_iter()
+                .skip(query.offset)
+                .take(query.limit.unwrap_or(usize::MAX))
+                .cloned()
+                .collect(),
         }
     }
 
-    fn publish(&self, event: Event) -> Result<(), EventError> {
-        let listeners = self.listeners.lock().unwrap();
-
-        if let Some(listener_list) = listeners.get(&event.event_type) {
-            for listener in listener_list {
-                if let Err(e) = listener.handle_event(&event) {
-                    return Err(EventError::HandlingFailed {
-                        message: e.to_string(),
-                    });
+    /// Get a receiver for all events published from now on
+    pub fn receiver(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Get the number of subscribed listeners
+    pub fn listener_count(&self) -> usize {
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .values()
+            .map(|subscriptions| subscriptions.len())
+            .sum()
+    }
+}
+
+impl Drop for EventBusImpl {
+    fn drop(&mut self) {
+        if let Ok(subscriptions) = self.subscriptions.lock() {
+            for subscription in subscriptions.values().flatten() {
+                subscription.task.abort();
+            }
+        }
+    }
+}
+
+impl EventBus for EventBusImpl {
+    fn subscribe(
+        &self,
+        event_type: EventType,
+        listener: Arc<dyn EventListener>,
+    ) -> Result<(), EventError> {
+        let runtime =
+            tokio::runtime::Handle::try_current().map_err(|e| EventError::HandlingFailed {
+                message: format!("Subscribing requires a tokio runtime: {}", e),
+            })?;
+
+        let listener_id = listener.listener_id().to_string();
+        let mut receiver = self.sender.subscribe();
+        let filter = event_type.clone();
+
+        let task = runtime.spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if event.event_type == filter => {
+                        if let Err(e) = listener.handle_event(&event).await {
+                            tracing::warn!(
+                                "Event listener {} failed to handle {:?}: {}",
+                                listener.listener_id(),
+                                event.event_type,
+                                e
+                            );
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!(
+                            "Event listener {} fell behind and missed {} events",
+                            listener.listener_id(),
+                            skipped
+                        );
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
                 }
             }
+        });
+
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        subscriptions
+            .entry(event_type)
+            .or_default()
+            .push(Subscription {
+                listener_id,
+                task: task.abort_handle(),
+            });
+
+        Ok(())
+    }
+
+    fn unsubscribe(&self, event_type: EventType, listener_id: &str) {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        if let Some(subscription_list) = subscriptions.get_mut(&event_type) {
+            subscription_list.retain(|subscription| {
+                let keep = subscription.listener_id != listener_id;
+                if !keep {
+                    subscription.task.abort();
+                }
+                keep
+            });
         }
+    }
 
-        // Add to event queue for debugging/logging
-        let mut queue = self.event_queue.lock().unwrap();
-        queue.push(event);
+    fn publish(&self, event: Event) -> Result<(), EventError> {
+        self.history.lock().unwrap().push(event.clone());
 
+        // Sending only fails when nobody is subscribed, which is fine
+        let _ = self.sender.send(event);
         Ok(())
     }
 }
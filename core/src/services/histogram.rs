@@ -0,0 +1,472 @@
+//! Lock-free latency histogram and sliding-window rate counter
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Sub-buckets per power of two; bounds the relative error to 1/16
+const SUB_BUCKET_BITS: u32 = 4;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+
+/// Largest tracked power of two in microseconds (about 12 days)
+const MAX_EXPONENT: u32 = 40;
+
+/// Number of buckets covering 0 to 2^(MAX_EXPONENT + 1) microseconds
+const BUCKET_COUNT: usize = ((MAX_EXPONENT - SUB_BUCKET_BITS + 2) as u64 * SUB_BUCKETS) as usize;
+
+/// Number of one second slots in the rate window
+const WINDOW_SECONDS: u64 = 60;
+
+/// Get the bucket of a value in microseconds
+///
+/// Values below `SUB_BUCKETS` get a bucket each; above that every power of
+/// two is split into `SUB_BUCKETS` equally wide buckets (log-linear, as in
+/// HDR histograms). Values beyond the largest tracked power of two all fall
+/// into the last bucket.
+fn bucket_index(micros: u64) -> usize {
+    if micros < SUB_BUCKETS {
+        return micros as usize;
+    }
+    let exponent = 63 - micros.leading_zeros();
+    if exponent > MAX_EXPONENT {
+        return BUCKET_COUNT - 1;
+    }
+    let sub_bucket = (micros >> (exponent - SUB_BUCKET_BITS)) & (SUB_BUCKETS - 1);
+    (((exponent - SUB_BUCKET_BITS + 1) as u64 * SUB_BUCKETS) + sub_bucket) as usize
+}
+
+/// Get the largest value in microseconds that falls into a bucket
+fn bucket_upper_bound(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let exponent = (index / SUB_BUCKETS) as u32 + SUB_BUCKET_BITS - 1;
+    let sub_bucket = index % SUB_BUCKETS;
+    let width = 1u64 << (exponent - SUB_BUCKET_BITS);
+    (1u64 << exponent) + (sub_bucket + 1) * width - 1
+}
+
+/// Latency histogram with log-linear buckets
+///
+/// Recording is a handful of relaxed atomic operations, so it never blocks
+/// concurrent recorders or readers.
+pub struct LatencyHistogram {
+    buckets: Box<[AtomicU64]>,
+    count: AtomicU64,
+    min_micros: AtomicU64,
+    max_micros: AtomicU64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyHistogram {
+    /// Create an empty histogram
+    pub fn new() -> Self {
+        Self {
+            buckets: (0..BUCKET_COUNT).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            min_micros: AtomicU64::new(u64::MAX),
+            max_micros: AtomicU64::new(0),
+        }
+    }
+
+    /// Record a duration
+    pub fn record(&self, duration: Duration) {
+        let micros = as_micros(duration);
+        self.buckets[bucket_index(micros)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.min_micros.fetch_min(micros, Ordering::Relaxed);
+        self.max_micros.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    /// Add the durations of a snapshot (e.g. restored from disk)
+    pub fn add(&self, snapshot: &HistogramSnapshot) {
+        if snapshot.count == 0 {
+            return;
+        }
+        for (bucket, count) in self.buckets.iter().zip(&snapshot.counts) {
+            if *count > 0 {
+                bucket.fetch_add(*count, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(snapshot.count, Ordering::Relaxed);
+        self.min_micros
+            .fetch_min(snapshot.min_micros, Ordering::Relaxed);
+        self.max_micros
+            .fetch_max(snapshot.max_micros, Ordering::Relaxed);
+    }
+
+    /// Clear all recorded durations
+    pub fn reset(&self) {
+        for bucket in self.buckets.iter() {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.count.store(0, Ordering::Relaxed);
+        self.min_micros.store(u64::MAX, Ordering::Relaxed);
+        self.max_micros.store(0, Ordering::Relaxed);
+    }
+
+    /// Take a consistent-enough copy of the histogram for reporting
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let counts: Vec<u64> = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .collect();
+        let min = self.min_micros.load(Ordering::Relaxed);
+
+        HistogramSnapshot {
+            count: counts.iter().sum(),
+            counts,
+            min_micros: if min == u64::MAX { 0 } else { min },
+            max_micros: self.max_micros.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn as_micros(duration: Duration) -> u64 {
+    duration.as_micros().min(u64::MAX as u128) as u64
+}
+
+/// Point-in-time copy of a latency histogram
+///
+/// Serialized sparsely, as a map from bucket index to count.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "SparseHistogram", from = "SparseHistogram")]
+pub struct HistogramSnapshot {
+    counts: Vec<u64>,
+    count: u64,
+    min_micros: u64,
+    max_micros: u64,
+}
+
+/// Serialized form of a histogram snapshot
+#[derive(Serialize, Deserialize)]
+struct SparseHistogram {
+    #[serde(default)]
+    buckets: BTreeMap<usize, u64>,
+    #[serde(default)]
+    min_micros: u64,
+    #[serde(default)]
+    max_micros: u64,
+}
+
+impl From<HistogramSnapshot> for SparseHistogram {
+    fn from(snapshot: HistogramSnapshot) -> Self {
+        Self {
+            buckets: snapshot
+                .counts
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count > 0)
+                .map(|(index, count)| (index, *count))
+                .collect(),
+            min_micros: snapshot.min_micros,
+            max_micros: snapshot.max_micros,
+        }
+    }
+}
+
+impl From<SparseHistogram> for HistogramSnapshot {
+    fn from(sparse: SparseHistogram) -> Self {
+        let mut snapshot = Self::default();
+        for (index, count) in sparse.buckets {
+            snapshot.counts[index.min(BUCKET_COUNT - 1)] += count;
+            snapshot.count += count;
+        }
+        if snapshot.count > 0 {
+            snapshot.min_micros = sparse.min_micros;
+            snapshot.max_micros = sparse.max_micros;
+        }
+        snapshot
+    }
+}
+
+impl Default for HistogramSnapshot {
+    fn default() -> Self {
+        Self {
+            counts: vec![0; BUCKET_COUNT],
+            count: 0,
+            min_micros: 0,
+            max_micros: 0,
+        }
+    }
+}
+
+impl HistogramSnapshot {
+    /// Record a duration
+    pub fn record(&mut self, duration: Duration) {
+        let micros = as_micros(duration);
+        self.counts[bucket_index(micros)] += 1;
+        self.min_micros = if self.count == 0 {
+            micros
+        } else {
+            self.min_micros.min(micros)
+        };
+        self.max_micros = self.max_micros.max(micros);
+        self.count += 1;
+    }
+
+    /// Number of recorded durations
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Smallest recorded duration
+    pub fn min(&self) -> Duration {
+        Duration::from_micros(self.min_micros)
+    }
+
+    /// Largest recorded duration
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.max_micros)
+    }
+
+    /// Get the duration at a quantile between 0.0 and 1.0
+    ///
+    /// The result is the upper bound of the bucket holding the quantile,
+    /// clamped to the recorded range, so it overestimates by at most 1/16.
+    pub fn quantile(&self, quantile: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let micros = bucket_upper_bound(index).clamp(self.min_micros, self.max_micros);
+                return Duration::from_micros(micros);
+            }
+        }
+        self.max()
+    }
+
+    /// Count the recorded durations of at most `limit`
+    ///
+    /// Exact for limits on bucket boundaries, otherwise the bucket holding
+    /// the limit is counted as a whole.
+    pub fn count_at_most(&self, limit: Duration) -> u64 {
+        let micros = as_micros(limit);
+        let last = bucket_index(micros);
+        self.counts[..=last].iter().sum()
+    }
+
+    /// Add the counts of another snapshot
+    pub fn merge(&mut self, other: &HistogramSnapshot) {
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+        if other.count > 0 {
+            self.min_micros = if self.count == 0 {
+                other.min_micros
+            } else {
+                self.min_micros.min(other.min_micros)
+            };
+            self.max_micros = self.max_micros.max(other.max_micros);
+        }
+        self.count += other.count;
+    }
+}
+
+/// Events per second over a sliding window of the last minute
+///
+/// Each slot holds the count of one second and the second it belongs to;
+/// slots from an earlier lap around the ring are ignored and reused.
+pub struct RateWindow {
+    started_at: Instant,
+    counts: Box<[AtomicU64]>,
+    seconds: Box<[AtomicU64]>,
+}
+
+impl Default for RateWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateWindow {
+    /// Create an empty rate window
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            counts: (0..WINDOW_SECONDS).map(|_| AtomicU64::new(0)).collect(),
+            // Slot `n` starts out owned by second `n`, with a zero count
+            seconds: (0..WINDOW_SECONDS).map(AtomicU64::new).collect(),
+        }
+    }
+
+    /// Length of the window
+    pub fn window(&self) -> Duration {
+        Duration::from_secs(WINDOW_SECONDS)
+    }
+
+    /// Count an event now
+    pub fn record(&self) {
+        self.record_at(self.started_at.elapsed().as_secs());
+    }
+
+    /// Events per second over the window
+    pub fn rate(&self) -> f64 {
+        self.rate_at(self.started_at.elapsed().as_secs())
+    }
+
+    /// Clear the window
+    pub fn reset(&self) {
+        for count in self.counts.iter() {
+            count.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn record_at(&self, second: u64) {
+        let slot = (second % WINDOW_SECONDS) as usize;
+        let owner = self.seconds[slot].load(Ordering::Acquire);
+        if owner != second
+            && self.seconds[slot]
+                .compare_exchange(owner, second, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        {
+            // This recorder claimed the slot for a new second
+            self.counts[slot].store(0, Ordering::Release);
+        }
+        self.counts[slot].fetch_add(1, Ordering::AcqRel);
+    }
+
+    fn rate_at(&self, second: u64) -> f64 {
+        let oldest = (second + 1).saturating_sub(WINDOW_SECONDS);
+        let total: u64 = self
+            .seconds
+            .iter()
+            .zip(self.counts.iter())
+            .filter(|(owner, _)| (oldest..=second).contains(&owner.load(Ordering::Acquire)))
+            .map(|(_, count)| count.load(Ordering::Acquire))
+            .sum();
+        total as f64 / WINDOW_SECONDS as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets_cover_values() {
+        for micros in [0, 1, 15, 16, 17, 31, 32, 1000, 123_456, 10_000_000] {
+            let index = bucket_index(micros);
+            assert!(bucket_upper_bound(index) >= micros, "{}", micros);
+            if index > 0 {
+                assert!(bucket_upper_bound(index - 1) < micros, "{}", micros);
+            }
+        }
+        assert_eq!(
+            bucket_index(bucket_upper_bound(BUCKET_COUNT - 1)),
+            BUCKET_COUNT - 1
+        );
+        for micros in [1 << (MAX_EXPONENT + 1), 3 << (MAX_EXPONENT + 1), u64::MAX] {
+            assert_eq!(bucket_index(micros), BUCKET_COUNT - 1, "{}", micros);
+        }
+    }
+
+    #[test]
+    fn test_quantiles() {
+        let histogram = LatencyHistogram::new();
+        for ms in 1..=100 {
+            histogram.record(Duration::from_millis(ms));
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 100);
+        assert_eq!(snapshot.min(), Duration::from_millis(1));
+        assert_eq!(snapshot.max(), Duration::from_millis(100));
+
+        for (quantile, expected) in [(0.5, 50.0), (0.9, 90.0), (0.99, 99.0)] {
+            let actual = snapshot.quantile(quantile).as_secs_f64() * 1000.0;
+            assert!(
+                actual >= expected && actual <= expected * 1.07,
+                "p{} = {}",
+                quantile,
+                actual
+            );
+        }
+        assert_eq!(snapshot.quantile(1.0), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_long_tail_is_visible() {
+        let histogram = LatencyHistogram::new();
+        for _ in 0..95 {
+            histogram.record(Duration::from_millis(10));
+        }
+        for _ in 0..5 {
+            histogram.record(Duration::from_secs(60));
+        }
+
+        let snapshot = histogram.snapshot();
+        assert!(snapshot.quantile(0.5) < Duration::from_millis(11));
+        assert!(snapshot.quantile(0.99) >= Duration::from_secs(60));
+        assert_eq!(snapshot.count_at_most(Duration::from_secs(1)), 95);
+    }
+
+    #[test]
+    fn test_merge_and_reset() {
+        let a = LatencyHistogram::new();
+        let b = LatencyHistogram::new();
+        a.record(Duration::from_millis(5));
+        b.record(Duration::from_millis(500));
+
+        let mut merged = a.snapshot();
+        merged.merge(&b.snapshot());
+        assert_eq!(merged.count(), 2);
+        assert_eq!(merged.min(), Duration::from_millis(5));
+        assert_eq!(merged.max(), Duration::from_millis(500));
+
+        a.reset();
+        assert_eq!(a.snapshot().count(), 0);
+        assert_eq!(a.snapshot().quantile(0.5), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut snapshot = HistogramSnapshot::default();
+        snapshot.record(Duration::from_millis(3));
+        snapshot.record(Duration::from_secs(90));
+
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(json["buckets"].as_object().unwrap().len(), 2);
+        let restored: HistogramSnapshot = serde_json::from_value(json).unwrap();
+        assert_eq!(restored, snapshot);
+
+        let histogram = LatencyHistogram::new();
+        histogram.record(Duration::from_millis(1));
+        histogram.add(&restored);
+        let merged = histogram.snapshot();
+        assert_eq!(merged.count(), 3);
+        assert_eq!(merged.min(), Duration::from_millis(1));
+        assert_eq!(merged.max(), Duration::from_secs(90));
+    }
+
+    #[test]
+    fn test_rate_window_slides() {
+        let window = RateWindow::new();
+        for second in 100..160 {
+            window.record_at(second);
+            window.record_at(second);
+        }
+        assert!((window.rate_at(159) - 2.0).abs() < f64::EPSILON);
+
+        // Half the window later only the last 30 seconds still count
+        assert!((window.rate_at(189) - 1.0).abs() < f64::EPSILON);
+        assert_eq!(window.rate_at(1000), 0.0);
+
+        // Reused slots start counting from zero
+        window.record_at(1000);
+        assert!((window.rate_at(1000) - 1.0 / 60.0).abs() < f64::EPSILON);
+    }
+}
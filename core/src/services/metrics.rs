@@ -1,45 +1,76 @@
 //! Tool execution metrics and statistics collection
 
-use parking_lot::Mutex;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
+use super::histogram::{HistogramSnapshot, LatencyHistogram, RateWindow};
+
 /// Tool execution metrics
+///
+/// All counters are atomics, so recording never takes a lock.
 #[derive(Clone)]
 pub struct ToolMetrics {
     call_count: Arc<AtomicU64>,
-    total_duration: Arc<Mutex<Duration>>,
+    total_duration_nanos: Arc<AtomicU64>,
     error_count: Arc<AtomicU64>,
+    /// Durations of successful executions
+    latency: Arc<LatencyHistogram>,
+    /// Executions per second, successful or not
+    rate: Arc<RateWindow>,
 }
 
 impl ToolMetrics {
     pub fn new() -> Self {
         Self {
             call_count: Arc::new(AtomicU64::new(0)),
-            total_duration: Arc::new(Mutex::new(Duration::ZERO)),
+            total_duration_nanos: Arc::new(AtomicU64::new(0)),
             error_count: Arc::new(AtomicU64::new(0)),
+            latency: Arc::new(LatencyHistogram::new()),
+            rate: Arc::new(RateWindow::new()),
         }
     }
 
     /// Record successful execution
     pub fn record_success(&self, duration: Duration) {
         self.call_count.fetch_add(1, Ordering::Relaxed);
-        let mut total = self.total_duration.lock();
-        *total += duration;
+        self.total_duration_nanos.fetch_add(
+            duration.as_nanos().min(u64::MAX as u128) as u64,
+            Ordering::Relaxed,
+        );
+        self.latency.record(duration);
+        self.rate.record();
     }
 
-    /// Record failed execution
+    /// Record failed execution whose duration is unknown
     pub fn record_error(&self) {
+        self.record_error_with_duration(Duration::ZERO);
+    }
+
+    /// Record failed execution
+    ///
+    /// The duration counts towards the total and average duration, but not
+    /// towards the latency distribution of successful executions.
+    pub fn record_error_with_duration(&self, duration: Duration) {
         self.call_count.fetch_add(1, Ordering::Relaxed);
         self.error_count.fetch_add(1, Ordering::Relaxed);
+        self.total_duration_nanos.fetch_add(
+            duration.as_nanos().min(u64::MAX as u128) as u64,
+            Ordering::Relaxed,
+        );
+        self.rate.record();
     }
 
     /// Get metrics statistics
     pub fn get_stats(&self) -> MetricsStats {
         let call_count = self.call_count.load(Ordering::Relaxed);
         let error_count = self.error_count.load(Ordering::Relaxed);
-        let total_duration = *self.total_duration.lock();
+        let total_duration = self.total_duration();
+        let latency = self.latency.snapshot();
 
         let success_count = call_count.saturating_sub(error_count);
         let avg_duration = if call_count > 0 {
@@ -54,14 +85,47 @@ impl ToolMetrics {
             success_count,
             total_duration,
             average_duration: avg_duration,
+            min_duration: latency.min(),
+            max_duration: latency.max(),
+            p50_duration: latency.quantile(0.5),
+            p90_duration: latency.quantile(0.9),
+            p99_duration: latency.quantile(0.99),
+            rate_per_second: self.rate.rate(),
+            rate_window: self.rate.window(),
+            latency,
         }
     }
 
+    /// Get the cumulative counters, e.g. to persist them
+    pub fn snapshot(&self) -> ToolMetricsSnapshot {
+        ToolMetricsSnapshot {
+            call_count: self.call_count(),
+            error_count: self.error_count(),
+            total_duration_micros: self.total_duration().as_micros() as u64,
+            latency: self.latency.snapshot(),
+        }
+    }
+
+    /// Add previously recorded counters (e.g. restored from disk)
+    pub fn restore(&self, snapshot: &ToolMetricsSnapshot) {
+        self.call_count
+            .fetch_add(snapshot.call_count, Ordering::Relaxed);
+        self.error_count
+            .fetch_add(snapshot.error_count, Ordering::Relaxed);
+        self.total_duration_nanos.fetch_add(
+            snapshot.total_duration_micros.saturating_mul(1000),
+            Ordering::Relaxed,
+        );
+        self.latency.add(&snapshot.latency);
+    }
+
     /// Reset all metrics
     pub fn reset(&self) {
         self.call_count.store(0, Ordering::Relaxed);
         self.error_count.store(0, Ordering::Relaxed);
-        *self.total_duration.lock() = Duration::ZERO;
+        self.total_duration_nanos.store(0, Ordering::Relaxed);
+        self.latency.reset();
+        self.rate.reset();
     }
 
     /// Get call count
@@ -83,7 +147,7 @@ impl ToolMetrics {
 
     /// Get total duration
     pub fn total_duration(&self) -> Duration {
-        *self.total_duration.lock()
+        Duration::from_nanos(self.total_duration_nanos.load(Ordering::Relaxed))
     }
 }
 
@@ -94,6 +158,9 @@ impl Default for ToolMetrics {
 }
 
 /// Metrics statistics snapshot
+///
+/// Duration statistics cover successful executions; percentiles are
+/// accurate to about 6%.
 #[derive(Debug, Clone)]
 pub struct MetricsStats {
     pub call_count: u64,
@@ -101,6 +168,16 @@ pub struct MetricsStats {
     pub success_count: u64,
     pub total_duration: Duration,
     pub average_duration: Duration,
+    pub min_duration: Duration,
+    pub max_duration: Duration,
+    pub p50_duration: Duration,
+    pub p90_duration: Duration,
+    pub p99_duration: Duration,
+    /// Executions per second over the last `rate_window`
+    pub rate_per_second: f64,
+    pub rate_window: Duration,
+    /// Distribution of the successful execution durations
+    pub latency: HistogramSnapshot,
 }
 
 impl MetricsStats {
@@ -127,6 +204,163 @@ impl MetricsStats {
     pub fn average_duration_ms(&self) -> f64 {
         self.average_duration.as_secs_f64() * 1000.0
     }
+
+    /// Convert to JSON with durations in milliseconds
+    pub fn to_json(&self) -> Value {
+        json!({
+            "call_count": self.call_count,
+            "error_count": self.error_count,
+            "success_count": self.success_count,
+            "error_rate": self.error_rate(),
+            "total_duration_ms": self.total_duration_secs() * 1000.0,
+            "average_duration_ms": self.average_duration_ms(),
+            "min_duration_ms": millis(self.min_duration),
+            "max_duration_ms": millis(self.max_duration),
+            "p50_duration_ms": millis(self.p50_duration),
+            "p90_duration_ms": millis(self.p90_duration),
+            "p99_duration_ms": millis(self.p99_duration),
+            "rate_per_second": self.rate_per_second,
+            "rate_window_secs": self.rate_window.as_secs(),
+        })
+    }
+}
+
+/// Cumulative tool counters that can be persisted and merged
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolMetricsSnapshot {
+    pub call_count: u64,
+    pub error_count: u64,
+    /// Total duration of all executions
+    pub total_duration_micros: u64,
+    /// Distribution of the successful execution durations
+    pub latency: HistogramSnapshot,
+}
+
+impl ToolMetricsSnapshot {
+    /// Record an execution
+    pub fn record(&mut self, duration: Duration, success: bool) {
+        self.call_count += 1;
+        self.total_duration_micros += duration.as_micros() as u64;
+        if success {
+            self.latency.record(duration);
+        } else {
+            self.error_count += 1;
+        }
+    }
+
+    /// Add the counters of another snapshot
+    pub fn merge(&mut self, other: &ToolMetricsSnapshot) {
+        self.call_count += other.call_count;
+        self.error_count += other.error_count;
+        self.total_duration_micros += other.total_duration_micros;
+        self.latency.merge(&other.latency);
+    }
+
+    /// Compute the statistics of the snapshot
+    ///
+    /// Snapshots carry no timing, so the call rate is zero.
+    pub fn stats(&self) -> MetricsStats {
+        let metrics = ToolMetrics::new();
+        metrics.restore(self);
+        let mut stats = metrics.get_stats();
+        stats.rate_per_second = 0.0;
+        stats
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Metrics of every tool, keyed by tool name
+#[derive(Default)]
+pub struct MetricsRegistry {
+    tools: RwLock<HashMap<String, ToolMetrics>>,
+}
+
+impl MetricsRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the metrics of a tool, creating them on first use
+    pub fn tool(&self, name: &str) -> ToolMetrics {
+        if let Some(metrics) = self.tools.read().get(name) {
+            return metrics.clone();
+        }
+        self.tools
+            .write()
+            .entry(name.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Add previously recorded counters per tool (e.g. restored from disk)
+    pub fn restore<'a>(
+        &self,
+        snapshots: impl IntoIterator<Item = (&'a String, &'a ToolMetricsSnapshot)>,
+    ) {
+        for (name, snapshot) in snapshots {
+            self.tool(name).restore(snapshot);
+        }
+    }
+
+    /// Record a tool call
+    pub fn record(&self, name: &str, duration: Duration, success: bool) {
+        let metrics = self.tool(name);
+        if success {
+            metrics.record_success(duration);
+        } else {
+            metrics.record_error_with_duration(duration);
+        }
+    }
+
+    /// Get the statistics of every tool, sorted by tool name
+    pub fn snapshot(&self) -> Vec<(String, MetricsStats)> {
+        let mut stats: Vec<_> = self
+            .tools
+            .read()
+            .iter()
+            .map(|(name, metrics)| (name.clone(), metrics.get_stats()))
+            .collect();
+        stats.sort_by(|(a, _), (b, _)| a.cmp(b));
+        stats
+    }
+
+    /// Render the statistics of every tool as a text table
+    pub fn summary(&self) -> String {
+        let snapshot = self.snapshot();
+        if snapshot.is_empty() {
+            return "No tool calls recorded".to_string();
+        }
+
+        let width = snapshot
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0)
+            .max("TOOL".len());
+        let mut summary = format!(
+            "{:<width$}  {:>6}  {:>6}  {:>7}  {:>10}  {:>10}  {:>10}  {:>10}\n",
+            "TOOL", "CALLS", "ERRORS", "ERROR%", "AVG MS", "P50 MS", "P99 MS", "MAX MS"
+        );
+        for (name, stats) in snapshot {
+            summary.push_str(&format!(
+                "{:<width$}  {:>6}  {:>6}  {:>6.1}%  {:>10.1}  {:>10.1}  {:>10.1}  {:>10.1}\n",
+                name,
+                stats.call_count,
+                stats.error_count,
+                stats.error_rate(),
+                stats.average_duration_ms(),
+                millis(stats.p50_duration),
+                millis(stats.p99_duration),
+                millis(stats.max_duration),
+            ));
+        }
+        summary
+    }
 }
 
 #[cfg(test)]
@@ -155,11 +389,17 @@ mod tests {
     fn test_record_error() {
         let metrics = ToolMetrics::new();
         metrics.record_success(Duration::from_millis(100));
-        metrics.record_error();
+        metrics.record_error_with_duration(Duration::from_millis(300));
 
         assert_eq!(metrics.call_count(), 2);
         assert_eq!(metrics.error_count(), 1);
         assert_eq!(metrics.success_count(), 1);
+
+        // Failed calls count towards the total, not the latency distribution
+        let stats = metrics.get_stats();
+        assert_eq!(stats.total_duration, Duration::from_millis(400));
+        assert_eq!(stats.average_duration, Duration::from_millis(200));
+        assert_eq!(stats.max_duration, Duration::from_millis(100));
     }
 
     #[test]
@@ -249,6 +489,74 @@ mod tests {
         assert!((stats.average_duration_ms() - 750.0).abs() < 1.0);
     }
 
+    #[test]
+    fn test_registry_records_per_tool() {
+        let registry = MetricsRegistry::new();
+        registry.record("b_tool", Duration::from_millis(10), true);
+        registry.record("a_tool", Duration::from_millis(30), true);
+        registry.record("a_tool", Duration::from_millis(5), false);
+
+        let snapshot = registry.snapshot();
+        let names: Vec<_> = snapshot.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["a_tool", "b_tool"]);
+        assert_eq!(snapshot[0].1.call_count, 2);
+        assert_eq!(snapshot[0].1.error_count, 1);
+        assert_eq!(snapshot[0].1.total_duration, Duration::from_millis(35));
+
+        // Handles share the registry's counters
+        registry.tool("b_tool").record_error();
+        assert_eq!(registry.tool("b_tool").error_count(), 1);
+
+        let summary = registry.summary();
+        assert!(summary.starts_with("TOOL"));
+        assert!(summary.contains("a_tool"));
+    }
+
+    #[test]
+    fn test_percentiles_and_rate() {
+        let metrics = ToolMetrics::new();
+        for ms in 1..=100 {
+            metrics.record_success(Duration::from_millis(ms));
+        }
+        metrics.record_error();
+
+        let stats = metrics.get_stats();
+        assert_eq!(stats.min_duration, Duration::from_millis(1));
+        assert_eq!(stats.max_duration, Duration::from_millis(100));
+        assert!(stats.p50_duration >= Duration::from_millis(50));
+        assert!(stats.p50_duration < Duration::from_millis(54));
+        assert!(stats.p99_duration >= Duration::from_millis(99));
+        assert_eq!(stats.latency.count(), 100);
+        assert!((stats.rate_per_second - 101.0 / 60.0).abs() < 1e-9);
+
+        metrics.reset();
+        let stats = metrics.get_stats();
+        assert_eq!(stats.max_duration, Duration::ZERO);
+        assert_eq!(stats.rate_per_second, 0.0);
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let metrics = ToolMetrics::new();
+        metrics.record_success(Duration::from_millis(100));
+        metrics.record_error();
+
+        let mut snapshot = metrics.snapshot();
+        snapshot.record(Duration::from_millis(300), true);
+        assert_eq!(snapshot.stats().total_duration, Duration::from_millis(400));
+
+        let restored = ToolMetrics::new();
+        restored.restore(&snapshot);
+        restored.record_success(Duration::from_millis(200));
+
+        let stats = restored.get_stats();
+        assert_eq!(stats.call_count, 4);
+        assert_eq!(stats.error_count, 1);
+        assert_eq!(stats.total_duration, Duration::from_millis(600));
+        assert_eq!(stats.latency.count(), 3);
+        assert_eq!(stats.max_duration, Duration::from_millis(300));
+    }
+
     #[test]
     fn test_metrics_clone() {
         let metrics = ToolMetrics::new();
@@ -0,0 +1,389 @@
+//! OpenMetrics text exposition of the server metrics
+//!
+//! Metrics are served by an optional local HTTP listener and/or written
+//! periodically to `.ifm-ruta/metrics.prom` for node_exporter's textfile
+//! collector.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{EventBusImpl, MetricsRegistry, MetricsStats, ProcessManagerImpl};
+use crate::traits::ProcessManager;
+
+/// Content type of the OpenMetrics text format
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Upper bounds of the tool duration histogram buckets in seconds
+///
+/// Reaches up to ten minutes, since human-in-the-loop tools wait for a
+/// person to answer.
+const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0,
+];
+
+/// Escape a label value
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Write the metadata of a metric family
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+}
+
+/// Render tool metrics in OpenMetrics text format (without `# EOF`)
+pub fn render_tool_metrics(out: &mut String, tools: &[(String, MetricsStats)]) {
+    if tools.is_empty() {
+        return;
+    }
+
+    family(out, "ifm_ruta_tool_calls", "counter", "Tool calls.");
+    for (tool, stats) in tools {
+        let _ = writeln!(
+            out,
+            "ifm_ruta_tool_calls_total{{tool=\"{}\"}} {}",
+            escape_label(tool),
+            stats.call_count
+        );
+    }
+
+    family(out, "ifm_ruta_tool_errors", "counter", "Failed tool calls.");
+    for (tool, stats) in tools {
+        let _ = writeln!(
+            out,
+            "ifm_ruta_tool_errors_total{{tool=\"{}\"}} {}",
+            escape_label(tool),
+            stats.error_count
+        );
+    }
+
+    family(
+        out,
+        "ifm_ruta_tool_duration_seconds",
+        "histogram",
+        "Duration of successful tool calls.",
+    );
+    for (tool, stats) in tools {
+        let tool = escape_label(tool);
+        for bound in DURATION_BUCKETS {
+            let _ = writeln!(
+                out,
+                "ifm_ruta_tool_duration_seconds_bucket{{tool=\"{}\",le=\"{}\"}} {}",
+                tool,
+                bound,
+                stats.latency.count_at_most(Duration::from_secs_f64(*bound))
+            );
+        }
+        let _ = writeln!(
+            out,
+            "ifm_ruta_tool_duration_seconds_bucket{{tool=\"{}\",le=\"+Inf\"}} {}",
+            tool,
+            stats.latency.count()
+        );
+        let _ = writeln!(
+            out,
+            "ifm_ruta_tool_duration_seconds_sum{{tool=\"{}\"}} {}",
+            tool,
+            stats.total_duration_secs()
+        );
+        let _ = writeln!(
+            out,
+            "ifm_ruta_tool_duration_seconds_count{{tool=\"{}\"}} {}",
+            tool,
+            stats.latency.count()
+        );
+    }
+
+    family(
+        out,
+        "ifm_ruta_tool_call_rate",
+        "gauge",
+        "Tool calls per second over the last minute.",
+    );
+    for (tool, stats) in tools {
+        let _ = writeln!(
+            out,
+            "ifm_ruta_tool_call_rate{{tool=\"{}\"}} {}",
+            escape_label(tool),
+            stats.rate_per_second
+        );
+    }
+}
+
+/// Renders the server metrics for scraping
+pub struct MetricsExporter {
+    metrics: Arc<MetricsRegistry>,
+    event_bus: Option<Arc<EventBusImpl>>,
+    process_manager: Option<Arc<ProcessManagerImpl>>,
+}
+
+impl MetricsExporter {
+    /// Create an exporter for tool metrics
+    pub fn new(metrics: Arc<MetricsRegistry>) -> Self {
+        Self {
+            metrics,
+            event_bus: None,
+            process_manager: None,
+        }
+    }
+
+    /// Also export the number of published events per type
+    pub fn with_event_bus(mut self, event_bus: Arc<EventBusImpl>) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Also export the number of managed processes per status
+    pub fn with_process_manager(mut self, process_manager: Arc<ProcessManagerImpl>) -> Self {
+        self.process_manager = Some(process_manager);
+        self
+    }
+
+    /// Render all metrics in OpenMetrics text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        render_tool_metrics(&mut out, &self.metrics.snapshot());
+
+        if let Some(event_bus) = &self.event_bus {
+            let counts: BTreeMap<String, u64> = event_bus
+                .published_counts()
+                .into_iter()
+                .map(|(event_type, count)| (format!("{:?}", event_type), count))
+                .collect();
+            family(&mut out, "ifm_ruta_events", "counter", "Published events.");
+            for (event_type, count) in counts {
+                let _ = writeln!(
+                    out,
+                    "ifm_ruta_events_total{{type=\"{}\"}} {}",
+                    event_type, count
+                );
+            }
+        }
+
+        if let Some(process_manager) = &self.process_manager {
+            let mut counts: BTreeMap<String, u64> = ["running", "completed", "failed", "killed"]
+                .into_iter()
+                .map(|status| (status.to_string(), 0))
+                .collect();
+            for info in process_manager.list_processes() {
+                *counts.entry(info.handle.status.to_string()).or_default() += 1;
+            }
+            family(
+                &mut out,
+                "ifm_ruta_processes",
+                "gauge",
+                "Managed processes by status.",
+            );
+            for (status, count) in counts {
+                let _ = writeln!(out, "ifm_ruta_processes{{status=\"{}\"}} {}", status, count);
+            }
+        }
+
+        out.push_str("# EOF\n");
+        out
+    }
+
+    /// Get the textfile path of a directory
+    pub fn textfile_path(directory: &Path) -> PathBuf {
+        directory.join(".ifm-ruta").join("metrics.prom")
+    }
+
+    /// Write the metrics to a file
+    ///
+    /// The file is replaced atomically so collectors never read a partial
+    /// exposition.
+    pub fn write_textfile(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp = path.with_extension("prom.tmp");
+        std::fs::write(&temp, self.render())?;
+        std::fs::rename(&temp, path)
+    }
+
+    /// Write the metrics to a file every `interval` on the tokio runtime
+    pub fn spawn_textfile_writer(
+        self: Arc<Self>,
+        path: PathBuf,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let exporter = self.clone();
+                let target = path.clone();
+                let written =
+                    tokio::task::spawn_blocking(move || exporter.write_textfile(&target)).await;
+                if let Ok(Err(e)) = written {
+                    tracing::warn!("Failed to write metrics to {}: {}", path.display(), e);
+                }
+            }
+        })
+    }
+
+    /// Serve `GET /metrics` over HTTP on a background thread
+    ///
+    /// # Returns
+    /// The address the listener is bound to
+    pub fn serve_http(self: Arc<Self>, address: &str) -> std::io::Result<std::net::SocketAddr> {
+        let listener = TcpListener::bind(address)?;
+        let local_address = listener.local_addr()?;
+
+        std::thread::Builder::new()
+            .name("metrics-http".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    match stream {
+                        Ok(stream) => {
+                            if let Err(e) = self.respond(stream) {
+                                tracing::debug!("Metrics request failed: {}", e);
+                            }
+                        }
+                        Err(e) => tracing::warn!("Failed to accept metrics connection: {}", e),
+                    }
+                }
+            })?;
+
+        Ok(local_address)
+    }
+
+    /// Answer a single HTTP request
+    fn respond(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // Skip the headers, requests have no body
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 2 {
+            header.clear();
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let (status, content_type, body) = match (parts.next(), parts.next()) {
+            (Some("GET"), Some("/metrics")) => ("200 OK", OPENMETRICS_CONTENT_TYPE, self.render()),
+            (Some("GET"), _) => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+            _ => (
+                "405 Method Not Allowed",
+                "text/plain",
+                "Method not allowed\n".to_string(),
+            ),
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        )?;
+        stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::{Event, EventBus, EventType};
+    use std::io::Read;
+
+    fn exporter() -> MetricsExporter {
+        let metrics = Arc::new(MetricsRegistry::new());
+        metrics.record("interactive_feedback", Duration::from_secs(45), true);
+        metrics.record("interactive_feedback", Duration::from_millis(20), true);
+        metrics.record("run_\"quoted\"", Duration::from_millis(20), false);
+
+        let event_bus = Arc::new(EventBusImpl::new());
+        event_bus
+            .publish(Event::new(
+                EventType::ToolExecuted,
+                "test",
+                serde_json::json!({}),
+            ))
+            .unwrap();
+
+        MetricsExporter::new(metrics)
+            .with_event_bus(event_bus)
+            .with_process_manager(Arc::new(ProcessManagerImpl::new()))
+    }
+
+    #[test]
+    fn test_render_openmetrics() {
+        let text = exporter().render();
+
+        assert!(text.ends_with("# EOF\n"));
+        assert!(text.contains("ifm_ruta_tool_calls_total{tool=\"interactive_feedback\"} 2"));
+        assert!(text.contains("ifm_ruta_tool_errors_total{tool=\"run_\\\"quoted\\\"\"} 1"));
+        assert!(text.contains(
+            "ifm_ruta_tool_duration_seconds_bucket{tool=\"interactive_feedback\",le=\"0.025\"} 1"
+        ));
+        assert!(text.contains(
+            "ifm_ruta_tool_duration_seconds_bucket{tool=\"interactive_feedback\",le=\"60\"} 2"
+        ));
+        assert!(
+            text.contains("ifm_ruta_tool_duration_seconds_count{tool=\"interactive_feedback\"} 2")
+        );
+        assert!(text.contains("ifm_ruta_events_total{type=\"ToolExecuted\"} 1"));
+        assert!(text.contains("ifm_ruta_processes{status=\"running\"} 0"));
+
+        // Every sample belongs to a declared family
+        let families: Vec<&str> = text
+            .lines()
+            .filter_map(|line| line.strip_prefix("# TYPE "))
+            .filter_map(|line| line.split_whitespace().next())
+            .collect();
+        for sample in text.lines().filter(|line| !line.starts_with('#')) {
+            assert!(
+                families.iter().any(|family| sample.starts_with(family)),
+                "{}",
+                sample
+            );
+        }
+    }
+
+    #[test]
+    fn test_write_textfile() {
+        let dir = std::env::temp_dir().join(format!("ifm-ruta-metrics-{}", uuid::Uuid::new_v4()));
+        let path = MetricsExporter::textfile_path(&dir);
+
+        exporter().write_textfile(&path).unwrap();
+
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("ifm_ruta_tool_calls_total"));
+        assert!(!path.with_extension("prom.tmp").exists());
+    }
+
+    #[test]
+    fn test_serve_http() {
+        let address = Arc::new(exporter()).serve_http("127.0.0.1:0").unwrap();
+
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let response = get("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains(OPENMETRICS_CONTENT_TYPE));
+        assert!(response.ends_with("# EOF\n"));
+
+        assert!(get("/").starts_with("HTTP/1.1 404"));
+    }
+}
@@ -0,0 +1,448 @@
+//! Per-project metrics persistence with daily rollups
+//!
+//! Tool metrics are kept in one file per local day under
+//! `<project>/.ifm-ruta/metrics/YYYY-MM-DD.json`, so counters survive server
+//! restarts and questions like "average human response time this week" can
+//! be answered by merging the days of interest.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::models::AppError;
+use crate::services::{MetricsRegistry, ToolMetricsSnapshot};
+use crate::traits::{Event, EventError, EventListener};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Tool metrics recorded on one day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyRollup {
+    pub date: NaiveDate,
+    #[serde(default)]
+    pub tools: BTreeMap<String, ToolMetricsSnapshot>,
+}
+
+impl DailyRollup {
+    /// Create an empty rollup
+    pub fn new(date: NaiveDate) -> Self {
+        Self {
+            date,
+            tools: BTreeMap::new(),
+        }
+    }
+
+    /// Merge the per-tool counters of several rollups
+    pub fn merge<'a>(
+        rollups: impl IntoIterator<Item = &'a DailyRollup>,
+    ) -> BTreeMap<String, ToolMetricsSnapshot> {
+        let mut tools: BTreeMap<String, ToolMetricsSnapshot> = BTreeMap::new();
+        for rollup in rollups {
+            for (name, snapshot) in &rollup.tools {
+                tools.entry(name.clone()).or_default().merge(snapshot);
+            }
+        }
+        tools
+    }
+}
+
+/// Daily metrics rollups of a project
+pub struct MetricsStore {
+    directory: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl MetricsStore {
+    /// Get the metrics directory of a project
+    pub fn metrics_dir(project_directory: &Path) -> PathBuf {
+        project_directory.join(".ifm-ruta").join("metrics")
+    }
+
+    /// Open the metrics store of a project; the directory is created on the
+    /// first write
+    pub fn open(project_directory: &Path) -> Self {
+        Self {
+            directory: Self::metrics_dir(project_directory),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Directory holding the rollup files
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    fn day_path(&self, date: NaiveDate) -> PathBuf {
+        self.directory
+            .join(format!("{}.json", date.format(DATE_FORMAT)))
+    }
+
+    /// Record a tool call in the rollup of its day
+    pub fn record(
+        &self,
+        tool: &str,
+        duration: Duration,
+        success: bool,
+        at: DateTime<Local>,
+    ) -> Result<(), AppError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut rollup = self.load_day(at.date_naive())?;
+        rollup
+            .tools
+            .entry(tool.to_string())
+            .or_default()
+            .record(duration, success);
+        self.write_day(&rollup)
+    }
+
+    /// Load the rollup of a day, empty if nothing was recorded
+    pub fn load_day(&self, date: NaiveDate) -> Result<DailyRollup, AppError> {
+        let path = self.day_path(date);
+        if !path.exists() {
+            return Ok(DailyRollup::new(date));
+        }
+
+        let content = std::fs::read_to_string(&path).map_err(|e| AppError::StorageError {
+            message: format!("Failed to read {}: {}", path.display(), e),
+        })?;
+        serde_json::from_str(&content).map_err(|e| AppError::DeserializationError {
+            message: format!("Failed to parse {}: {}", path.display(), e),
+        })
+    }
+
+    /// Replace the rollup file of a day atomically
+    fn write_day(&self, rollup: &DailyRollup) -> Result<(), AppError> {
+        std::fs::create_dir_all(&self.directory).map_err(|e| AppError::StorageError {
+            message: format!("Failed to create {}: {}", self.directory.display(), e),
+        })?;
+
+        let path = self.day_path(rollup.date);
+        let content =
+            serde_json::to_string_pretty(rollup).map_err(|e| AppError::SerializationError {
+                message: e.to_string(),
+            })?;
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, content)
+            .and_then(|_| std::fs::rename(&temp, &path))
+            .map_err(|e| AppError::StorageError {
+                message: format!("Failed to write {}: {}", path.display(), e),
+            })
+    }
+
+    /// List the days with a rollup, oldest first
+    pub fn days(&self) -> Result<Vec<NaiveDate>, AppError> {
+        let entries = match std::fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(AppError::StorageError {
+                    message: format!("Failed to read {}: {}", self.directory.display(), e),
+                })
+            }
+        };
+
+        let mut days: Vec<NaiveDate> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let stem = name.strip_suffix(".json")?;
+                NaiveDate::parse_from_str(stem, DATE_FORMAT).ok()
+            })
+            .collect();
+        days.sort();
+        Ok(days)
+    }
+
+    /// Load the rollups of the days between `from` and `to`, inclusive
+    pub fn range(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<DailyRollup>, AppError> {
+        self.days()?
+            .into_iter()
+            .filter(|day| (from..=to).contains(day))
+            .map(|day| self.load_day(day))
+            .collect()
+    }
+
+    /// Load the rollups of the last `days` days, including today
+    pub fn last_days(&self, days: u32) -> Result<Vec<DailyRollup>, AppError> {
+        let today = Local::now().date_naive();
+        let from = today
+            .checked_sub_days(chrono::Days::new(days.saturating_sub(1) as u64))
+            .unwrap_or(NaiveDate::MIN);
+        self.range(from, today)
+    }
+
+    /// Merge all rollups into per-tool totals
+    pub fn totals(&self) -> Result<BTreeMap<String, ToolMetricsSnapshot>, AppError> {
+        let rollups = self
+            .days()?
+            .into_iter()
+            .map(|day| self.load_day(day))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(DailyRollup::merge(&rollups))
+    }
+}
+
+/// Event listener that persists `ToolExecuted` events into the metrics store
+/// of the project they belong to
+pub struct MetricsStoreListener {
+    default_directory: PathBuf,
+    stores: Mutex<HashMap<PathBuf, Arc<MetricsStore>>>,
+    /// Live counters continued with the persisted totals of each project
+    registry: Option<Arc<MetricsRegistry>>,
+}
+
+impl MetricsStoreListener {
+    /// Create a new metrics store listener
+    pub fn new(default_directory: PathBuf) -> Self {
+        Self {
+            default_directory,
+            stores: Mutex::new(HashMap::new()),
+            registry: None,
+        }
+    }
+
+    /// Add the persisted totals of a project to the live counters of a
+    /// registry the first time an event of that project is handled
+    pub fn with_registry(mut self, registry: Arc<MetricsRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Get or open the metrics store of a project
+    ///
+    /// Opening a store restores its totals into the registry before any
+    /// event of this run is recorded in it, so nothing is counted twice.
+    fn store_for(&self, project_directory: &Path) -> Arc<MetricsStore> {
+        let mut stores = self.stores.lock().unwrap();
+        if let Some(store) = stores.get(project_directory) {
+            return store.clone();
+        }
+
+        let store = Arc::new(MetricsStore::open(project_directory));
+        if let Some(registry) = &self.registry {
+            match store.totals() {
+                Ok(totals) => registry.restore(&totals),
+                Err(e) => tracing::warn!(
+                    "Failed to restore persisted metrics of {}: {}",
+                    project_directory.display(),
+                    e
+                ),
+            }
+        }
+        stores.insert(project_directory.to_path_buf(), store.clone());
+        store
+    }
+}
+
+#[async_trait]
+impl EventListener for MetricsStoreListener {
+    async fn handle_event(&self, event: &Event) -> Result<(), EventError> {
+        let data = &event.data;
+        let Some(tool) = data.get("tool").and_then(|v| v.as_str()) else {
+            return Ok(());
+        };
+        let project_directory = data
+            .get("project_directory")
+            .and_then(|v| v.as_str())
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.default_directory.clone());
+
+        let duration = data
+            .get("duration_us")
+            .and_then(|v| v.as_u64())
+            .map(Duration::from_micros)
+            .or_else(|| {
+                data.get("duration_ms")
+                    .and_then(|v| v.as_u64())
+                    .map(Duration::from_millis)
+            })
+            .unwrap_or_default();
+        let success = data.get("success").and_then(|v| v.as_bool()) == Some(true)
+            && data.get("is_error").and_then(|v| v.as_bool()) != Some(true);
+
+        self.store_for(&project_directory)
+            .record(
+                tool,
+                duration,
+                success,
+                DateTime::<Local>::from(event.timestamp),
+            )
+            .map_err(|e| EventError::HandlingFailed {
+                message: format!("Failed to persist metrics: {}", e),
+            })
+    }
+
+    fn listener_id(&self) -> &str {
+        "metrics_store"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::EventType;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn project_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("ifm-ruta-metrics-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn at(day: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 10, day, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_rollups_per_day() {
+        let dir = project_dir();
+        let store = MetricsStore::open(&dir);
+        store
+            .record(
+                "interactive_feedback",
+                Duration::from_secs(30),
+                true,
+                at(12),
+            )
+            .unwrap();
+        store
+            .record(
+                "interactive_feedback",
+                Duration::from_secs(90),
+                true,
+                at(14),
+            )
+            .unwrap();
+        store
+            .record(
+                "interactive_feedback",
+                Duration::from_secs(5),
+                false,
+                at(14),
+            )
+            .unwrap();
+        store
+            .record("run_command", Duration::from_millis(40), true, at(14))
+            .unwrap();
+
+        let days = store.days().unwrap();
+        assert_eq!(days, vec![at(12).date_naive(), at(14).date_naive()]);
+
+        let day = store.load_day(at(14).date_naive()).unwrap();
+        assert_eq!(day.tools["interactive_feedback"].call_count, 2);
+        assert_eq!(day.tools["interactive_feedback"].error_count, 1);
+
+        // Reopening sees the same data
+        let totals = MetricsStore::open(&dir).totals().unwrap();
+        let feedback = totals["interactive_feedback"].stats();
+        assert_eq!(feedback.call_count, 3);
+        assert_eq!(feedback.total_duration, Duration::from_secs(125));
+        assert_eq!(feedback.latency.count(), 2);
+        assert_eq!(totals["run_command"].call_count, 1);
+
+        let week = store
+            .range(at(13).date_naive(), at(19).date_naive())
+            .unwrap();
+        assert_eq!(week.len(), 1);
+        assert_eq!(
+            DailyRollup::merge(&week)["interactive_feedback"]
+                .stats()
+                .max_duration,
+            Duration::from_secs(90)
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_missing_store_is_empty() {
+        let store = MetricsStore::open(&project_dir());
+        assert!(store.days().unwrap().is_empty());
+        assert!(store.totals().unwrap().is_empty());
+        assert!(store.last_days(7).unwrap().is_empty());
+        assert!(store.last_days(u32::MAX).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_listener_routes_by_project() {
+        let default_dir = project_dir();
+        let project = project_dir();
+        let listener = MetricsStoreListener::new(default_dir.clone());
+
+        for (directory, success) in [(&project, true), (&project, false), (&default_dir, true)] {
+            listener
+                .handle_event(&Event::new(
+                    EventType::ToolExecuted,
+                    "test",
+                    json!({
+                        "tool": "interactive_feedback",
+                        "project_directory": directory.to_string_lossy(),
+                        "duration_us": 1500,
+                        "duration_ms": 1,
+                        "success": true,
+                        "is_error": !success,
+                    }),
+                ))
+                .await
+                .unwrap();
+        }
+
+        let totals = MetricsStore::open(&project).totals().unwrap();
+        let stats = totals["interactive_feedback"].stats();
+        assert_eq!(stats.call_count, 2);
+        assert_eq!(stats.error_count, 1);
+        assert_eq!(stats.total_duration, Duration::from_micros(3000));
+        assert_eq!(
+            MetricsStore::open(&default_dir).last_days(1).unwrap().len(),
+            1
+        );
+
+        std::fs::remove_dir_all(&project).ok();
+        std::fs::remove_dir_all(&default_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_listener_restores_each_project_once() {
+        let default_dir = project_dir();
+        let project = project_dir();
+        MetricsStore::open(&project)
+            .record("run_command", Duration::from_millis(10), true, Local::now())
+            .unwrap();
+
+        let registry = Arc::new(MetricsRegistry::new());
+        let listener =
+            MetricsStoreListener::new(default_dir.clone()).with_registry(registry.clone());
+        for _ in 0..2 {
+            // The server records the call before publishing the event
+            registry.record("run_command", Duration::from_millis(10), true);
+            listener
+                .handle_event(&Event::new(
+                    EventType::ToolExecuted,
+                    "test",
+                    json!({
+                        "tool": "run_command",
+                        "project_directory": project.to_string_lossy(),
+                        "duration_us": 10000,
+                        "success": true,
+                    }),
+                ))
+                .await
+                .unwrap();
+        }
+
+        // One persisted call from a previous run and two from this one
+        assert_eq!(registry.tool("run_command").call_count(), 3);
+        assert_eq!(
+            MetricsStore::open(&project).totals().unwrap()["run_command"].call_count,
+            3
+        );
+        // Projects without events are not restored
+        assert!(!MetricsStore::metrics_dir(&default_dir).exists());
+
+        std::fs::remove_dir_all(&project).ok();
+    }
+}
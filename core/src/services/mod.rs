@@ -1,18 +1,34 @@
 //! Core services implementation
 
+pub mod audit_log;
 pub mod conversation_storage;
+pub mod conversation_store;
+pub mod database_store;
 pub mod event_bus;
+pub mod histogram;
 pub mod metrics;
+pub mod metrics_export;
+pub mod metrics_store;
 pub mod process_manager;
+pub mod response_analytics;
+pub mod session_log;
 pub mod settings_manager;
 pub mod tool_registry;
 pub mod validation;
 
 // Re-export all services
+pub use audit_log::*;
 pub use conversation_storage::*;
+pub use conversation_store::*;
+pub use database_store::*;
 pub use event_bus::*;
+pub use histogram::*;
 pub use metrics::*;
+pub use metrics_export::*;
+pub use metrics_store::*;
 pub use process_manager::*;
+pub use response_analytics::*;
+pub use session_log::*;
 pub use settings_manager::*;
 pub use tool_registry::*;
 pub use validation::*;
@@ -1,18 +1,419 @@
 //! Process manager implementation
 
+use async_trait::async_trait;
 use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
 use std::path::Path;
-use std::process::Stdio;
+use std::process::{Child, ExitStatus, Stdio};
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessRefreshKind, System};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt};
+use tokio::sync::mpsc;
 
+use crate::security::ProcessPolicy;
 use crate::traits::{
-    ProcessError, ProcessHandle, ProcessManager, ProcessOutput, ProcessResult, ProcessStatus,
+    AsyncProcessManager, Event, EventBus, EventType, ProcessError, ProcessEvent, ProcessHandle,
+    ProcessInfo, ProcessManager, ProcessOutput, ProcessResult, ProcessSpec, ProcessStatus,
+    StreamingProcess,
 };
 
+/// Interval between exit checks while waiting for a process
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long descendants may keep the output pipes open after the child exited
+const READER_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Source of the events published by the process manager
+const EVENT_SOURCE: &str = "process_manager";
+
+/// Stdin of a streaming process, shared with async writers
+type SharedStdin = Arc<tokio::sync::Mutex<Option<tokio::process::ChildStdin>>>;
+
+/// Ownership of the OS child process
+enum ChildSlot {
+    /// Spawned through `ProcessManager`, polled with `try_wait`
+    Sync(Child),
+    /// Spawned through `AsyncProcessManager`; the streaming task owns the
+    /// child and publishes its exit status once the output is drained
+    Streaming {
+        exit: Arc<Mutex<Option<ExitStatus>>>,
+        // Unix kills the whole process group by pid instead
+        #[cfg_attr(unix, allow(dead_code))]
+        kill: Arc<tokio::sync::Notify>,
+        stdin: SharedStdin,
+    },
+}
+
+/// A spawned child process and its captured output
+struct ManagedProcess {
+    handle: ProcessHandle,
+    pid: u32,
+    child: ChildSlot,
+    stdout: Arc<Mutex<String>>,
+    stderr: Arc<Mutex<String>>,
+    readers: Vec<JoinHandle<()>>,
+    started_at: Instant,
+    /// When a synchronous child was first seen exited, before it is reaped
+    exited_at: Option<Instant>,
+    exit_code: Option<i32>,
+    duration: Option<Duration>,
+    /// Receives the `ProcessCompleted` event of synchronous children
+    event_bus: Option<Arc<dyn EventBus>>,
+}
+
+impl ManagedProcess {
+    /// Check whether the child has exited and record its exit status
+    ///
+    /// An exited child is reaped once its output pipes are drained, so it
+    /// never lingers as a zombie once it has been polled. Until then its
+    /// process group is still safe to kill, see `ready_to_reap`.
+    fn poll_exit(&mut self) -> Result<bool, ProcessError> {
+        if self.exit_code.is_some() {
+            return Ok(true);
+        }
+
+        let status = match &self.child {
+            ChildSlot::Streaming { exit, .. } => *exit.lock().unwrap(),
+            ChildSlot::Sync(_) => {
+                if self.ready_to_reap()? {
+                    self.reap_sync()?
+                } else {
+                    None
+                }
+            }
+        };
+
+        match status {
+            Some(status) => {
+                self.exit_code = Some(exit_code(&status));
+                self.duration = Some(
+                    self.exited_at
+                        .unwrap_or_else(Instant::now)
+                        .duration_since(self.started_at),
+                );
+                if self.handle.status == ProcessStatus::Running {
+                    self.handle.status = if status.success() {
+                        ProcessStatus::Completed
+                    } else {
+                        ProcessStatus::Failed
+                    };
+                }
+                if let Some(event_bus) = &self.event_bus {
+                    publish(event_bus.as_ref(), self.completed_event());
+                }
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Check whether an exited synchronous child may be reaped
+    ///
+    /// The child is left unreaped while descendants still hold its output
+    /// pipes: its pid, and with it the process group, cannot be reused until
+    /// then. If the pipes are still open after `READER_DRAIN_TIMEOUT`, the
+    /// rest of the group is killed first.
+    #[cfg(unix)]
+    fn ready_to_reap(&mut self) -> Result<bool, ProcessError> {
+        let exited = has_exited_unreaped(self.pid).map_err(|e| ProcessError::ExecutionFailed {
+            message: e.to_string(),
+        })?;
+        if !exited {
+            return Ok(false);
+        }
+
+        let exited_at = *self.exited_at.get_or_insert_with(Instant::now);
+        if self.readers.iter().all(|reader| reader.is_finished()) {
+            return Ok(true);
+        }
+        if exited_at.elapsed() < READER_DRAIN_TIMEOUT {
+            return Ok(false);
+        }
+        kill_process_group(self.pid)?;
+        Ok(true)
+    }
+
+    #[cfg(not(unix))]
+    fn ready_to_reap(&mut self) -> Result<bool, ProcessError> {
+        Ok(true)
+    }
+
+    /// Reap a synchronous child if it exited
+    fn reap_sync(&mut self) -> Result<Option<ExitStatus>, ProcessError> {
+        let ChildSlot::Sync(child) = &mut self.child else {
+            return Ok(None);
+        };
+        let status = child
+            .try_wait()
+            .map_err(|e| ProcessError::ExecutionFailed {
+                message: e.to_string(),
+            })?;
+        if status.is_some() {
+            self.exited_at.get_or_insert_with(Instant::now);
+        }
+        Ok(status)
+    }
+
+    /// Check whether the child was reaped, after which its pid may belong to
+    /// an unrelated process
+    fn is_reaped(&self) -> bool {
+        match &self.child {
+            ChildSlot::Sync(_) => self.exit_code.is_some(),
+            ChildSlot::Streaming { exit, .. } => exit.lock().unwrap().is_some(),
+        }
+    }
+
+    /// Build the `ProcessCompleted` event once the exit status is known
+    fn completed_event(&self) -> Event {
+        Event::new(
+            EventType::ProcessCompleted,
+            EVENT_SOURCE,
+            serde_json::json!({
+                "process_id": self.handle.id,
+                "command": self.handle.command,
+                "args": self.handle.args,
+                "pid": self.pid,
+                "status": self.handle.status.to_string(),
+                "exit_code": self.exit_code,
+                "duration_ms": self.duration.map(|d| d.as_millis() as u64),
+            }),
+        )
+    }
+
+    /// Send SIGKILL to the whole process group of the child
+    ///
+    /// Does nothing once the child is reaped: the group id may have been
+    /// reused by then. Callers must hold the process table lock, which
+    /// streaming children are also reaped under.
+    #[cfg(unix)]
+    fn kill(&mut self) -> Result<(), ProcessError> {
+        if self.is_reaped() {
+            return Ok(());
+        }
+        kill_process_group(self.pid)
+    }
+
+    #[cfg(not(unix))]
+    fn kill(&mut self) -> Result<(), ProcessError> {
+        match &mut self.child {
+            ChildSlot::Sync(child) => child.kill().map_err(|e| ProcessError::ExecutionFailed {
+                message: e.to_string(),
+            }),
+            ChildSlot::Streaming { kill, .. } => {
+                kill.notify_one();
+                Ok(())
+            }
+        }
+    }
+
+    /// Reap a killed child (streaming children are reaped by their task)
+    fn reap_killed(&mut self) {
+        if let ChildSlot::Sync(child) = &mut self.child {
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Publish an event, logging instead of failing when it cannot be sent
+fn publish(event_bus: &dyn EventBus, event: Event) {
+    if let Err(e) = event_bus.publish(event) {
+        tracing::error!("Failed to publish process event: {}", e);
+    }
+}
+
+/// Check whether the child `pid` exited, without reaping it
+#[cfg(unix)]
+fn has_exited_unreaped(pid: u32) -> std::io::Result<bool> {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    let result = unsafe {
+        libc::waitid(
+            libc::P_PID,
+            pid as libc::id_t,
+            &mut info,
+            libc::WEXITED | libc::WNOHANG | libc::WNOWAIT,
+        )
+    };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // `info` stays zeroed while the child is still running
+    Ok(info.si_signo != 0)
+}
+
+/// Block until the child `pid` exits, without reaping it
+#[cfg(unix)]
+fn wait_exited_unreaped(pid: u32) -> std::io::Result<()> {
+    loop {
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        let result = unsafe {
+            libc::waitid(
+                libc::P_PID,
+                pid as libc::id_t,
+                &mut info,
+                libc::WEXITED | libc::WNOWAIT,
+            )
+        };
+        if result == 0 {
+            return Ok(());
+        }
+        let error = std::io::Error::last_os_error();
+        if error.kind() != std::io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+}
+
+/// Wait for the output readers of a streaming child
+///
+/// Background descendants may still hold the pipes open; they get a moment
+/// and then the rest of the group is taken down. Must only be called while
+/// the child is unreaped.
+async fn drain_readers(readers: Vec<tokio::task::JoinHandle<()>>, pid: u32) {
+    let mut drain = futures::future::join_all(readers);
+    if tokio::time::timeout(READER_DRAIN_TIMEOUT, &mut drain)
+        .await
+        .is_err()
+    {
+        #[cfg(unix)]
+        let _ = kill_process_group(pid);
+        #[cfg(not(unix))]
+        let _ = pid;
+        drain.await;
+    }
+}
+
+/// Send SIGKILL to the process group led by `pid`
+///
+/// `pid` must be an unreaped child, otherwise the group id may already
+/// belong to unrelated processes.
+#[cfg(unix)]
+fn kill_process_group(pid: u32) -> Result<(), ProcessError> {
+    // Children lead their own process group (see `build_command`), so a
+    // negative pid signals the child together with all its descendants
+    let pgid = pid as libc::pid_t;
+    let result = unsafe { libc::kill(-pgid, libc::SIGKILL) };
+    if result != 0 {
+        let error = std::io::Error::last_os_error();
+        // The group is already gone if every member exited in the meantime
+        if error.raw_os_error() != Some(libc::ESRCH) {
+            return Err(ProcessError::ExecutionFailed {
+                message: format!("Failed to kill process group {}: {}", pgid, error),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Convert an exit status into a shell-style exit code
+///
+/// Processes terminated by a signal report `128 + signal`, like a shell does.
+fn exit_code(status: &ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(-1)
+}
+
+/// Build the OS command for a process specification
+fn build_command(spec: &ProcessSpec) -> std::process::Command {
+    let mut cmd = std::process::Command::new(&spec.command);
+    cmd.args(&spec.args);
+    if let Some(cwd) = &spec.cwd {
+        cmd.current_dir(cwd);
+    }
+    if spec.env_clear {
+        cmd.env_clear();
+    }
+    cmd.envs(&spec.env);
+    cmd.stdin(if spec.stdin {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    });
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+
+    // Run the child in its own process group so killing it also
+    // takes down everything it spawned
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    #[cfg(target_os = "linux")]
+    if let Some(limits) = spec.limits.clone() {
+        use std::os::unix::process::CommandExt;
+        // Safety: the hook only calls getrlimit/setrlimit, which are
+        // async-signal-safe and do not allocate
+        unsafe {
+            cmd.pre_exec(move || apply_resource_limits(&limits));
+        }
+    }
+
+    cmd
+}
+
+/// Apply resource limits to the current process (runs in the forked child)
+#[cfg(target_os = "linux")]
+fn apply_resource_limits(limits: &crate::traits::ResourceLimits) -> std::io::Result<()> {
+    // glibc has its own type for resources, other C libraries use an int
+    #[cfg(target_env = "gnu")]
+    type Resource = libc::__rlimit_resource_t;
+    #[cfg(not(target_env = "gnu"))]
+    type Resource = libc::c_int;
+
+    fn set_limit(resource: Resource, soft: u64, hard: u64) -> std::io::Result<()> {
+        let mut current = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        if unsafe { libc::getrlimit(resource, &mut current) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        // Unprivileged processes may only lower their hard limits
+        let hard = hard.min(current.rlim_max);
+        let limit = libc::rlimit {
+            rlim_cur: soft.min(hard),
+            rlim_max: hard,
+        };
+        if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    if let Some(cpu_time) = limits.cpu_time {
+        // SIGXCPU at the soft limit, SIGKILL one second later
+        let seconds = cpu_time.as_secs().max(1);
+        set_limit(libc::RLIMIT_CPU, seconds, seconds + 1)?;
+    }
+    if let Some(bytes) = limits.address_space {
+        set_limit(libc::RLIMIT_AS, bytes, bytes)?;
+    }
+    if let Some(files) = limits.open_files {
+        set_limit(libc::RLIMIT_NOFILE, files, files)?;
+    }
+    if let Some(processes) = limits.processes {
+        set_limit(libc::RLIMIT_NPROC, processes, processes)?;
+    }
+    Ok(())
+}
+
 /// Process manager implementation
 pub struct ProcessManagerImpl {
-    processes: Arc<Mutex<HashMap<String, ProcessHandle>>>,
+    processes: Arc<Mutex<HashMap<String, ManagedProcess>>>,
+    /// Kept between listings so CPU usage is measured over the interval
+    system: Mutex<System>,
+    policy: Option<ProcessPolicy>,
+    event_bus: Option<Arc<dyn EventBus>>,
 }
 
 impl Default for ProcessManagerImpl {
@@ -26,13 +427,228 @@ impl ProcessManagerImpl {
     pub fn new() -> Self {
         Self {
             processes: Arc::new(Mutex::new(HashMap::new())),
+            system: Mutex::new(System::new()),
+            policy: None,
+            event_bus: None,
+        }
+    }
+
+    /// Gate every spawned process by a policy
+    pub fn with_policy(mut self, policy: ProcessPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Publish process lifecycle events and policy denials to an event bus
+    pub fn with_event_bus(mut self, event_bus: Arc<dyn EventBus>) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Get the policy processes are spawned under
+    pub fn policy(&self) -> Option<&ProcessPolicy> {
+        self.policy.as_ref()
+    }
+
+    /// Apply the configured policy to a process specification
+    fn authorize(&self, spec: ProcessSpec) -> Result<ProcessSpec, ProcessError> {
+        match &self.policy {
+            Some(policy) => self.authorize_with(policy, spec),
+            None => Ok(spec),
         }
     }
 
+    /// Apply a policy to a process specification
+    ///
+    /// Used for policies that depend on the caller (e.g. the project a
+    /// command runs in). Denials are logged and published as
+    /// `ErrorOccurred` events.
+    pub fn authorize_with(
+        &self,
+        policy: &ProcessPolicy,
+        spec: ProcessSpec,
+    ) -> Result<ProcessSpec, ProcessError> {
+        policy.apply(&spec).map_err(|reason| {
+            tracing::warn!("Process denied by policy: {}", reason);
+
+            self.publish(
+                EventType::ErrorOccurred,
+                serde_json::json!({
+                    "error": "process_denied",
+                    "command": spec.command,
+                    "args": spec.args,
+                    "cwd": spec.cwd,
+                    "reason": reason,
+                }),
+            );
+
+            ProcessError::PermissionDenied { message: reason }
+        })
+    }
+
+    /// Publish an event if an event bus is configured
+    fn publish(&self, event_type: EventType, data: serde_json::Value) {
+        if let Some(event_bus) = &self.event_bus {
+            publish(
+                event_bus.as_ref(),
+                Event::new(event_type, EVENT_SOURCE, data),
+            );
+        }
+    }
+
+    /// Report a process that failed to spawn
+    fn spawn_failed(&self, spec: &ProcessSpec, error: std::io::Error) -> ProcessError {
+        let message = format!("Failed to spawn '{}': {}", spec.command, error);
+        self.publish(
+            EventType::ErrorOccurred,
+            serde_json::json!({
+                "error": "spawn_failed",
+                "command": spec.command,
+                "args": spec.args,
+                "cwd": spec.cwd,
+                "reason": message,
+            }),
+        );
+        ProcessError::ExecutionFailed { message }
+    }
+
+    /// Report a spawned process
+    fn spawned(&self, handle: &ProcessHandle, pid: u32, streaming: bool) {
+        self.publish(
+            EventType::ProcessSpawned,
+            serde_json::json!({
+                "process_id": handle.id,
+                "command": handle.command,
+                "args": handle.args,
+                "cwd": handle.cwd,
+                "pid": pid,
+                "streaming": streaming,
+            }),
+        );
+    }
+
     /// Generate a unique process ID
     fn generate_process_id(&self) -> String {
         uuid::Uuid::new_v4().to_string()
     }
+
+    /// Spawn a thread that appends everything read from a pipe to a buffer
+    fn spawn_reader<R: Read + Send + 'static>(
+        pipe: R,
+        buffer: Arc<Mutex<String>>,
+    ) -> JoinHandle<()> {
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(pipe);
+            let mut line = Vec::new();
+            loop {
+                line.clear();
+                match reader.read_until(b'\n', &mut line) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        let mut buffer = buffer.lock().unwrap();
+                        buffer.push_str(&String::from_utf8_lossy(&line));
+                    }
+                }
+            }
+        })
+    }
+
+    /// Spawn a task that forwards lines read from a pipe as events
+    fn spawn_line_reader<R: AsyncRead + Unpin + Send + 'static>(
+        pipe: R,
+        buffer: Arc<Mutex<String>>,
+        events: mpsc::UnboundedSender<ProcessEvent>,
+        to_event: fn(String) -> ProcessEvent,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut reader = tokio::io::BufReader::new(pipe);
+            let mut line = Vec::new();
+            loop {
+                line.clear();
+                match reader.read_until(b'\n', &mut line).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        let text = String::from_utf8_lossy(&line).into_owned();
+                        buffer.lock().unwrap().push_str(&text);
+                        let text = text.trim_end_matches(['\n', '\r']).to_string();
+                        let _ = events.send(to_event(text));
+                    }
+                }
+            }
+        })
+    }
+
+    fn not_found(handle: &ProcessHandle) -> ProcessError {
+        ProcessError::ProcessNotFound {
+            id: handle.id.clone(),
+        }
+    }
+
+    /// Get the current status of a tracked process
+    pub fn process_status(&self, handle: &ProcessHandle) -> Result<ProcessStatus, ProcessError> {
+        let mut processes = self.processes.lock().unwrap();
+        let process = processes
+            .get_mut(&handle.id)
+            .ok_or_else(|| Self::not_found(handle))?;
+        process.poll_exit()?;
+        Ok(process.handle.status.clone())
+    }
+
+    /// Reap exited processes and remove them from the process table
+    ///
+    /// Returns the final handles of the removed processes. Their output is
+    /// no longer available afterwards.
+    pub fn reap_exited(&self) -> Vec<ProcessHandle> {
+        let mut processes = self.processes.lock().unwrap();
+
+        let exited: Vec<String> = processes
+            .iter_mut()
+            .filter_map(|(id, process)| match process.poll_exit() {
+                Ok(true) => Some(id.clone()),
+                _ => None,
+            })
+            .collect();
+
+        exited
+            .into_iter()
+            .filter_map(|id| processes.remove(&id))
+            .map(|process| process.handle)
+            .collect()
+    }
+
+    /// Get the number of tracked processes
+    pub fn process_count(&self) -> usize {
+        self.processes.lock().unwrap().len()
+    }
+
+    /// Get the stdin of a streaming process
+    fn stdin_of(&self, handle: &ProcessHandle) -> Result<SharedStdin, ProcessError> {
+        let processes = self.processes.lock().unwrap();
+        let process = processes
+            .get(&handle.id)
+            .ok_or_else(|| Self::not_found(handle))?;
+
+        match &process.child {
+            ChildSlot::Streaming { stdin, .. } => Ok(stdin.clone()),
+            ChildSlot::Sync(_) => Err(ProcessError::ExecutionFailed {
+                message: "stdin is only available for streaming processes".to_string(),
+            }),
+        }
+    }
+}
+
+impl Drop for ProcessManagerImpl {
+    fn drop(&mut self) {
+        // Do not leave orphaned process groups behind
+        if let Ok(mut processes) = self.processes.lock() {
+            for process in processes.values_mut() {
+                if !process.is_reaped() {
+                    let _ = process.kill();
+                    process.reap_killed();
+                }
+            }
+        }
+    }
 }
 
 impl ProcessManager for ProcessManagerImpl {
@@ -43,92 +659,351 @@ impl ProcessManager for ProcessManagerImpl {
         cwd: &Path,
     ) -> Result<ProcessHandle, ProcessError> {
         let process_id = self.generate_process_id();
+        let spec = self.authorize(
+            ProcessSpec::new(command)
+                .args(args.iter().cloned())
+                .cwd(cwd),
+        )?;
+
+        // Spawn the actual process
+        let started_at = Instant::now();
+        let mut child = build_command(&spec)
+            .spawn()
+            .map_err(|e| self.spawn_failed(&spec, e))?;
+
+        let stdout = Arc::new(Mutex::new(String::new()));
+        let stderr = Arc::new(Mutex::new(String::new()));
+        let mut readers = Vec::new();
+        if let Some(pipe) = child.stdout.take() {
+            readers.push(Self::spawn_reader(pipe, stdout.clone()));
+        }
+        if let Some(pipe) = child.stderr.take() {
+            readers.push(Self::spawn_reader(pipe, stderr.clone()));
+        }
 
-        // Create the process handle
         let handle = ProcessHandle {
             id: process_id.clone(),
             command: command.to_string(),
             args: args.to_vec(),
-            cwd: cwd.to_path_buf(),
+            cwd: spec.cwd.clone().unwrap_or_else(|| cwd.to_path_buf()),
             status: ProcessStatus::Running,
         };
 
-        // Store the handle
-        {
-            let mut processes = self.processes.lock().unwrap();
-            processes.insert(process_id.clone(), handle.clone());
-        }
-
-        // Spawn the actual process
-        let mut cmd = std::process::Command::new(command);
-        cmd.args(args);
-        cmd.current_dir(cwd);
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
+        let pid = child.id();
+        self.processes.lock().unwrap().insert(
+            process_id,
+            ManagedProcess {
+                handle: handle.clone(),
+                pid,
+                child: ChildSlot::Sync(child),
+                stdout,
+                stderr,
+                readers,
+                started_at,
+                exited_at: None,
+                exit_code: None,
+                duration: None,
+                event_bus: self.event_bus.clone(),
+            },
+        );
+        self.spawned(&handle, pid, false);
 
-        match cmd.spawn() {
-            Ok(_child) => {
-                // Update the handle with the actual process
-                let mut processes = self.processes.lock().unwrap();
-                if let Some(handle) = processes.get_mut(&process_id) {
-                    handle.status = ProcessStatus::Running;
-                }
-                Ok(handle)
-            }
-            Err(e) => {
-                // Remove the handle if process creation failed
-                let mut processes = self.processes.lock().unwrap();
-                processes.remove(&process_id);
-                Err(ProcessError::ExecutionFailed {
-                    message: e.to_string(),
-                })
-            }
-        }
+        Ok(handle)
     }
 
     fn kill_process(&self, handle: &ProcessHandle) -> Result<(), ProcessError> {
         let mut processes = self.processes.lock().unwrap();
-        if let Some(process_handle) = processes.get_mut(&handle.id) {
-            process_handle.status = ProcessStatus::Killed;
-            Ok(())
-        } else {
-            Err(ProcessError::ProcessNotFound {
-                id: handle.id.clone(),
-            })
+        let process = processes
+            .get_mut(&handle.id)
+            .ok_or_else(|| Self::not_found(handle))?;
+
+        // Signal the group even if the child already exited but is not
+        // reaped yet: descendants it left behind are still members of it
+        let exited = process.poll_exit()?;
+        process.kill()?;
+        if !exited {
+            process.handle.status = ProcessStatus::Killed;
         }
+        Ok(())
     }
 
     fn wait_for_process(&self, handle: &ProcessHandle) -> Result<ProcessResult, ProcessError> {
-        // This is a simplified implementation
-        // In a real implementation, you would wait for the actual process
-        let start_time = Instant::now();
-
-        // Simulate process execution
-        std::thread::sleep(Duration::from_millis(100));
-
-        let result = ProcessResult {
-            exit_code: 0,
-            stdout: "Process completed successfully".to_string(),
-            stderr: String::new(),
-            duration: start_time.elapsed(),
+        // Poll instead of blocking on the child so the process table stays
+        // available to other callers (e.g. to kill the process). Polling
+        // also drains the pipes, killing descendants that keep them open.
+        let readers = loop {
+            {
+                let mut processes = self.processes.lock().unwrap();
+                let process = processes
+                    .get_mut(&handle.id)
+                    .ok_or_else(|| Self::not_found(handle))?;
+                if process.poll_exit()? {
+                    break std::mem::take(&mut process.readers);
+                }
+            }
+            std::thread::sleep(WAIT_POLL_INTERVAL);
         };
 
-        // Update the handle status
-        let mut processes = self.processes.lock().unwrap();
-        if let Some(process_handle) = processes.get_mut(&handle.id) {
-            process_handle.status = ProcessStatus::Completed;
+        for reader in readers {
+            let _ = reader.join();
         }
 
-        Ok(result)
+        let processes = self.processes.lock().unwrap();
+        let process = processes
+            .get(&handle.id)
+            .ok_or_else(|| Self::not_found(handle))?;
+
+        let stdout = process.stdout.lock().unwrap().clone();
+        let stderr = process.stderr.lock().unwrap().clone();
+
+        Ok(ProcessResult {
+            exit_code: process.exit_code.unwrap_or(-1),
+            stdout,
+            stderr,
+            duration: process
+                .duration
+                .unwrap_or_else(|| process.started_at.elapsed()),
+        })
     }
 
-    fn get_process_output(&self, _handle: &ProcessHandle) -> Result<ProcessOutput, ProcessError> {
-        // This is a simplified implementation
-        // In a real implementation, you would read from the actual process
+    fn get_process_output(&self, handle: &ProcessHandle) -> Result<ProcessOutput, ProcessError> {
+        let mut processes = self.processes.lock().unwrap();
+        let process = processes
+            .get_mut(&handle.id)
+            .ok_or_else(|| Self::not_found(handle))?;
+
+        // Output is complete once the child exited and the pipes are drained
+        let exited = process.poll_exit()?;
+        let is_complete = exited && process.readers.iter().all(|r| r.is_finished());
+
+        let stdout = process.stdout.lock().unwrap().clone();
+        let stderr = process.stderr.lock().unwrap().clone();
+
         Ok(ProcessOutput {
-            stdout: "Process output".to_string(),
-            stderr: String::new(),
-            is_complete: true,
+            stdout,
+            stderr,
+            is_complete,
         })
     }
+
+    fn list_processes(&self) -> Vec<ProcessInfo> {
+        let mut processes = self.processes.lock().unwrap();
+        let mut system = self.system.lock().unwrap();
+
+        let mut infos: Vec<ProcessInfo> = processes
+            .values_mut()
+            .map(|process| {
+                let exited = process.poll_exit().unwrap_or(false);
+                let pid = Pid::from_u32(process.pid);
+
+                let (cpu_usage, memory_bytes) = if !exited
+                    && system.refresh_process_specifics(
+                        pid,
+                        ProcessRefreshKind::new().with_cpu().with_memory(),
+                    ) {
+                    system
+                        .process(pid)
+                        .map(|p| (p.cpu_usage(), p.memory()))
+                        .unwrap_or_default()
+                } else {
+                    (0.0, 0)
+                };
+
+                ProcessInfo {
+                    handle: process.handle.clone(),
+                    pid: process.pid,
+                    cpu_usage,
+                    memory_bytes,
+                    elapsed: process
+                        .duration
+                        .unwrap_or_else(|| process.started_at.elapsed()),
+                }
+            })
+            .collect();
+
+        infos.sort_by_key(|info| std::cmp::Reverse(info.elapsed));
+        infos
+    }
+}
+
+#[async_trait]
+impl AsyncProcessManager for ProcessManagerImpl {
+    async fn spawn_streaming(&self, spec: ProcessSpec) -> Result<StreamingProcess, ProcessError> {
+        let spec = self.authorize(spec)?;
+        let process_id = self.generate_process_id();
+
+        let started_at = Instant::now();
+        let mut child = tokio::process::Command::from(build_command(&spec))
+            .spawn()
+            .map_err(|e| self.spawn_failed(&spec, e))?;
+        let pid = child.id().ok_or_else(|| ProcessError::ExecutionFailed {
+            message: format!("'{}' exited before it could be tracked", spec.command),
+        })?;
+
+        let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+        let stdout = Arc::new(Mutex::new(String::new()));
+        let stderr = Arc::new(Mutex::new(String::new()));
+        let mut readers = Vec::new();
+        if let Some(pipe) = child.stdout.take() {
+            readers.push(Self::spawn_line_reader(
+                pipe,
+                stdout.clone(),
+                events_tx.clone(),
+                ProcessEvent::Stdout,
+            ));
+        }
+        if let Some(pipe) = child.stderr.take() {
+            readers.push(Self::spawn_line_reader(
+                pipe,
+                stderr.clone(),
+                events_tx.clone(),
+                ProcessEvent::Stderr,
+            ));
+        }
+
+        let exit = Arc::new(Mutex::new(None));
+        let kill = Arc::new(tokio::sync::Notify::new());
+        let stdin = Arc::new(tokio::sync::Mutex::new(child.stdin.take()));
+
+        let handle = ProcessHandle {
+            id: process_id.clone(),
+            command: spec.command.clone(),
+            args: spec.args.clone(),
+            cwd: spec
+                .cwd
+                .clone()
+                .or_else(|| std::env::current_dir().ok())
+                .unwrap_or_default(),
+            status: ProcessStatus::Running,
+        };
+
+        self.processes.lock().unwrap().insert(
+            process_id.clone(),
+            ManagedProcess {
+                handle: handle.clone(),
+                pid,
+                child: ChildSlot::Streaming {
+                    exit: exit.clone(),
+                    kill: kill.clone(),
+                    stdin: stdin.clone(),
+                },
+                stdout,
+                stderr,
+                readers: Vec::new(),
+                started_at,
+                exited_at: None,
+                exit_code: None,
+                duration: None,
+                // Completion is published by the streaming task below
+                event_bus: None,
+            },
+        );
+        self.spawned(&handle, pid, true);
+
+        let processes = self.processes.clone();
+        let event_bus = self.event_bus.clone();
+        tokio::spawn(async move {
+            // Wait for the exit without reaping the child: until it is
+            // reaped its pid, and with it the process group, cannot be
+            // reused, so the group can still be killed while the pipes drain
+            #[cfg(unix)]
+            let status = {
+                match tokio::task::spawn_blocking(move || wait_exited_unreaped(pid)).await {
+                    Ok(Err(e)) => tracing::warn!("Failed to wait for process {}: {}", pid, e),
+                    Err(e) => tracing::warn!("Failed to wait for process {}: {}", pid, e),
+                    Ok(Ok(())) => {}
+                }
+                // Drop our end of stdin so writers notice the process is gone
+                stdin.lock().await.take();
+                drain_readers(readers, pid).await;
+
+                // Reap under the process table lock so `kill` never signals
+                // a group whose leader is already reaped
+                let reaped = {
+                    let _processes = processes.lock().unwrap();
+                    let reaped = child.try_wait();
+                    if let Ok(Some(status)) = &reaped {
+                        *exit.lock().unwrap() = Some(*status);
+                    }
+                    reaped
+                };
+                match reaped {
+                    Ok(Some(status)) => Ok(status),
+                    Ok(None) => child.wait().await,
+                    Err(e) => Err(e),
+                }
+            };
+            #[cfg(not(unix))]
+            let status = {
+                let status = tokio::select! {
+                    status = child.wait() => status,
+                    _ = kill.notified() => {
+                        let _ = child.kill().await;
+                        child.wait().await
+                    }
+                };
+                stdin.lock().await.take();
+                drain_readers(readers, pid).await;
+                status
+            };
+
+            match status {
+                Ok(status) => {
+                    *exit.lock().unwrap() = Some(status);
+                    let _ = events_tx.send(ProcessEvent::Exited {
+                        exit_code: exit_code(&status),
+                        duration: started_at.elapsed(),
+                    });
+
+                    if let Some(event_bus) = event_bus {
+                        let mut processes = processes.lock().unwrap();
+                        if let Some(process) = processes.get_mut(&process_id) {
+                            if let Ok(true) = process.poll_exit() {
+                                publish(event_bus.as_ref(), process.completed_event());
+                            }
+                        }
+                    }
+                }
+                Err(e) => tracing::error!("Failed to wait for process {}: {}", pid, e),
+            }
+        });
+
+        let events = futures::stream::poll_fn(move |cx| events_rx.poll_recv(cx));
+
+        Ok(StreamingProcess {
+            handle,
+            events: Box::new(events),
+        })
+    }
+
+    async fn write_stdin(&self, handle: &ProcessHandle, data: &[u8]) -> Result<(), ProcessError> {
+        let stdin = self.stdin_of(handle)?;
+        let mut stdin = stdin.lock().await;
+        let pipe = stdin
+            .as_mut()
+            .ok_or_else(|| ProcessError::ExecutionFailed {
+                message: "stdin is closed".to_string(),
+            })?;
+
+        pipe.write_all(data)
+            .await
+            .map_err(|e| ProcessError::ExecutionFailed {
+                message: format!("Failed to write to stdin: {}", e),
+            })?;
+        pipe.flush()
+            .await
+            .map_err(|e| ProcessError::ExecutionFailed {
+                message: format!("Failed to flush stdin: {}", e),
+            })
+    }
+
+    async fn close_stdin(&self, handle: &ProcessHandle) -> Result<(), ProcessError> {
+        let stdin = self.stdin_of(handle)?;
+        stdin.lock().await.take();
+        Ok(())
+    }
+
+    async fn kill(&self, handle: &ProcessHandle) -> Result<(), ProcessError> {
+        self.kill_process(handle)
+    }
 }
@@ -0,0 +1,240 @@
+//! Human response-time analytics for interactive feedback
+//!
+//! Built from the response timings recorded in the conversation store:
+//! distributions of time-to-first-keystroke and time-to-submit, and the
+//! cancellation rate, per project and per local hour of day.
+
+use chrono::{DateTime, Local, Timelike};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::models::{AppError, ConversationSettings, RetentionPolicy};
+use crate::services::{
+    open_conversation_store, ConversationSession, HistogramSnapshot, ResponseTiming,
+};
+
+/// Response statistics of a set of feedback requests
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResponseStats {
+    pub requests: u64,
+    pub cancelled: u64,
+    /// Time until the user started typing
+    pub first_keystroke: HistogramSnapshot,
+    /// Time until the user submitted, for requests that were not cancelled
+    pub submit: HistogramSnapshot,
+}
+
+impl ResponseStats {
+    /// Record a response
+    pub fn record(&mut self, timing: &ResponseTiming) {
+        self.requests += 1;
+        if let Some(first_keystroke) = timing.time_to_first_keystroke_ms {
+            self.first_keystroke
+                .record(Duration::from_millis(first_keystroke));
+        }
+        if timing.cancelled {
+            self.cancelled += 1;
+        } else {
+            self.submit
+                .record(Duration::from_millis(timing.time_to_submit_ms));
+        }
+    }
+
+    /// Percentage of requests the user cancelled
+    pub fn cancel_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.cancelled as f64 / self.requests as f64 * 100.0
+        }
+    }
+
+    /// Convert to JSON with durations in seconds
+    pub fn to_json(&self) -> Value {
+        json!({
+            "requests": self.requests,
+            "cancelled": self.cancelled,
+            "cancel_rate": self.cancel_rate(),
+            "time_to_first_keystroke_secs": distribution_json(&self.first_keystroke),
+            "time_to_submit_secs": distribution_json(&self.submit),
+        })
+    }
+}
+
+fn distribution_json(histogram: &HistogramSnapshot) -> Value {
+    json!({
+        "count": histogram.count(),
+        "min": histogram.min().as_secs_f64(),
+        "p50": histogram.quantile(0.5).as_secs_f64(),
+        "p90": histogram.quantile(0.9).as_secs_f64(),
+        "p99": histogram.quantile(0.99).as_secs_f64(),
+        "max": histogram.max().as_secs_f64(),
+    })
+}
+
+/// Response analytics of a project
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseAnalytics {
+    pub project_directory: PathBuf,
+    pub overall: ResponseStats,
+    /// Statistics per local hour of day (0-23) the request was made
+    pub by_hour: BTreeMap<u32, ResponseStats>,
+}
+
+impl ResponseAnalytics {
+    /// Compute the analytics of conversation sessions
+    pub fn from_sessions(project_directory: &Path, sessions: &[ConversationSession]) -> Self {
+        let mut analytics = Self {
+            project_directory: project_directory.to_path_buf(),
+            overall: ResponseStats::default(),
+            by_hour: BTreeMap::new(),
+        };
+
+        let responses = sessions
+            .iter()
+            .flat_map(|session| &session.messages)
+            .filter_map(|message| Some((message.response.as_ref()?, &message.timestamp)));
+        for (timing, timestamp) in responses {
+            analytics.overall.record(timing);
+            if let Ok(requested_at) = DateTime::parse_from_rfc3339(timestamp) {
+                let hour = requested_at.with_timezone(&Local).hour();
+                analytics.by_hour.entry(hour).or_default().record(timing);
+            }
+        }
+
+        analytics
+    }
+
+    /// Load the analytics of a project from the conversation store selected
+    /// by the settings
+    pub fn load(
+        project_directory: &Path,
+        settings: &ConversationSettings,
+    ) -> Result<Self, AppError> {
+        let store =
+            open_conversation_store(project_directory, settings, RetentionPolicy::default());
+        Ok(Self::from_sessions(
+            project_directory,
+            &store.list_sessions()?,
+        ))
+    }
+
+    /// Convert to JSON
+    pub fn to_json(&self) -> Value {
+        json!({
+            "project_directory": self.project_directory,
+            "overall": self.overall.to_json(),
+            "by_hour": self
+                .by_hour
+                .iter()
+                .map(|(hour, stats)| (format!("{:02}", hour), stats.to_json()))
+                .collect::<serde_json::Map<_, _>>(),
+        })
+    }
+
+    /// Render as a table with one row per hour of day
+    pub fn render(&self) -> String {
+        let mut report = format!("Project: {}\n", self.project_directory.display());
+        if self.overall.requests == 0 {
+            report.push_str("No feedback responses recorded\n");
+            return report;
+        }
+
+        report.push_str(&format!(
+            "{:<5}  {:>8}  {:>8}  {:>10}  {:>10}  {:>10}  {:>10}\n",
+            "HOUR", "REQUESTS", "CANCEL%", "KEY P50 S", "KEY P90 S", "SUB P50 S", "SUB P90 S"
+        ));
+        let rows = self
+            .by_hour
+            .iter()
+            .map(|(hour, stats)| (format!("{:02}", hour), stats))
+            .chain(std::iter::once(("ALL".to_string(), &self.overall)));
+        for (hour, stats) in rows {
+            report.push_str(&format!(
+                "{:<5}  {:>8}  {:>7.1}%  {:>10.1}  {:>10.1}  {:>10.1}  {:>10.1}\n",
+                hour,
+                stats.requests,
+                stats.cancel_rate(),
+                stats.first_keystroke.quantile(0.5).as_secs_f64(),
+                stats.first_keystroke.quantile(0.9).as_secs_f64(),
+                stats.submit.quantile(0.5).as_secs_f64(),
+                stats.submit.quantile(0.9).as_secs_f64(),
+            ));
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ConversationMessage;
+    use chrono::TimeZone;
+
+    fn prompt(hour: u32, timing: ResponseTiming) -> ConversationMessage {
+        ConversationMessage {
+            id: format!("prompt-{}", hour),
+            role: "assistant".to_string(),
+            content: "Done?".to_string(),
+            timestamp: Local
+                .with_ymd_and_hms(2026, 10, 12, hour, 30, 0)
+                .unwrap()
+                .to_rfc3339(),
+            response: Some(timing),
+            feedback: None,
+        }
+    }
+
+    fn timing(first_keystroke_ms: Option<u64>, submit_ms: u64, cancelled: bool) -> ResponseTiming {
+        ResponseTiming {
+            time_to_first_keystroke_ms: first_keystroke_ms,
+            time_to_submit_ms: submit_ms,
+            cancelled,
+        }
+    }
+
+    #[test]
+    fn test_stats_per_hour() {
+        let session = ConversationSession {
+            session_id: "s".to_string(),
+            project_directory: PathBuf::from("/project"),
+            messages: vec![
+                prompt(9, timing(Some(2_000), 20_000, false)),
+                prompt(9, timing(None, 5_000, true)),
+                prompt(14, timing(Some(10_000), 60_000, false)),
+                ConversationMessage {
+                    id: "reply".to_string(),
+                    role: "user".to_string(),
+                    content: "yes".to_string(),
+                    timestamp: String::new(),
+                    response: None,
+                    feedback: None,
+                },
+            ],
+            created_at: String::new(),
+            last_updated: String::new(),
+        };
+
+        let analytics = ResponseAnalytics::from_sessions(Path::new("/project"), &[session]);
+        assert_eq!(analytics.overall.requests, 3);
+        assert_eq!(analytics.overall.cancelled, 1);
+        assert_eq!(analytics.overall.submit.count(), 2);
+        assert_eq!(analytics.overall.first_keystroke.count(), 2);
+
+        let morning = &analytics.by_hour[&9];
+        assert_eq!(morning.requests, 2);
+        assert_eq!(morning.cancel_rate(), 50.0);
+        assert_eq!(morning.submit.max(), Duration::from_secs(20));
+        assert_eq!(analytics.by_hour[&14].cancel_rate(), 0.0);
+
+        let json = analytics.to_json();
+        assert_eq!(json["by_hour"]["09"]["requests"], 2);
+        assert_eq!(json["overall"]["time_to_submit_secs"]["max"], 60.0);
+
+        let report = analytics.render();
+        assert!(report.contains("ALL"));
+        assert!(report.lines().any(|line| line.starts_with("09")));
+    }
+}
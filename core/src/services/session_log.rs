@@ -0,0 +1,383 @@
+//! Append-only JSONL session log
+//!
+//! A session log is a header line followed by one JSON record per line.
+//! Adding a message appends a single line instead of rewriting the whole
+//! session, so writes take the same time however long the conversation gets.
+//! Response timings recorded later are appended as their own records and
+//! folded into their message when the log is read; compacting the log
+//! rewrites it with the timings inline. The header records how many bytes of
+//! records were written with it, so how much a log grew since it was last
+//! rewritten is known without reading it.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::models::{AppError, VersionedFeedback};
+use crate::services::{ConversationMessage, ConversationSession, ResponseTiming};
+use crate::utils::write_atomic;
+
+/// Identifies a session log in its header
+pub const SESSION_LOG_FORMAT: &str = "ifm-ruta-session";
+
+/// Newest log version this build reads and writes
+pub const SESSION_LOG_VERSION: u32 = 1;
+
+/// First line of a session log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionLogHeader {
+    pub format: String,
+    pub version: u32,
+    pub session_id: String,
+    pub project_directory: PathBuf,
+    pub created_at: String,
+    /// Last update when the log was written; later records move it forward
+    pub last_updated: String,
+    /// Length in bytes of the records written with the header, absent in
+    /// logs written by older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub records_length: Option<u64>,
+}
+
+impl SessionLogHeader {
+    fn for_session(session: &ConversationSession) -> Self {
+        Self {
+            format: SESSION_LOG_FORMAT.to_string(),
+            version: SESSION_LOG_VERSION,
+            session_id: session.session_id.clone(),
+            project_directory: session.project_directory.clone(),
+            created_at: session.created_at.clone(),
+            last_updated: session.last_updated.clone(),
+            records_length: None,
+        }
+    }
+}
+
+/// Record following the header
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionLogRecord {
+    Message(ConversationMessage),
+    /// Response timing of an earlier assistant message
+    Response {
+        /// Message the timing belongs to; the latest assistant message
+        /// before this record when not set
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message_id: Option<String>,
+        response: ResponseTiming,
+        /// Complete feedback, absent in logs written by older versions
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        feedback: Option<VersionedFeedback>,
+        at: String,
+    },
+}
+
+impl SessionLogRecord {
+    fn timestamp(&self) -> &str {
+        match self {
+            Self::Message(message) => &message.timestamp,
+            Self::Response { at, .. } => at,
+        }
+    }
+}
+
+fn to_line<T: Serialize>(value: &T) -> Result<String, AppError> {
+    serde_json::to_string(value)
+        .map(|line| line + "\n")
+        .map_err(|e| AppError::SerializationError {
+            message: format!("Failed to serialize session log record: {}", e),
+        })
+}
+
+/// Read a session log
+///
+/// A record that cannot be parsed, such as the torn last line of a write
+/// interrupted by a crash, is skipped; a missing or unreadable header makes
+/// the whole log unreadable.
+pub fn read_session_log(path: &Path) -> Result<ConversationSession, AppError> {
+    let file = fs::File::open(path).map_err(|e| AppError::StorageError {
+        message: format!("Failed to read session log: {}", e),
+    })?;
+    let mut lines = BufReader::new(file).lines();
+
+    let header_line = lines
+        .next()
+        .transpose()
+        .map_err(|e| AppError::StorageError {
+            message: format!("Failed to read session log: {}", e),
+        })?
+        .unwrap_or_default();
+    let header: SessionLogHeader =
+        serde_json::from_str(&header_line).map_err(|e| AppError::DeserializationError {
+            message: format!("Failed to deserialize session log header: {}", e),
+        })?;
+    if header.format != SESSION_LOG_FORMAT || header.version > SESSION_LOG_VERSION {
+        return Err(AppError::DeserializationError {
+            message: format!(
+                "Unsupported session log {} version {}",
+                header.format, header.version
+            ),
+        });
+    }
+
+    let mut session = ConversationSession {
+        session_id: header.session_id,
+        project_directory: header.project_directory,
+        messages: Vec::new(),
+        created_at: header.created_at,
+        last_updated: header.last_updated,
+    };
+
+    for (index, line) in lines.enumerate() {
+        let line = line.map_err(|e| AppError::StorageError {
+            message: format!("Failed to read session log: {}", e),
+        })?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: SessionLogRecord = match serde_json::from_str(&line) {
+            Ok(record) => record,
+            Err(e) => {
+                tracing::warn!(
+                    "Skipping unreadable record {} of {}: {}",
+                    index + 2,
+                    path.display(),
+                    e
+                );
+                continue;
+            }
+        };
+
+        if record.timestamp() > session.last_updated.as_str() {
+            session.last_updated = record.timestamp().to_string();
+        }
+        match record {
+            SessionLogRecord::Message(message) => session.messages.push(message),
+            SessionLogRecord::Response {
+                message_id,
+                response,
+                feedback,
+                ..
+            } => {
+                let target = session
+                    .messages
+                    .iter_mut()
+                    .rev()
+                    .find(|message| match &message_id {
+                        Some(id) => &message.id == id,
+                        None => message.role == "assistant",
+                    });
+                if let Some(message) = target {
+                    message.response = Some(response);
+                    message.feedback = feedback;
+                }
+            }
+        }
+    }
+
+    Ok(session)
+}
+
+/// Render a complete session log with one message record per message
+pub fn render_session_log(session: &ConversationSession) -> Result<String, AppError> {
+    let mut records = String::new();
+    for message in &session.messages {
+        records.push_str(&to_line(&SessionLogRecord::Message(message.clone()))?);
+    }
+    let header = SessionLogHeader {
+        records_length: Some(records.len() as u64),
+        ..SessionLogHeader::for_session(session)
+    };
+    Ok(to_line(&header)? + &records)
+}
+
+/// Get the length in bytes of the records written with the header of a log
+/// and of those appended since, reading only the header
+///
+/// Logs written by older versions count all their records as appended.
+pub fn session_log_growth(path: &Path) -> Result<(u64, u64), AppError> {
+    let io_error = |e: std::io::Error| AppError::StorageError {
+        message: format!("Failed to read session log: {}", e),
+    };
+    let file = fs::File::open(path).map_err(io_error)?;
+    let length = file.metadata().map_err(io_error)?.len();
+
+    let mut header_line = String::new();
+    BufReader::new(file)
+        .read_line(&mut header_line)
+        .map_err(io_error)?;
+    let header: SessionLogHeader =
+        serde_json::from_str(&header_line).map_err(|e| AppError::DeserializationError {
+            message: format!("Failed to deserialize session log header: {}", e),
+        })?;
+
+    let written = header.records_length.unwrap_or(0);
+    let appended = length.saturating_sub(header_line.len() as u64 + written);
+    Ok((written, appended))
+}
+
+/// Write a complete session log, replacing any existing one atomically
+///
+/// This is also how a log is compacted: response records are folded into
+/// their messages.
+pub fn write_session_log(path: &Path, session: &ConversationSession) -> Result<(), AppError> {
+    let content = render_session_log(session)?;
+    write_atomic(path, content.as_bytes()).map_err(|e| AppError::StorageError {
+        message: format!("Failed to write session log: {}", e),
+    })
+}
+
+/// Append records to an existing session log
+///
+/// Only the new lines are written and flushed. If the previous write was
+/// torn, its partial line is terminated first so it cannot swallow the new
+/// records.
+pub fn append_session_log(path: &Path, records: &[SessionLogRecord]) -> Result<(), AppError> {
+    let io_error = |e: std::io::Error| AppError::StorageError {
+        message: format!("Failed to append to session log: {}", e),
+    };
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .open(path)
+        .map_err(io_error)?;
+
+    let mut content = String::new();
+    if file.seek(SeekFrom::End(0)).map_err(io_error)? > 0 {
+        let mut last = [0u8; 1];
+        file.seek(SeekFrom::End(-1)).map_err(io_error)?;
+        file.read_exact(&mut last).map_err(io_error)?;
+        if last[0] != b'\n' {
+            content.push('\n');
+        }
+    }
+    for record in records {
+        content.push_str(&to_line(record)?);
+    }
+
+    file.write_all(content.as_bytes()).map_err(io_error)?;
+    file.sync_data().map_err(io_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, role: &str, timestamp: &str) -> ConversationMessage {
+        ConversationMessage {
+            id: id.to_string(),
+            role: role.to_string(),
+            content: format!("{} says hi", role),
+            timestamp: timestamp.to_string(),
+            response: None,
+            feedback: None,
+        }
+    }
+
+    #[test]
+    fn test_append_and_fold_responses() {
+        let dir = std::env::temp_dir().join(format!("ifm-ruta-log-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.jsonl");
+
+        let session = ConversationSession {
+            session_id: "session".to_string(),
+            project_directory: PathBuf::from("/project"),
+            messages: vec![message("m1", "assistant", "2026-10-18T09:00:00+00:00")],
+            created_at: "2026-10-18T09:00:00+00:00".to_string(),
+            last_updated: "2026-10-18T09:00:00+00:00".to_string(),
+        };
+        write_session_log(&path, &session).unwrap();
+
+        let timing = ResponseTiming {
+            time_to_first_keystroke_ms: Some(500),
+            time_to_submit_ms: 4000,
+            cancelled: false,
+        };
+        append_session_log(
+            &path,
+            &[
+                SessionLogRecord::Message(message("m2", "assistant", "2026-10-18T09:05:00+00:00")),
+                SessionLogRecord::Response {
+                    message_id: None,
+                    response: timing.clone(),
+                    feedback: None,
+                    at: "2026-10-18T09:06:00+00:00".to_string(),
+                },
+            ],
+        )
+        .unwrap();
+
+        let (written, appended) = session_log_growth(&path).unwrap();
+        assert_eq!(
+            written,
+            render_session_log(&session)
+                .unwrap()
+                .lines()
+                .skip(1)
+                .map(|line| line.len() as u64 + 1)
+                .sum::<u64>()
+        );
+        assert!(appended > written);
+
+        let read = read_session_log(&path).unwrap();
+        assert_eq!(read.messages.len(), 2);
+        assert_eq!(read.messages[0].response, None);
+        assert_eq!(read.messages[1].response, Some(timing));
+        assert_eq!(read.last_updated, "2026-10-18T09:06:00+00:00");
+
+        // Compacting folds the response record into its message
+        write_session_log(&path, &read).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+        assert_eq!(read_session_log(&path).unwrap().messages, read.messages);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_torn_record_is_skipped() {
+        let dir = std::env::temp_dir().join(format!("ifm-ruta-log-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.jsonl");
+
+        let session = ConversationSession {
+            session_id: "session".to_string(),
+            project_directory: PathBuf::from("/project"),
+            messages: vec![message("m1", "user", "2026-10-18T09:00:00+00:00")],
+            created_at: "2026-10-18T09:00:00+00:00".to_string(),
+            last_updated: "2026-10-18T09:00:00+00:00".to_string(),
+        };
+        write_session_log(&path, &session).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"type":"message","id":"m2","ro"#)
+            .unwrap();
+
+        append_session_log(
+            &path,
+            &[SessionLogRecord::Message(message(
+                "m3",
+                "user",
+                "2026-10-18T09:10:00+00:00",
+            ))],
+        )
+        .unwrap();
+
+        let ids: Vec<String> = read_session_log(&path)
+            .unwrap()
+            .messages
+            .into_iter()
+            .map(|message| message.id)
+            .collect();
+        assert_eq!(ids, vec!["m1", "m3"]);
+
+        fs::write(&path, "not a header\n").unwrap();
+        assert!(matches!(
+            read_session_log(&path),
+            Err(AppError::DeserializationError { .. })
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,14 +1,17 @@
 //! Settings manager implementation
 
+use serde_json::{Map, Value};
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use crate::models::AppSettings;
-use crate::traits::{SettingsError, SettingsManager};
+use crate::traits::{Event, EventBus, EventType, SettingsError, SettingsManager};
 
 /// Settings manager implementation
 pub struct SettingsManagerImpl {
     settings: AppSettings,
     settings_path: PathBuf,
+    event_bus: Option<Arc<dyn EventBus>>,
 }
 
 impl Default for SettingsManagerImpl {
@@ -24,6 +27,57 @@ impl SettingsManagerImpl {
         Self {
             settings: AppSettings::default(),
             settings_path,
+            event_bus: None,
+        }
+    }
+
+    /// Use a different settings file
+    pub fn with_path(mut self, settings_path: PathBuf) -> Self {
+        self.settings_path = settings_path;
+        self
+    }
+
+    /// Publish `SettingsChanged` events to an event bus
+    pub fn with_event_bus(mut self, event_bus: Arc<dyn EventBus>) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Get the current settings
+    pub fn settings(&self) -> &AppSettings {
+        &self.settings
+    }
+
+    /// Replace the current settings and save them
+    pub fn update_settings(&mut self, settings: AppSettings) -> Result<(), SettingsError> {
+        let previous = std::mem::replace(&mut self.settings, settings);
+        self.save_settings()?;
+        self.publish_changes("update", &previous, &self.settings);
+        Ok(())
+    }
+
+    /// Publish the difference between two settings, if there is any
+    fn publish_changes(&self, reason: &str, old: &AppSettings, new: &AppSettings) {
+        let Some(event_bus) = &self.event_bus else {
+            return;
+        };
+
+        let changes = settings_diff(old, new);
+        if changes.is_empty() {
+            return;
+        }
+
+        let event = Event::new(
+            EventType::SettingsChanged,
+            "settings_manager",
+            serde_json::json!({
+                "reason": reason,
+                "path": self.settings_path,
+                "changes": changes,
+            }),
+        );
+        if let Err(e) = event_bus.publish(event) {
+            tracing::error!("Failed to publish settings change: {}", e);
         }
     }
 
@@ -40,7 +94,8 @@ impl SettingsManager for SettingsManagerImpl {
     fn load_settings(&mut self) -> Result<(), SettingsError> {
         if self.settings_path.exists() {
             let content = std::fs::read_to_string(&self.settings_path)?;
-            self.settings = toml::from_str(&content)?;
+            let previous = std::mem::replace(&mut self.settings, toml::from_str(&content)?);
+            self.publish_changes("load", &previous, &self.settings);
         }
         Ok(())
     }
@@ -60,6 +115,125 @@ impl SettingsManager for SettingsManagerImpl {
         let default_settings = AppSettings::default();
         let content = toml::to_string_pretty(&default_settings)?;
         std::fs::write(&self.settings_path, content)?;
+        self.publish_changes("reset", &self.settings, &default_settings);
         Ok(())
     }
 }
+
+/// Compute the changed settings as a map from dotted key to old and new value
+///
+/// # Example
+/// `{"ui.theme": {"old": "dark", "new": "light"}}`
+pub fn settings_diff(old: &AppSettings, new: &AppSettings) -> Map<String, Value> {
+    let mut changes = Map::new();
+    let old = serde_json::to_value(old).unwrap_or_default();
+    let new = serde_json::to_value(new).unwrap_or_default();
+    diff_values("", &old, &new, &mut changes);
+    changes
+}
+
+fn diff_values(path: &str, old: &Value, new: &Value, changes: &mut Map<String, Value>) {
+    match (old, new) {
+        (Value::Object(old_fields), Value::Object(new_fields)) => {
+            let keys = old_fields.keys().chain(
+                new_fields
+                    .keys()
+                    .filter(|key| !old_fields.contains_key(*key)),
+            );
+            for key in keys {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                diff_values(
+                    &child,
+                    old_fields.get(key).unwrap_or(&Value::Null),
+                    new_fields.get(key).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        _ if old != new => {
+            changes.insert(
+                path.to_string(),
+                serde_json::json!({ "old": old, "new": new }),
+            );
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_diff() {
+        let old = AppSettings::default();
+        assert!(settings_diff(&old, &old).is_empty());
+
+        let mut new = old.clone();
+        new.security.sandbox_mode = !old.security.sandbox_mode;
+        new.security.allowed_commands.push("cargo".to_string());
+
+        let changes = settings_diff(&old, &new);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            changes["security.sandbox_mode"],
+            serde_json::json!({
+                "old": old.security.sandbox_mode,
+                "new": new.security.sandbox_mode
+            })
+        );
+        assert!(changes.contains_key("security.allowed_commands"));
+    }
+
+    #[test]
+    fn test_missing_allowlist_gets_defaults() {
+        let mut value = toml::Value::try_from(AppSettings::default()).unwrap();
+        value["security"]
+            .as_table_mut()
+            .unwrap()
+            .remove("allowed_commands");
+
+        let settings: AppSettings = toml::from_str(&toml::to_string(&value).unwrap()).unwrap();
+        assert_eq!(
+            settings.security.allowed_commands,
+            AppSettings::default().security.allowed_commands
+        );
+    }
+
+    #[test]
+    fn test_update_publishes_settings_changed() {
+        let dir = std::env::temp_dir().join(format!("ifm-ruta-settings-{}", uuid::Uuid::new_v4()));
+        let event_bus = Arc::new(crate::services::EventBusImpl::new());
+        let mut receiver = event_bus.receiver();
+        let mut manager = SettingsManagerImpl::new()
+            .with_path(dir.join("settings.toml"))
+            .with_event_bus(event_bus);
+
+        let mut settings = manager.settings().clone();
+        manager.update_settings(settings.clone()).unwrap();
+        assert!(receiver.try_recv().is_err());
+
+        settings.security.sandbox_mode = !settings.security.sandbox_mode;
+        manager.update_settings(settings).unwrap();
+
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::SettingsChanged);
+        assert_eq!(event.data["reason"], "update");
+        assert_eq!(
+            event.data["changes"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["security.sandbox_mode"]
+        );
+
+        // Loading the saved settings back is not a change
+        manager.load_settings().unwrap();
+        assert!(receiver.try_recv().is_err());
+    }
+}
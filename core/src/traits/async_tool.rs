@@ -61,6 +61,14 @@ pub trait AsyncTool: Send + Sync {
     /// Execute the tool with given arguments
     async fn execute(&self, args: Value) -> MCPResult<ToolResponse>;
 
+    /// Execute the tool with the `_meta` object of the request
+    ///
+    /// The metadata is kept out of the arguments so they still match the
+    /// input schema. The default implementation ignores it.
+    async fn execute_with_meta(&self, args: Value, _meta: Value) -> MCPResult<ToolResponse> {
+        self.execute(args).await
+    }
+
     /// Get tool metadata
     fn metadata(&self) -> ToolMetadata;
 
@@ -0,0 +1,97 @@
+//! Conversation storage interface
+
+use std::path::PathBuf;
+
+use crate::models::{AppError, Feedback, RetentionPolicy};
+use crate::services::{CompactionReport, ConversationMessage, ConversationSession, RecoveryReport};
+
+/// Storage of the user-agent conversations of a project
+///
+/// Implementations must be safe to use from several processes at once (the
+/// MCP server and the GUI share a project), at least for `add_message` and
+/// `record_feedback`, which must never lose a concurrent writer's messages,
+/// and for `trim_session` and `compact`, which must check and change a
+/// session under one lock or transaction.
+pub trait ConversationStore: Send + Sync {
+    /// Load a session
+    fn load_session(&self, session_id: &str) -> Result<Option<ConversationSession>, AppError>;
+
+    /// Store a session, replacing any stored version
+    fn save_session(&self, session: &ConversationSession) -> Result<(), AppError>;
+
+    /// Remove a session and its attachments, returning whether it existed
+    fn remove_session(&self, session_id: &str) -> Result<bool, AppError>;
+
+    /// Get all sessions, most recently updated first
+    fn list_sessions(&self) -> Result<Vec<ConversationSession>, AppError>;
+
+    /// Add a message to a session, creating the session if needed
+    ///
+    /// Returns the id of the new message.
+    fn add_message(&self, session_id: &str, role: &str, content: &str) -> Result<String, AppError>;
+
+    /// Record the user's feedback on the latest assistant message of a
+    /// session
+    fn record_feedback(&self, session_id: &str, feedback: &Feedback) -> Result<(), AppError>;
+
+    /// Store a file attached to a session, returning where it was stored
+    fn save_attachment(
+        &self,
+        session_id: &str,
+        file_name: &str,
+        data: &[u8],
+    ) -> Result<PathBuf, AppError>;
+
+    /// Get the last `limit` messages of a session, oldest first
+    fn recent_messages(
+        &self,
+        session_id: &str,
+        limit: usize,
+    ) -> Result<Vec<ConversationMessage>, AppError> {
+        let mut messages = self
+            .load_session(session_id)?
+            .map(|session| session.messages)
+            .unwrap_or_default();
+        let skip = messages.len().saturating_sub(limit);
+        Ok(messages.split_off(skip))
+    }
+
+    /// Get the most recently updated sessions, newest first
+    fn latest_sessions(&self, limit: usize) -> Result<Vec<ConversationSession>, AppError> {
+        let mut sessions = self.list_sessions()?;
+        sessions.truncate(limit);
+        Ok(sessions)
+    }
+
+    /// Drop the oldest messages of a session beyond `max_messages`
+    fn trim_session(&self, session_id: &str, max_messages: usize) -> Result<(), AppError>;
+
+    /// Enforce a retention policy
+    ///
+    /// See `plan_compaction` for what is removed. With `dry_run` nothing is
+    /// changed and the report tells what would have been removed.
+    fn compact(
+        &self,
+        policy: &RetentionPolicy,
+        dry_run: bool,
+    ) -> Result<CompactionReport, AppError>;
+
+    /// Quarantine corrupt sessions and clean up after interrupted writes
+    ///
+    /// Stores a crash cannot leave corrupt have nothing to recover.
+    fn recover(&self) -> Result<RecoveryReport, AppError> {
+        Ok(RecoveryReport::default())
+    }
+
+    /// Get the stored copies of corrupt sessions, oldest first
+    fn quarantined_sessions(&self) -> Result<Vec<PathBuf>, AppError> {
+        Ok(Vec::new())
+    }
+
+    /// Convert sessions stored in older formats to the current one
+    ///
+    /// Returns the number of sessions converted.
+    fn migrate(&self) -> Result<usize, AppError> {
+        Ok(0)
+    }
+}
@@ -1,25 +1,38 @@
 //! Event system interface
 
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::result::Result;
+use std::sync::Arc;
 use std::time::SystemTime;
 
 /// Event bus interface
-pub trait EventBus {
+pub trait EventBus: Send + Sync {
     /// Subscribe to events of a specific type
-    fn subscribe(&mut self, event_type: EventType, listener: Box<dyn EventListener>);
+    ///
+    /// Each listener handles its events in publish order, independently of
+    /// the other listeners.
+    fn subscribe(
+        &self,
+        event_type: EventType,
+        listener: Arc<dyn EventListener>,
+    ) -> Result<(), EventError>;
 
     /// Unsubscribe from events
-    fn unsubscribe(&mut self, event_type: EventType, listener_id: &str);
+    fn unsubscribe(&self, event_type: EventType, listener_id: &str);
 
     /// Publish an event
+    ///
+    /// Delivery is asynchronous; publishing never waits for listeners.
     fn publish(&self, event: Event) -> Result<(), EventError>;
 }
 
 /// Event listener interface
-pub trait EventListener {
+#[async_trait]
+pub trait EventListener: Send + Sync {
     /// Handle an event
-    fn handle_event(&self, event: &Event) -> Result<(), EventError>;
+    async fn handle_event(&self, event: &Event) -> Result<(), EventError>;
 
     /// Get the listener ID
     fn listener_id(&self) -> &str;
@@ -34,8 +47,44 @@ pub struct Event {
     pub source: String,
 }
 
+impl Event {
+    /// Create an event timestamped now
+    pub fn new(event_type: EventType, source: impl Into<String>, data: Value) -> Self {
+        Self {
+            event_type,
+            data,
+            timestamp: SystemTime::now(),
+            source: source.into(),
+        }
+    }
+
+    /// Convert to JSON with an RFC 3339 timestamp
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({
+            "event_type": self.event_type,
+            "source": self.source,
+            "timestamp": chrono::DateTime::<chrono::Utc>::from(self.timestamp)
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "data": self.data,
+        })
+    }
+
+    /// Parse an event converted with `to_json`
+    pub fn from_json(value: &Value) -> Option<Self> {
+        let timestamp = chrono::DateTime::parse_from_rfc3339(value.get("timestamp")?.as_str()?)
+            .ok()?
+            .with_timezone(&chrono::Utc);
+        Some(Self {
+            event_type: serde_json::from_value(value.get("event_type")?.clone()).ok()?,
+            data: value.get("data").cloned().unwrap_or(Value::Null),
+            timestamp: timestamp.into(),
+            source: value.get("source")?.as_str()?.to_string(),
+        })
+    }
+}
+
 /// Event types
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum EventType {
     ToolExecuted,
     ProcessSpawned,
@@ -2,6 +2,7 @@
 
 pub mod async_tool;
 pub mod command;
+pub mod conversation_store;
 pub mod event;
 pub mod process;
 pub mod settings;
@@ -11,6 +12,7 @@ pub mod validation; // NEW for Phase 1: Async architecture
 // Re-export all traits
 pub use async_tool::*;
 pub use command::*;
+pub use conversation_store::*;
 pub use event::*;
 pub use process::*;
 pub use settings::*;
@@ -1,9 +1,14 @@
 //! Process management interface
 
-use std::path::Path;
+use async_trait::async_trait;
+use futures::stream::{Stream, StreamExt};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::result::Result;
 use std::time::Duration;
 
+use crate::traits::ToolStream;
+
 /// Process management interface
 pub trait ProcessManager {
     /// Spawn a new process
@@ -22,6 +27,9 @@ pub trait ProcessManager {
 
     /// Get process output (stdout/stderr)
     fn get_process_output(&self, handle: &ProcessHandle) -> Result<ProcessOutput, ProcessError>;
+
+    /// List tracked processes with their live resource usage
+    fn list_processes(&self) -> Vec<ProcessInfo>;
 }
 
 /// Process handle for tracking spawned processes
@@ -43,6 +51,17 @@ pub enum ProcessStatus {
     Killed,
 }
 
+impl std::fmt::Display for ProcessStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProcessStatus::Running => write!(f, "running"),
+            ProcessStatus::Completed => write!(f, "completed"),
+            ProcessStatus::Failed => write!(f, "failed"),
+            ProcessStatus::Killed => write!(f, "killed"),
+        }
+    }
+}
+
 /// Process execution result
 #[derive(Debug, Clone)]
 pub struct ProcessResult {
@@ -60,6 +79,19 @@ pub struct ProcessOutput {
     pub is_complete: bool,
 }
 
+/// Live resource usage of a tracked process
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub handle: ProcessHandle,
+    pub pid: u32,
+    /// CPU usage in percent of one core since the previous listing
+    pub cpu_usage: f32,
+    /// Resident set size in bytes (zero once the process exited)
+    pub memory_bytes: u64,
+    /// Wall-clock time since spawn, or the total run time once exited
+    pub elapsed: Duration,
+}
+
 /// Process management error
 #[derive(Debug, thiserror::Error)]
 pub enum ProcessError {
@@ -81,3 +113,154 @@ pub enum ProcessError {
     #[error("Internal error: {0}")]
     InternalError(#[from] anyhow::Error),
 }
+
+/// Specification of a process to spawn
+#[derive(Debug, Clone, Default)]
+pub struct ProcessSpec {
+    pub command: String,
+    pub args: Vec<String>,
+    /// Working directory override (defaults to the current directory)
+    pub cwd: Option<PathBuf>,
+    /// Additional environment variables
+    pub env: HashMap<String, String>,
+    /// Start from an empty environment instead of inheriting the parent's
+    pub env_clear: bool,
+    /// Keep stdin open for `write_stdin` instead of connecting it to null
+    pub stdin: bool,
+    /// Resource limits applied to the child (Linux only)
+    pub limits: Option<ResourceLimits>,
+}
+
+impl ProcessSpec {
+    /// Create a new process specification
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Append an argument
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Append multiple arguments
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set the working directory
+    pub fn cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Set an environment variable
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// Do not inherit the environment of the parent process
+    pub fn env_clear(mut self) -> Self {
+        self.env_clear = true;
+        self
+    }
+
+    /// Keep stdin open for writing
+    pub fn with_stdin(mut self) -> Self {
+        self.stdin = true;
+        self
+    }
+
+    /// Apply resource limits to the child
+    pub fn limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+}
+
+/// Per-process resource limits, applied as rlimits on Linux
+///
+/// Limits are inherited by descendants; the process count is counted per
+/// user by the kernel.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourceLimits {
+    /// CPU time (`RLIMIT_CPU`)
+    pub cpu_time: Option<Duration>,
+    /// Address space in bytes (`RLIMIT_AS`)
+    pub address_space: Option<u64>,
+    /// Open file descriptors (`RLIMIT_NOFILE`)
+    pub open_files: Option<u64>,
+    /// Processes of the user (`RLIMIT_NPROC`)
+    ///
+    /// The kernel compares this with every process the user runs, not just
+    /// the descendants of the limited process, and does not enforce it for
+    /// root.
+    pub processes: Option<u64>,
+}
+
+/// Event emitted by a streaming process
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessEvent {
+    /// A line written to stdout (without the trailing newline)
+    Stdout(String),
+    /// A line written to stderr (without the trailing newline)
+    Stderr(String),
+    /// The process exited; always the last event of the stream
+    Exited { exit_code: i32, duration: Duration },
+}
+
+/// Stream of process events
+pub type ProcessEventStream = Box<dyn Stream<Item = ProcessEvent> + Send + Unpin>;
+
+/// A process spawned with streaming output
+pub struct StreamingProcess {
+    pub handle: ProcessHandle,
+    pub events: ProcessEventStream,
+}
+
+/// Async process management interface with streaming output
+#[async_trait]
+pub trait AsyncProcessManager: Send + Sync {
+    /// Spawn a process and stream its output line by line
+    async fn spawn_streaming(&self, spec: ProcessSpec) -> Result<StreamingProcess, ProcessError>;
+
+    /// Write data to the stdin of a process spawned with `ProcessSpec::with_stdin`
+    async fn write_stdin(&self, handle: &ProcessHandle, data: &[u8]) -> Result<(), ProcessError>;
+
+    /// Close stdin so the process sees end of input
+    async fn close_stdin(&self, handle: &ProcessHandle) -> Result<(), ProcessError>;
+
+    /// Kill a running process
+    async fn kill(&self, handle: &ProcessHandle) -> Result<(), ProcessError>;
+}
+
+/// Adapt process events to a tool stream
+///
+/// Output lines become chunks; a non-zero exit status ends the stream with
+/// an error.
+pub fn process_events_to_tool_stream(events: ProcessEventStream) -> ToolStream {
+    Box::new(
+        events
+            .filter_map(|event| async move {
+                match event {
+                    ProcessEvent::Stdout(line) | ProcessEvent::Stderr(line) => {
+                        Some(Ok(format!("{}\n", line)))
+                    }
+                    ProcessEvent::Exited { exit_code: 0, .. } => None,
+                    ProcessEvent::Exited { exit_code, .. } => {
+                        Some(Err(format!("Process exited with code {}", exit_code)))
+                    }
+                }
+            })
+            .boxed(),
+    )
+}
@@ -4,7 +4,7 @@ use serde_json::Value;
 use std::result::Result;
 
 /// Core tool interface for MCP tools
-pub trait Tool {
+pub trait Tool: Send + Sync {
     /// Get the tool name
     fn name(&self) -> &str;
 
@@ -0,0 +1,90 @@
+//! Crash-safe file replacement
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Suffix of the temporary files written by `write_atomic`
+pub const TEMP_FILE_SUFFIX: &str = "tmp";
+
+/// Replace a file atomically
+///
+/// The contents are written to a unique temporary file next to `path`,
+/// flushed to disk and renamed over `path`, so readers and crashes only ever
+/// see the old or the new contents. The directory is synced afterwards so the
+/// rename itself survives a crash.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let temp = temp_path(path);
+    let result = write_synced(&temp, contents).and_then(|_| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+        return result;
+    }
+
+    if let Some(parent) = path.parent() {
+        sync_directory(parent)?;
+    }
+    Ok(())
+}
+
+/// Check whether a path is a temporary file left by `write_atomic`
+pub fn is_temp_file(path: &Path) -> bool {
+    path.extension().and_then(|s| s.to_str()) == Some(TEMP_FILE_SUFFIX)
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(
+        "{}.{}.{}",
+        file_name,
+        &uuid::Uuid::new_v4().simple().to_string()[..8],
+        TEMP_FILE_SUFFIX
+    ))
+}
+
+fn write_synced(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+#[cfg(unix)]
+fn sync_directory(directory: &Path) -> std::io::Result<()> {
+    File::open(directory)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_directory(_directory: &Path) -> std::io::Result<()> {
+    // Directories cannot be opened for syncing on Windows; the rename is
+    // durable once the file system flushes its metadata
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic_replaces_file() {
+        let dir = std::env::temp_dir().join(format!("ifm-ruta-atomic-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.json");
+
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        let leftovers: Vec<PathBuf> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| is_temp_file(path))
+            .collect();
+        assert!(leftovers.is_empty());
+        assert!(is_temp_file(&temp_path(&path)));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
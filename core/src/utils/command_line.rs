@@ -0,0 +1,120 @@
+//! Command line parsing utilities
+
+/// Split a command line into program and arguments
+///
+/// Supports whitespace separation, single and double quotes and backslash
+/// escapes. A backslash only escapes a quote, another backslash or
+/// whitespace, so Windows paths such as `C:\Tools\app.exe` are kept as
+/// they are. No shell expansion is performed.
+pub fn split_command_line(input: &str) -> Result<Vec<String>, String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut in_part = false;
+    let mut quote: Option<char> = None;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => match chars.peek() {
+                Some(&next) if is_escapable(quote, next) => {
+                    current.push(next);
+                    chars.next();
+                    in_part = true;
+                }
+                Some(_) => {
+                    current.push(c);
+                    in_part = true;
+                }
+                None => return Err("Trailing backslash in command line".to_string()),
+            },
+            (Some(_), c) => current.push(c),
+            (None, '"') | (None, '\'') => {
+                quote = Some(c);
+                in_part = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_part {
+                    parts.push(std::mem::take(&mut current));
+                    in_part = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_part = true;
+            }
+        }
+    }
+
+    if quote.is_some() {
+        return Err("Unterminated quote in command line".to_string());
+    }
+
+    if in_part {
+        parts.push(current);
+    }
+
+    if parts.is_empty() {
+        return Err("Command line is empty".to_string());
+    }
+
+    Ok(parts)
+}
+
+/// Whether a backslash before `c` escapes it
+fn is_escapable(quote: Option<char>, c: char) -> bool {
+    match quote {
+        Some(_) => c == '"' || c == '\\',
+        None => c == '"' || c == '\'' || c == '\\' || c.is_whitespace(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_simple() {
+        assert_eq!(
+            split_command_line("cargo test --workspace").unwrap(),
+            vec!["cargo", "test", "--workspace"]
+        );
+    }
+
+    #[test]
+    fn test_split_quotes() {
+        assert_eq!(
+            split_command_line(r#"git commit -m "fix the build" 'a b'"#).unwrap(),
+            vec!["git", "commit", "-m", "fix the build", "a b"]
+        );
+        assert_eq!(split_command_line(r#"echo """#).unwrap(), vec!["echo", ""]);
+    }
+
+    #[test]
+    fn test_split_escapes() {
+        assert_eq!(
+            split_command_line(r"ls my\ dir").unwrap(),
+            vec!["ls", "my dir"]
+        );
+        assert_eq!(
+            split_command_line(r#"echo \"quoted\" "a \"b\" \\ c""#).unwrap(),
+            vec!["echo", "\"quoted\"", "a \"b\" \\ c"]
+        );
+    }
+
+    #[test]
+    fn test_split_windows_paths() {
+        assert_eq!(
+            split_command_line(r#"C:\Tools\app.exe --out "D:\build dir\out""#).unwrap(),
+            vec![r"C:\Tools\app.exe", "--out", r"D:\build dir\out"]
+        );
+    }
+
+    #[test]
+    fn test_split_errors() {
+        assert!(split_command_line("").is_err());
+        assert!(split_command_line("   ").is_err());
+        assert!(split_command_line("echo 'open").is_err());
+        assert!(split_command_line("echo \\").is_err());
+    }
+}
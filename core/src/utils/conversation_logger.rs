@@ -1,42 +1,41 @@
 //! Conversation logging utility for tracking user-agent interactions
 
 use crate::models::AppError;
-use crate::services::ConversationStorage;
+use crate::services::{render_conversations, ConversationStorage};
+use crate::traits::ConversationStore;
 use std::path::Path;
+use std::sync::Arc;
 use uuid::Uuid;
 
 /// Conversation logger for tracking user-agent interactions
 pub struct ConversationLogger {
-    storage: ConversationStorage,
+    store: Arc<dyn ConversationStore>,
     current_session_id: String,
 }
 
 impl ConversationLogger {
-    /// Create a new conversation logger
+    /// Create a new conversation logger storing files in the project
     pub fn new(project_directory: &Path) -> Self {
-        let storage = ConversationStorage::new(project_directory);
-        let current_session_id = Uuid::new_v4().to_string();
+        Self::with_store(Arc::new(ConversationStorage::new(project_directory)))
+    }
 
+    /// Create a new conversation logger on a conversation store
+    pub fn with_store(store: Arc<dyn ConversationStore>) -> Self {
         Self {
-            storage,
-            current_session_id,
+            store,
+            current_session_id: Uuid::new_v4().to_string(),
         }
     }
 
-    /// Initialize the logger
-    pub fn initialize(&self) -> Result<(), AppError> {
-        self.storage.initialize()
-    }
-
-    /// Log a user message
-    pub fn log_user_message(&self, content: &str) -> Result<(), AppError> {
-        self.storage
+    /// Log a user message, returning its id
+    pub fn log_user_message(&self, content: &str) -> Result<String, AppError> {
+        self.store
             .add_message(&self.current_session_id, "user", content)
     }
 
-    /// Log an assistant message
-    pub fn log_assistant_message(&self, content: &str) -> Result<(), AppError> {
-        self.storage
+    /// Log an assistant message, returning its id
+    pub fn log_assistant_message(&self, content: &str) -> Result<String, AppError> {
+        self.store
             .add_message(&self.current_session_id, "assistant", content)
     }
 
@@ -52,7 +51,7 @@ impl ConversationLogger {
 
     /// Get conversation history (latest 5)
     pub fn get_conversation_history(&self) -> Result<String, AppError> {
-        self.storage.get_latest_5_conversations()
+        Ok(render_conversations(&self.store.latest_sessions(5)?))
     }
 }
 
@@ -63,9 +62,6 @@ static mut CONVERSATION_LOGGER: Option<ConversationLogger> = None;
 pub fn init_conversation_logger(project_directory: &Path) -> Result<(), AppError> {
     unsafe {
         CONVERSATION_LOGGER = Some(ConversationLogger::new(project_directory));
-        if let Some(ref logger) = CONVERSATION_LOGGER {
-            logger.initialize()?;
-        }
     }
     Ok(())
 }
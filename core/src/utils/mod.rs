@@ -1,14 +1,20 @@
 //! Core utilities
 
+pub mod atomic_write;
+pub mod command_line;
 pub mod conversation_logger;
 pub mod error_handling;
 pub mod logging;
+pub mod process_table;
 pub mod serialization;
 pub mod validator; // NEW for Phase 1: Input validation
 
 // Re-export all utilities
+pub use atomic_write::*;
+pub use command_line::*;
 pub use conversation_logger::*;
 pub use error_handling::*;
 pub use logging::*;
+pub use process_table::*;
 pub use serialization::*;
 pub use validator::*; // NEW for Phase 1
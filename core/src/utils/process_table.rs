@@ -0,0 +1,139 @@
+//! Process table formatting utilities
+
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::traits::ProcessInfo;
+
+/// Format a byte count with a binary unit (e.g. `12.3 MiB`)
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    format!("{:.1} {}", value, unit)
+}
+
+/// Format an elapsed duration as `1h02m03s`, `2m03s` or `3.4s`
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs >= 3600 {
+        format!("{}h{:02}m{:02}s", secs / 3600, secs % 3600 / 60, secs % 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{:.1}s", elapsed.as_secs_f64())
+    }
+}
+
+/// Get the command line of a process as typed
+pub fn process_command_line(info: &ProcessInfo) -> String {
+    std::iter::once(info.handle.command.as_str())
+        .chain(info.handle.args.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Convert a process table entry to JSON
+pub fn process_info_json(info: &ProcessInfo) -> Value {
+    json!({
+        "id": info.handle.id,
+        "pid": info.pid,
+        "command": process_command_line(info),
+        "cwd": info.handle.cwd,
+        "status": info.handle.status.to_string(),
+        "cpuPercent": info.cpu_usage,
+        "memoryBytes": info.memory_bytes,
+        "elapsedSeconds": info.elapsed.as_secs_f64(),
+    })
+}
+
+/// Render a process table as aligned plain text
+pub fn render_process_table(processes: &[ProcessInfo]) -> String {
+    if processes.is_empty() {
+        return "No processes launched.".to_string();
+    }
+
+    let mut table = format!(
+        "{:>7}  {:<9}  {:>6}  {:>10}  {:>9}  COMMAND\n",
+        "PID", "STATUS", "CPU%", "RSS", "ELAPSED"
+    );
+    for info in processes {
+        table.push_str(&format!(
+            "{:>7}  {:<9}  {:>6.1}  {:>10}  {:>9}  {}\n",
+            info.pid,
+            info.handle.status.to_string(),
+            info.cpu_usage,
+            format_bytes(info.memory_bytes),
+            format_elapsed(info.elapsed),
+            process_command_line(info)
+        ));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::{ProcessHandle, ProcessStatus};
+    use std::path::PathBuf;
+
+    fn info() -> ProcessInfo {
+        ProcessInfo {
+            handle: ProcessHandle {
+                id: "id-1".to_string(),
+                command: "cargo".to_string(),
+                args: vec!["test".to_string()],
+                cwd: PathBuf::from("/tmp"),
+                status: ProcessStatus::Running,
+            },
+            pid: 4242,
+            cpu_usage: 12.5,
+            memory_bytes: 3 * 1024 * 1024,
+            elapsed: Duration::from_secs(75),
+        }
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MiB");
+    }
+
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!(format_elapsed(Duration::from_millis(3400)), "3.4s");
+        assert_eq!(format_elapsed(Duration::from_secs(125)), "2m05s");
+        assert_eq!(format_elapsed(Duration::from_secs(3723)), "1h02m03s");
+    }
+
+    #[test]
+    fn test_process_info_json() {
+        let json = process_info_json(&info());
+        assert_eq!(json["command"], "cargo test");
+        assert_eq!(json["status"], "running");
+        assert_eq!(json["memoryBytes"], 3 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_render_process_table() {
+        let table = render_process_table(&[info()]);
+        assert!(table.starts_with("    PID"));
+        assert!(table.contains("4242  running"));
+        assert!(table.contains("3.0 MiB"));
+        assert!(table.ends_with("cargo test\n"));
+        assert_eq!(render_process_table(&[]), "No processes launched.");
+    }
+}
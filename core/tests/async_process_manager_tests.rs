@@ -0,0 +1,127 @@
+//! Async process manager tests with streaming output
+
+#[cfg(all(test, unix))]
+mod async_process_manager_tests {
+    use futures::StreamExt;
+    use ifm_ruta_core::services::ProcessManagerImpl;
+    use ifm_ruta_core::traits::{
+        process_events_to_tool_stream, AsyncProcessManager, ProcessEvent, ProcessSpec,
+    };
+    use std::time::Duration;
+
+    fn sh(script: &str) -> ProcessSpec {
+        ProcessSpec::new("sh").arg("-c").arg(script)
+    }
+
+    async fn collect(
+        events: &mut (impl futures::Stream<Item = ProcessEvent> + Unpin),
+    ) -> Vec<ProcessEvent> {
+        let mut collected = Vec::new();
+        while let Some(event) = tokio::time::timeout(Duration::from_secs(10), events.next())
+            .await
+            .unwrap()
+        {
+            collected.push(event);
+        }
+        collected
+    }
+
+    #[tokio::test]
+    async fn test_streams_lines_and_exit_status() {
+        let manager = ProcessManagerImpl::new();
+        let mut process = manager
+            .spawn_streaming(sh("echo one; echo two >&2; echo three; exit 4"))
+            .await
+            .unwrap();
+
+        let events = collect(&mut process.events).await;
+        let stdout: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                ProcessEvent::Stdout(line) => Some(line.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(stdout, vec!["one", "three"]);
+        assert!(events.contains(&ProcessEvent::Stderr("two".to_string())));
+        assert!(matches!(
+            events.last(),
+            Some(ProcessEvent::Exited { exit_code: 4, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_env_and_cwd_override() {
+        let manager = ProcessManagerImpl::new();
+        let cwd = std::env::temp_dir().canonicalize().unwrap();
+        let mut process = manager
+            .spawn_streaming(
+                sh("echo $IFM_TEST_VALUE; pwd")
+                    .env("IFM_TEST_VALUE", "hello")
+                    .cwd(&cwd),
+            )
+            .await
+            .unwrap();
+
+        let events = collect(&mut process.events).await;
+        assert_eq!(events[0], ProcessEvent::Stdout("hello".to_string()));
+        assert_eq!(
+            events[1],
+            ProcessEvent::Stdout(cwd.to_str().unwrap().to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_stdin() {
+        let manager = ProcessManagerImpl::new();
+        let mut process = manager
+            .spawn_streaming(ProcessSpec::new("cat").with_stdin())
+            .await
+            .unwrap();
+
+        manager
+            .write_stdin(&process.handle, b"ping\n")
+            .await
+            .unwrap();
+        let first = tokio::time::timeout(Duration::from_secs(10), process.events.next())
+            .await
+            .unwrap();
+        assert_eq!(first, Some(ProcessEvent::Stdout("ping".to_string())));
+
+        manager.close_stdin(&process.handle).await.unwrap();
+        let events = collect(&mut process.events).await;
+        assert!(matches!(
+            events.as_slice(),
+            [ProcessEvent::Exited { exit_code: 0, .. }]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_kill_streaming_process() {
+        let manager = ProcessManagerImpl::new();
+        let mut process = manager.spawn_streaming(sh("sleep 30")).await.unwrap();
+
+        manager.kill(&process.handle).await.unwrap();
+        let events = collect(&mut process.events).await;
+        assert!(matches!(
+            events.last(),
+            Some(ProcessEvent::Exited { exit_code: 137, duration }) if *duration < Duration::from_secs(10)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_tool_stream_adapter() {
+        let manager = ProcessManagerImpl::new();
+        let process = manager
+            .spawn_streaming(sh("echo building; exit 1"))
+            .await
+            .unwrap();
+
+        let chunks: Vec<_> = process_events_to_tool_stream(process.events)
+            .collect()
+            .await;
+        assert_eq!(chunks[0], Ok("building\n".to_string()));
+        assert_eq!(chunks[1], Err("Process exited with code 1".to_string()));
+        assert_eq!(chunks.len(), 2);
+    }
+}
@@ -0,0 +1,793 @@
+//! Conversation storage integration tests
+
+#[cfg(test)]
+mod conversation_storage_tests {
+    use ifm_ruta_core::models::{
+        AppError, AppSettings, Feedback, ProjectSettings, RetentionPolicy, SessionFormat,
+    };
+    use ifm_ruta_core::services::{ConversationStorage, ResponseTiming, RetentionReason};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    // Create an isolated project directory for a test
+    fn temp_project_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ifm-ruta-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_save_attachment() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+
+        let path = storage
+            .save_attachment("session-1", "screenshot.png", b"png-bytes")
+            .unwrap();
+
+        assert!(path.starts_with(project_dir.join(".ifm-ruta").join("conversations")));
+        assert!(path
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .ends_with("-screenshot.png"));
+        assert_eq!(std::fs::read(&path).unwrap(), b"png-bytes");
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_save_attachment_sanitizes_name() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+
+        let path = storage
+            .save_attachment("session-1", "../../etc/passwd", b"data")
+            .unwrap();
+
+        assert_eq!(path.parent().unwrap(), storage.attachments_dir("session-1"));
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_list_attachments() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+
+        assert!(storage.list_attachments("session-1").unwrap().is_empty());
+
+        storage
+            .save_attachment("session-1", "a.log", b"first")
+            .unwrap();
+        storage
+            .save_attachment("session-1", "a.log", b"second")
+            .unwrap();
+
+        assert_eq!(storage.list_attachments("session-1").unwrap().len(), 2);
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_attachments_do_not_appear_as_sessions() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+
+        storage.add_message("session-1", "user", "hello").unwrap();
+        storage
+            .save_attachment("session-1", "notes.txt", b"notes")
+            .unwrap();
+
+        let sessions = storage.get_project_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].session_id, "session-1");
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    fn feedback(
+        project_dir: &std::path::Path,
+        text: &str,
+        time_to_first_keystroke_ms: Option<u64>,
+        time_to_submit_ms: u64,
+    ) -> Feedback {
+        let mut feedback = Feedback::new(project_dir.to_path_buf(), "question".to_string());
+        feedback.set_interactive_feedback(text.to_string());
+        feedback.set_response_timing(
+            time_to_first_keystroke_ms.map(Duration::from_millis),
+            Duration::from_millis(time_to_submit_ms),
+            text.is_empty(),
+        );
+        feedback
+    }
+
+    #[test]
+    fn test_record_feedback() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+
+        storage.add_message("session-1", "user", "fix it").unwrap();
+        storage
+            .add_message("session-1", "assistant", "Fixed, anything else?")
+            .unwrap();
+        storage
+            .record_feedback(
+                "session-1",
+                &feedback(&project_dir, "ship it", Some(1500), 9000),
+            )
+            .unwrap();
+
+        let session = storage.load_session("session-1").unwrap().unwrap();
+        assert_eq!(session.messages.len(), 3);
+        assert_eq!(
+            session.messages[1].response,
+            Some(ResponseTiming {
+                time_to_first_keystroke_ms: Some(1500),
+                time_to_submit_ms: 9000,
+                cancelled: false,
+            })
+        );
+        assert_eq!(session.messages[2].role, "user");
+        assert_eq!(session.messages[2].content, "ship it");
+        assert!(session.messages[2].response.is_none());
+
+        // A cancelled request keeps its timing but adds no message
+        storage
+            .add_message("session-1", "assistant", "Another question?")
+            .unwrap();
+        storage
+            .record_feedback("session-1", &feedback(&project_dir, "", None, 3000))
+            .unwrap();
+        let session = storage.load_session("session-1").unwrap().unwrap();
+        assert_eq!(session.messages.len(), 4);
+        assert!(session.messages[3].response.as_ref().unwrap().cancelled);
+
+        assert!(storage
+            .record_feedback("missing", &feedback(&project_dir, "", None, 0))
+            .is_err());
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_recent_messages_have_stable_ids() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+
+        let mut ids = Vec::new();
+        for i in 0..5 {
+            ids.push(
+                storage
+                    .add_message("session-1", "user", &format!("message {}", i))
+                    .unwrap(),
+            );
+        }
+
+        let recent = storage.recent_messages("session-1", 2).unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].content, "message 3");
+        assert_eq!(recent[1].id, ids[4]);
+        assert_eq!(storage.recent_messages("session-1", 10).unwrap().len(), 5);
+        assert!(storage.recent_messages("missing", 10).unwrap().is_empty());
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_messages_without_ids_get_positional_ids() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+        storage.initialize().unwrap();
+
+        std::fs::write(
+            project_dir
+                .join(".ifm-ruta")
+                .join("conversations")
+                .join("legacy.json"),
+            r#"{
+                "session_id": "legacy",
+                "project_directory": "/project",
+                "messages": [
+                    {"role": "user", "content": "hi", "timestamp": "2026-10-18T09:30:00Z"},
+                    {"role": "assistant", "content": "hello", "timestamp": "2026-10-18T09:31:00Z"}
+                ],
+                "created_at": "2026-10-18T09:30:00Z",
+                "last_updated": "2026-10-18T09:31:00Z"
+            }"#,
+        )
+        .unwrap();
+
+        let first = storage.recent_messages("legacy", 10).unwrap();
+        assert_eq!(first[0].id, "legacy-0");
+        assert_eq!(first[1].id, "legacy-1");
+
+        // Appending keeps the ids of the existing messages
+        storage.add_message("legacy", "user", "again").unwrap();
+        let second = storage.recent_messages("legacy", 10).unwrap();
+        assert_eq!(second[0].id, "legacy-0");
+        assert_eq!(second[1].id, "legacy-1");
+        assert_ne!(second[2].id, "legacy-2");
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_reading_latest_sessions_never_deletes() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+
+        for i in 0..7 {
+            storage
+                .add_message(&format!("session-{}", i), "user", "hello")
+                .unwrap();
+        }
+
+        assert_eq!(storage.get_latest_5_sessions().unwrap().len(), 5);
+        assert_eq!(storage.get_latest_sessions(2).unwrap().len(), 2);
+        assert!(storage
+            .get_latest_5_conversations()
+            .unwrap()
+            .contains("SESSION 5"));
+        assert_eq!(storage.get_project_sessions().unwrap().len(), 7);
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_session_id_validation() {
+        assert!(ConversationStorage::is_valid_session_id("cursor-1a2b3c4d"));
+        assert!(ConversationStorage::is_valid_session_id("chat_1.2"));
+        assert!(!ConversationStorage::is_valid_session_id(""));
+        assert!(!ConversationStorage::is_valid_session_id(".hidden"));
+        assert!(!ConversationStorage::is_valid_session_id("../etc/passwd"));
+        assert!(!ConversationStorage::is_valid_session_id("a/b"));
+        assert!(!ConversationStorage::is_valid_session_id(&"x".repeat(129)));
+    }
+
+    fn session_ids(storage: &ConversationStorage) -> Vec<String> {
+        storage
+            .get_project_sessions()
+            .unwrap()
+            .into_iter()
+            .map(|session| session.session_id)
+            .collect()
+    }
+
+    #[test]
+    fn test_compact_dry_run_changes_nothing() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+        for i in 0..4 {
+            storage
+                .add_message(&format!("session-{}", i), "user", "hello")
+                .unwrap();
+        }
+        let policy = RetentionPolicy {
+            max_sessions: Some(2),
+            ..Default::default()
+        };
+
+        let report = storage.compact(&policy, true).unwrap();
+        assert!(report.dry_run);
+        let removed: Vec<&str> = report
+            .removed_sessions
+            .iter()
+            .map(|removal| removal.session_id.as_str())
+            .collect();
+        assert_eq!(removed, vec!["session-1", "session-0"]);
+        assert!(report
+            .removed_sessions
+            .iter()
+            .all(|removal| removal.reason == RetentionReason::MaxSessions && removal.bytes > 0));
+        assert!(report.render().contains("Would remove session session-0"));
+        assert_eq!(session_ids(&storage).len(), 4);
+
+        let report = storage.compact(&policy, false).unwrap();
+        assert_eq!(report.removed_sessions.len(), 2);
+        assert_eq!(session_ids(&storage), vec!["session-3", "session-2"]);
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_compact_removes_expired_sessions() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+        storage.add_message("old", "user", "hello").unwrap();
+        storage
+            .save_attachment("old", "notes.txt", b"notes")
+            .unwrap();
+        let mut old = storage.load_session("old").unwrap().unwrap();
+        old.last_updated = (chrono::Utc::now() - chrono::Duration::days(40)).to_rfc3339();
+        storage.save_session(&old).unwrap();
+        storage.add_message("recent", "user", "hello").unwrap();
+
+        let report = storage
+            .compact(
+                &RetentionPolicy {
+                    max_age: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(report.removed_sessions.len(), 1);
+        assert_eq!(report.removed_sessions[0].reason, RetentionReason::MaxAge);
+        assert_eq!(session_ids(&storage), vec!["recent"]);
+        assert!(!storage.attachments_dir("old").exists());
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_compact_keeps_sessions_updated_meanwhile() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+        storage.add_message("old", "user", "hello").unwrap();
+        let mut old = storage.load_session("old").unwrap().unwrap();
+        old.last_updated = (chrono::Utc::now() - chrono::Duration::days(40)).to_rfc3339();
+        storage.save_session(&old).unwrap();
+
+        // Compaction plans the removal, then waits for the session lock
+        // while the session is written to
+        let lock = storage.lock_session("old").unwrap();
+        let compacting = {
+            let project_dir = project_dir.clone();
+            std::thread::spawn(move || {
+                ConversationStorage::new(&project_dir)
+                    .compact(
+                        &RetentionPolicy {
+                            max_age: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+                            ..Default::default()
+                        },
+                        false,
+                    )
+                    .unwrap()
+            })
+        };
+        std::thread::sleep(Duration::from_millis(200));
+        old.last_updated = chrono::Utc::now().to_rfc3339();
+        storage.save_session(&old).unwrap();
+        drop(lock);
+
+        let report = compacting.join().unwrap();
+        assert!(report.removed_sessions.is_empty());
+        assert_eq!(session_ids(&storage), vec!["old"]);
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_compact_trims_messages_and_total_size() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+        for session in ["first", "second", "third"] {
+            for i in 0..5 {
+                storage
+                    .add_message(session, "user", &format!("{} {}", session, i))
+                    .unwrap();
+            }
+        }
+        let session_size = storage.get_storage_stats().unwrap().total_size_bytes / 3;
+
+        let report = storage
+            .compact(
+                &RetentionPolicy {
+                    max_messages_per_session: Some(2),
+                    max_total_bytes: Some(session_size),
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+
+        // Trimming alone is not enough, so the oldest sessions go too
+        assert_eq!(report.trimmed_sessions.len(), 1);
+        assert_eq!(report.trimmed_sessions[0].session_id, "third");
+        assert_eq!(report.trimmed_sessions[0].removed_messages, 3);
+        assert!(report
+            .removed_sessions
+            .iter()
+            .all(|removal| removal.reason == RetentionReason::MaxTotalBytes));
+        assert_eq!(session_ids(&storage), vec!["third"]);
+
+        let messages = storage.recent_messages("third", 10).unwrap();
+        let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["third 3", "third 4"]);
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_retention_is_enforced_on_write() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir).with_retention(RetentionPolicy {
+            max_sessions: Some(2),
+            max_messages_per_session: Some(3),
+            ..Default::default()
+        });
+
+        for i in 0..5 {
+            storage
+                .add_message("session-a", "user", &i.to_string())
+                .unwrap();
+        }
+        assert_eq!(storage.recent_messages("session-a", 10).unwrap().len(), 3);
+
+        storage.add_message("session-b", "user", "hello").unwrap();
+        storage.add_message("session-c", "user", "hello").unwrap();
+        assert_eq!(session_ids(&storage), vec!["session-c", "session-b"]);
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_jsonl_sessions_keep_message_limit() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir)
+            .with_format(SessionFormat::Jsonl)
+            .with_retention(RetentionPolicy {
+                max_messages_per_session: Some(3),
+                ..Default::default()
+            });
+
+        for i in 0..5 {
+            storage
+                .add_message("session-a", "user", &i.to_string())
+                .unwrap();
+        }
+        let messages = storage.recent_messages("session-a", 10).unwrap();
+        let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["2", "3", "4"]);
+
+        // The log itself is trimmed while it grows, and down to the limit
+        // when compacted: a header and one line per message
+        let log = project_dir
+            .join(".ifm-ruta")
+            .join("conversations")
+            .join("session-a.jsonl");
+        assert!(std::fs::read_to_string(&log).unwrap().lines().count() < 6);
+        assert!(storage.compact_session_log("session-a").unwrap());
+        assert_eq!(std::fs::read_to_string(&log).unwrap().lines().count(), 4);
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_project_retention_overrides_app_settings() {
+        let app_settings = AppSettings {
+            retention: RetentionPolicy {
+                max_sessions: Some(10),
+                max_age: Some(Duration::from_secs(3600)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(RetentionPolicy::default().is_unlimited());
+
+        let mut project_settings = ProjectSettings::default();
+        project_settings.retention.max_sessions = Some(3);
+        project_settings.retention.max_total_bytes = Some(1024);
+
+        let policy = project_settings.retention_policy(&app_settings);
+        assert_eq!(policy.max_sessions, Some(3));
+        assert_eq!(policy.max_age, Some(Duration::from_secs(3600)));
+        assert_eq!(policy.max_total_bytes, Some(1024));
+        assert_eq!(policy.max_messages_per_session, None);
+    }
+
+    fn write_session_file(project_dir: &std::path::Path, name: &str, content: &str) {
+        let path = project_dir
+            .join(".ifm-ruta")
+            .join("conversations")
+            .join(name);
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_sessions_are_quarantined() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+        storage.add_message("good", "user", "hello").unwrap();
+        write_session_file(&project_dir, "truncated.json", r#"{"session_id": "trunc"#);
+
+        let sessions = storage.get_project_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].session_id, "good");
+        // Reading never moves files
+        assert!(storage.quarantined_sessions().unwrap().is_empty());
+
+        // The corrupt file is kept for inspection, not lost
+        assert_eq!(storage.recover().unwrap().quarantined.len(), 1);
+        let quarantined = storage.quarantined_sessions().unwrap();
+        assert_eq!(quarantined.len(), 1);
+        assert!(quarantined[0].starts_with(storage.quarantine_dir()));
+        assert_eq!(
+            std::fs::read_to_string(&quarantined[0]).unwrap(),
+            r#"{"session_id": "trunc"#
+        );
+        assert_eq!(storage.get_storage_stats().unwrap().quarantined_sessions, 1);
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_recover_reports_corrupt_files() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+        storage.add_message("good", "user", "hello").unwrap();
+        write_session_file(&project_dir, "broken.json", "not json");
+        write_session_file(&project_dir, "good.json.1a2b3c4d.tmp", "{");
+        write_session_file(&project_dir, "fresh.json.5e6f7a8b.tmp", "{");
+        let stale = project_dir
+            .join(".ifm-ruta")
+            .join("conversations")
+            .join("good.json.1a2b3c4d.tmp");
+        std::fs::File::options()
+            .write(true)
+            .open(&stale)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() - Duration::from_secs(3600))
+            .unwrap();
+
+        let report = storage.recover().unwrap();
+        assert_eq!(report.quarantined.len(), 1);
+        assert_eq!(report.quarantined[0].session_id, "broken");
+        assert!(report.quarantined[0].path.exists());
+        // Only temp files old enough to be left by a crash are deleted
+        assert_eq!(report.removed_temp_files, 1);
+        assert!(!stale.exists());
+
+        assert!(storage.recover().unwrap().quarantined.is_empty());
+        assert_eq!(
+            storage
+                .load_session("good")
+                .unwrap()
+                .unwrap()
+                .messages
+                .len(),
+            1
+        );
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_writing_to_corrupt_session_fails_until_recovered() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+        storage.initialize().unwrap();
+        write_session_file(&project_dir, "session-1.json", "{\"session_id\":");
+
+        // The corrupt file is neither replaced nor moved
+        assert!(matches!(
+            storage.add_message("session-1", "user", "hello"),
+            Err(AppError::DeserializationError { .. })
+        ));
+        assert!(storage.quarantined_sessions().unwrap().is_empty());
+
+        assert_eq!(storage.recover().unwrap().quarantined.len(), 1);
+        storage.add_message("session-1", "user", "hello").unwrap();
+        let session = storage.load_session("session-1").unwrap().unwrap();
+        assert_eq!(session.messages.len(), 1);
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    /// Environment of the writer processes spawned by the concurrency test
+    const WRITER_DIR_ENV: &str = "IFM_RUTA_TEST_WRITER_DIR";
+    const WRITER_ID_ENV: &str = "IFM_RUTA_TEST_WRITER_ID";
+    const WRITER_FORMAT_ENV: &str = "IFM_RUTA_TEST_WRITER_FORMAT";
+    const WRITER_MESSAGES: usize = 25;
+
+    #[test]
+    #[ignore = "run as a child process by test_concurrent_processes_keep_all_messages"]
+    fn concurrent_writer_process() {
+        let project_dir = PathBuf::from(std::env::var(WRITER_DIR_ENV).unwrap());
+        let writer = std::env::var(WRITER_ID_ENV).unwrap();
+        let format = match std::env::var(WRITER_FORMAT_ENV).unwrap().as_str() {
+            "jsonl" => SessionFormat::Jsonl,
+            _ => SessionFormat::Json,
+        };
+        let storage = ConversationStorage::new(&project_dir).with_format(format);
+        for i in 0..WRITER_MESSAGES {
+            storage
+                .add_message("shared", "user", &format!("{}-{}", writer, i))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_concurrent_processes_keep_all_messages() {
+        for format in [SessionFormat::Json, SessionFormat::Jsonl] {
+            hammer_shared_session(format);
+        }
+    }
+
+    fn hammer_shared_session(format: SessionFormat) {
+        let project_dir = temp_project_dir();
+        let writers = 4;
+
+        let children: Vec<std::process::Child> = (0..writers)
+            .map(|writer| {
+                std::process::Command::new(std::env::current_exe().unwrap())
+                    .args([
+                        "--ignored",
+                        "--exact",
+                        "conversation_storage_tests::concurrent_writer_process",
+                    ])
+                    .env(WRITER_DIR_ENV, &project_dir)
+                    .env(WRITER_ID_ENV, writer.to_string())
+                    .env(WRITER_FORMAT_ENV, format.extension())
+                    .stdout(std::process::Stdio::null())
+                    .spawn()
+                    .unwrap()
+            })
+            .collect();
+        for mut child in children {
+            assert!(child.wait().unwrap().success());
+        }
+
+        let messages = ConversationStorage::new(&project_dir)
+            .recent_messages("shared", usize::MAX)
+            .unwrap();
+        assert_eq!(messages.len(), writers * WRITER_MESSAGES);
+        for writer in 0..writers {
+            // Each writer's messages are all there, in the order it wrote them
+            let own: Vec<&str> = messages
+                .iter()
+                .map(|message| message.content.as_str())
+                .filter(|content| content.starts_with(&format!("{}-", writer)))
+                .collect();
+            let expected: Vec<String> = (0..WRITER_MESSAGES)
+                .map(|i| format!("{}-{}", writer, i))
+                .collect();
+            assert_eq!(own, expected);
+        }
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_lock_timeout() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir);
+        storage.initialize().unwrap();
+        let _lock = storage.lock_session("session-1").unwrap();
+
+        let waiting =
+            ConversationStorage::new(&project_dir).with_lock_timeout(Duration::from_millis(50));
+        let error = waiting
+            .add_message("session-1", "user", "hello")
+            .unwrap_err();
+        assert!(error.to_string().contains("Timed out"));
+        // Other sessions are not blocked
+        waiting.add_message("session-2", "user", "hello").unwrap();
+
+        drop(_lock);
+        waiting.add_message("session-1", "user", "hello").unwrap();
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    fn session_file(project_dir: &std::path::Path, name: &str) -> PathBuf {
+        project_dir
+            .join(".ifm-ruta")
+            .join("conversations")
+            .join(name)
+    }
+
+    #[test]
+    fn test_jsonl_sessions() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir).with_format(SessionFormat::Jsonl);
+
+        storage
+            .add_message("session-1", "user", "Fix the bug")
+            .unwrap();
+        let prompt_id = storage
+            .add_message("session-1", "assistant", "Done?")
+            .unwrap();
+        let mut feedback = Feedback::new(project_dir.clone(), "Done?".to_string());
+        feedback.set_interactive_feedback("Yes".to_string());
+        feedback.set_response_timing(None, Duration::from_secs(3), false);
+        storage.record_feedback("session-1", &feedback).unwrap();
+
+        assert!(session_file(&project_dir, "session-1.jsonl").exists());
+        assert!(!session_file(&project_dir, "session-1.json").exists());
+
+        // Readers need not know the format
+        let reader = ConversationStorage::new(&project_dir);
+        let messages = reader.recent_messages("session-1", 10).unwrap();
+        let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["Fix the bug", "Done?", "Yes"]);
+        assert_eq!(messages[1].id, prompt_id);
+        assert_eq!(
+            messages[1].response.as_ref().unwrap().time_to_submit_ms,
+            3000
+        );
+        assert_eq!(reader.get_project_sessions().unwrap().len(), 1);
+
+        // Header, three messages and the response record
+        let log = session_file(&project_dir, "session-1.jsonl");
+        assert_eq!(std::fs::read_to_string(&log).unwrap().lines().count(), 5);
+        assert!(storage.compact_session_log("session-1").unwrap());
+        assert_eq!(std::fs::read_to_string(&log).unwrap().lines().count(), 4);
+        assert_eq!(reader.recent_messages("session-1", 10).unwrap(), messages);
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_jsonl_appends_do_not_rewrite_the_session() {
+        let project_dir = temp_project_dir();
+        let storage = ConversationStorage::new(&project_dir).with_format(SessionFormat::Jsonl);
+        let log = session_file(&project_dir, "long.jsonl");
+        let content = "x".repeat(200);
+
+        for _ in 0..1000 {
+            storage.add_message("long", "user", &content).unwrap();
+        }
+        let before = std::fs::metadata(&log).unwrap().len();
+        storage.add_message("long", "user", &content).unwrap();
+        let growth = std::fs::metadata(&log).unwrap().len() - before;
+
+        // Exactly one record is written, however long the session is
+        assert!(growth < 500, "appending wrote {} bytes", growth);
+        assert_eq!(storage.recent_messages("long", 2000).unwrap().len(), 1001);
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_json_sessions_migrate_to_jsonl() {
+        let project_dir = temp_project_dir();
+        let json = ConversationStorage::new(&project_dir);
+        for session in ["first", "second"] {
+            json.add_message(session, "user", "hello").unwrap();
+            json.add_message(session, "assistant", "hi").unwrap();
+        }
+        let ids: Vec<String> = json
+            .recent_messages("first", 10)
+            .unwrap()
+            .into_iter()
+            .map(|message| message.id)
+            .collect();
+
+        // Sessions written in JSON move to JSONL on their next write
+        let jsonl = ConversationStorage::new(&project_dir).with_format(SessionFormat::Jsonl);
+        jsonl.add_message("first", "user", "again").unwrap();
+        assert!(!session_file(&project_dir, "first.json").exists());
+        let messages = jsonl.recent_messages("first", 10).unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].id, ids[0]);
+        assert_eq!(messages[1].id, ids[1]);
+
+        // ... or all at once
+        assert_eq!(json.migrate_to_jsonl().unwrap(), 1);
+        assert!(session_file(&project_dir, "second.jsonl").exists());
+        assert!(!session_file(&project_dir, "second.json").exists());
+        assert_eq!(json.migrate_to_jsonl().unwrap(), 0);
+
+        // Retention keeps working on logs
+        let report = json
+            .compact(
+                &RetentionPolicy {
+                    max_messages_per_session: Some(1),
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+        assert_eq!(report.trimmed_sessions.len(), 2);
+        let messages = json.recent_messages("first", 10).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "again");
+        assert!(session_file(&project_dir, "first.jsonl").exists());
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+}
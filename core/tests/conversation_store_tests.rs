@@ -0,0 +1,440 @@
+//! Conversation store backend integration tests
+
+#[cfg(test)]
+mod conversation_store_tests {
+    use ifm_ruta_core::models::{
+        ConversationBackend, ConversationSettings, Feedback, RetentionPolicy, SessionFormat,
+    };
+    use ifm_ruta_core::services::{
+        open_conversation_store, ConversationSession, ConversationStorage,
+        DatabaseConversationStore, MemoryConversationStore,
+    };
+    use ifm_ruta_core::traits::ConversationStore;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    // Create an isolated project directory for a test
+    fn temp_project_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ifm-ruta-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // Every backend, each in its own project directory
+    fn stores() -> Vec<(&'static str, PathBuf, Box<dyn ConversationStore>)> {
+        let json = temp_project_dir();
+        let jsonl = temp_project_dir();
+        let database = temp_project_dir();
+        let memory = temp_project_dir();
+        vec![
+            (
+                "json",
+                json.clone(),
+                Box::new(ConversationStorage::new(&json)),
+            ),
+            (
+                "jsonl",
+                jsonl.clone(),
+                Box::new(ConversationStorage::new(&jsonl).with_format(SessionFormat::Jsonl)),
+            ),
+            (
+                "database",
+                database.clone(),
+                Box::new(DatabaseConversationStore::new(&database)),
+            ),
+            (
+                "memory",
+                memory.clone(),
+                Box::new(MemoryConversationStore::new(&memory)),
+            ),
+        ]
+    }
+
+    fn feedback(project_dir: &Path, text: &str) -> Feedback {
+        let mut feedback = Feedback::new(project_dir.to_path_buf(), "question".to_string());
+        feedback.set_interactive_feedback(text.to_string());
+        feedback.set_command_logs("$ cargo test\nok".to_string());
+        feedback.add_attachment(project_dir.join("screenshot.png"));
+        feedback.set_response_timing(
+            Some(Duration::from_millis(700)),
+            Duration::from_millis(5000),
+            text.is_empty(),
+        );
+        feedback
+    }
+
+    fn contents(session: &ConversationSession) -> Vec<(&str, &str)> {
+        session
+            .messages
+            .iter()
+            .map(|message| (message.role.as_str(), message.content.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_backends_behave_the_same() {
+        for (backend, project_dir, store) in stores() {
+            assert!(store.list_sessions().unwrap().is_empty(), "{}", backend);
+            assert!(store.load_session("session-1").unwrap().is_none());
+
+            let first = store.add_message("session-1", "user", "fix it").unwrap();
+            store
+                .add_message("session-1", "assistant", "Fixed, anything else?")
+                .unwrap();
+            let given = feedback(&project_dir, "ship it");
+            store.record_feedback("session-1", &given).unwrap();
+            assert!(store
+                .record_feedback("missing", &feedback(&project_dir, ""))
+                .is_err());
+
+            let session = store.load_session("session-1").unwrap().unwrap();
+            assert_eq!(session.messages[0].id, first, "{}", backend);
+            assert_eq!(
+                contents(&session),
+                vec![
+                    ("user", "fix it"),
+                    ("assistant", "Fixed, anything else?"),
+                    ("user", "ship it"),
+                ],
+                "{}",
+                backend
+            );
+            let timing = session.messages[1].response.as_ref().unwrap();
+            assert_eq!(timing.time_to_submit_ms, 5000, "{}", backend);
+            assert_eq!(timing.time_to_first_keystroke_ms, Some(700));
+            let recorded = session.messages[1].feedback.clone().unwrap().into_latest();
+            assert_eq!(recorded, given, "{}", backend);
+
+            let recent = store.recent_messages("session-1", 2).unwrap();
+            assert_eq!(recent.len(), 2);
+            assert_eq!(recent[1].content, "ship it");
+
+            // Newest session first
+            std::thread::sleep(Duration::from_millis(5));
+            store.add_message("session-2", "user", "hello").unwrap();
+            let ids: Vec<String> = store
+                .latest_sessions(5)
+                .unwrap()
+                .into_iter()
+                .map(|session| session.session_id)
+                .collect();
+            assert_eq!(ids, vec!["session-2", "session-1"], "{}", backend);
+
+            // Saving replaces the stored session
+            let mut edited = store.load_session("session-1").unwrap().unwrap();
+            edited.messages.truncate(1);
+            store.save_session(&edited).unwrap();
+            let reloaded = store.load_session("session-1").unwrap().unwrap();
+            assert_eq!(contents(&reloaded), vec![("user", "fix it")], "{}", backend);
+            store.add_message("session-1", "user", "again").unwrap();
+            assert_eq!(
+                store
+                    .load_session("session-1")
+                    .unwrap()
+                    .unwrap()
+                    .messages
+                    .len(),
+                2,
+                "{}",
+                backend
+            );
+
+            let attachment = store
+                .save_attachment("session-1", "../notes.txt", b"notes")
+                .unwrap();
+            // Stored next to the session whatever the name says
+            assert_eq!(
+                attachment.parent().unwrap().file_name().unwrap(),
+                "session-1.attachments",
+                "{}",
+                backend
+            );
+
+            assert!(store.remove_session("session-1").unwrap(), "{}", backend);
+            assert!(!store.remove_session("session-1").unwrap());
+            assert!(store.load_session("session-1").unwrap().is_none());
+            assert_eq!(store.list_sessions().unwrap().len(), 1, "{}", backend);
+            assert!(!attachment.exists(), "{}", backend);
+
+            std::fs::remove_dir_all(project_dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_backends_trim_the_same() {
+        for (backend, project_dir, store) in stores() {
+            for i in 0..4 {
+                store
+                    .add_message("session", "user", &format!("message {}", i))
+                    .unwrap();
+            }
+
+            store.trim_session("session", 2).unwrap();
+            store.trim_session("missing", 2).unwrap();
+
+            let messages = store.recent_messages("session", 10).unwrap();
+            let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+            assert_eq!(contents, vec!["message 2", "message 3"], "{}", backend);
+
+            std::fs::remove_dir_all(project_dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_backends_compact_the_same() {
+        let policy = RetentionPolicy {
+            max_sessions: Some(2),
+            max_messages_per_session: Some(2),
+            ..Default::default()
+        };
+        for (backend, project_dir, store) in stores() {
+            for session_id in ["oldest", "middle", "newest"] {
+                for i in 0..3 {
+                    store
+                        .add_message(session_id, "user", &format!("{} {}", session_id, i))
+                        .unwrap();
+                }
+                std::thread::sleep(Duration::from_millis(5));
+            }
+
+            let planned = store.compact(&policy, true).unwrap();
+            assert_eq!(store.list_sessions().unwrap().len(), 3, "{}", backend);
+
+            let report = store.compact(&policy, false).unwrap();
+            assert_eq!(report.removed_sessions, planned.removed_sessions);
+            assert_eq!(report.removed_sessions.len(), 1, "{}", backend);
+            assert_eq!(report.removed_sessions[0].session_id, "oldest");
+            assert_eq!(report.trimmed_sessions.len(), 2, "{}", backend);
+            let messages = store.recent_messages("newest", 10).unwrap();
+            let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+            assert_eq!(contents, vec!["newest 1", "newest 2"], "{}", backend);
+
+            // Only the file backend can be left corrupt by a crash
+            assert!(store.recover().unwrap().is_clean(), "{}", backend);
+            assert!(store.quarantined_sessions().unwrap().is_empty());
+
+            std::fs::remove_dir_all(project_dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_database_enforces_retention_on_write() {
+        let project_dir = temp_project_dir();
+        let settings = ConversationSettings {
+            backend: ConversationBackend::Database,
+            format: SessionFormat::Json,
+        };
+        let store = open_conversation_store(
+            &project_dir,
+            &settings,
+            RetentionPolicy {
+                max_sessions: Some(2),
+                max_messages_per_session: Some(3),
+                ..Default::default()
+            },
+        );
+
+        for i in 0..5 {
+            store
+                .add_message("session-a", "user", &i.to_string())
+                .unwrap();
+        }
+        let messages = store.recent_messages("session-a", 10).unwrap();
+        let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["2", "3", "4"]);
+
+        // Feedback counts towards the limit too
+        store
+            .add_message("session-a", "assistant", "question")
+            .unwrap();
+        store
+            .record_feedback("session-a", &feedback(&project_dir, "answer"))
+            .unwrap();
+        let messages = store.recent_messages("session-a", 10).unwrap();
+        let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[1], "question");
+
+        std::thread::sleep(Duration::from_millis(5));
+        store.add_message("session-b", "user", "hello").unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        store.add_message("session-c", "user", "hello").unwrap();
+        let ids: Vec<String> = store
+            .list_sessions()
+            .unwrap()
+            .into_iter()
+            .map(|session| session.session_id)
+            .collect();
+        assert_eq!(ids, vec!["session-c", "session-b"]);
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_database_migration_imports_file_sessions() {
+        let project_dir = temp_project_dir();
+        let files = ConversationStorage::new(&project_dir);
+        files.add_message("session-1", "user", "hello").unwrap();
+        files.add_message("session-1", "assistant", "hi").unwrap();
+
+        let database = DatabaseConversationStore::new(&project_dir);
+        database
+            .add_message("session-2", "user", "already here")
+            .unwrap();
+        assert_eq!(database.migrate().unwrap(), 1);
+        assert_eq!(database.recent_messages("session-1", 10).unwrap().len(), 2);
+        assert_eq!(database.recent_messages("session-2", 10).unwrap().len(), 1);
+
+        // Sessions already in the database are not imported again
+        assert_eq!(database.migrate().unwrap(), 0);
+        assert_eq!(files.get_project_sessions().unwrap().len(), 1);
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_memory_store_keeps_attachments() {
+        let project_dir = temp_project_dir();
+        let store = MemoryConversationStore::new(&project_dir);
+
+        let path = store
+            .save_attachment("session-1", "screenshot.png", b"png-bytes")
+            .unwrap();
+        assert_eq!(store.attachment(&path).unwrap(), b"png-bytes");
+        // Nothing is written to the project
+        assert!(!project_dir.join(".ifm-ruta").exists());
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_conversation_store_uses_configured_backend() {
+        let project_dir = temp_project_dir();
+
+        let settings = ConversationSettings {
+            backend: ConversationBackend::Database,
+            format: SessionFormat::Json,
+        };
+        let store = open_conversation_store(&project_dir, &settings, RetentionPolicy::default());
+        store.add_message("session-1", "user", "hello").unwrap();
+        assert!(DatabaseConversationStore::database_path(&project_dir).exists());
+        assert!(ConversationStorage::new(&project_dir)
+            .get_project_sessions()
+            .unwrap()
+            .is_empty());
+
+        // Messages survive reopening the database
+        drop(store);
+        let reopened = open_conversation_store(&project_dir, &settings, RetentionPolicy::default());
+        assert_eq!(reopened.recent_messages("session-1", 10).unwrap().len(), 1);
+
+        let files = open_conversation_store(
+            &project_dir,
+            &ConversationSettings::default(),
+            RetentionPolicy::default(),
+        );
+        assert!(files.list_sessions().unwrap().is_empty());
+        files.add_message("session-1", "user", "hello").unwrap();
+        assert_eq!(
+            ConversationStorage::new(&project_dir)
+                .get_project_sessions()
+                .unwrap()
+                .len(),
+            1
+        );
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_database_lock_timeout() {
+        let project_dir = temp_project_dir();
+        let store = DatabaseConversationStore::new(&project_dir);
+        store.add_message("session-1", "user", "hello").unwrap();
+
+        // A store keeps the database open until it is dropped
+        let path = DatabaseConversationStore::database_path(&project_dir);
+        assert!(matches!(
+            redb::Database::create(&path),
+            Err(redb::DatabaseError::DatabaseAlreadyOpen)
+        ));
+        drop(store);
+
+        // Another process keeping the database open
+        let held =
+            redb::Database::create(DatabaseConversationStore::database_path(&project_dir)).unwrap();
+        let waiting = DatabaseConversationStore::new(&project_dir)
+            .with_lock_timeout(Duration::from_millis(50));
+        let error = waiting
+            .add_message("session-1", "user", "hello")
+            .unwrap_err();
+        assert!(error.to_string().contains("Timed out"));
+
+        drop(held);
+        waiting.add_message("session-1", "user", "hello").unwrap();
+        assert_eq!(waiting.recent_messages("session-1", 10).unwrap().len(), 2);
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+
+    const WRITER_DIR_ENV: &str = "IFM_RUTA_TEST_DATABASE_WRITER_DIR";
+    const WRITER_ID_ENV: &str = "IFM_RUTA_TEST_DATABASE_WRITER_ID";
+    const WRITER_MESSAGES: usize = 25;
+
+    #[test]
+    #[ignore = "run as a child process by test_database_concurrent_processes"]
+    fn database_writer_process() {
+        let project_dir = PathBuf::from(std::env::var(WRITER_DIR_ENV).unwrap());
+        let writer = std::env::var(WRITER_ID_ENV).unwrap();
+        let store = DatabaseConversationStore::new(&project_dir);
+        for i in 0..WRITER_MESSAGES {
+            store
+                .add_message("shared", "user", &format!("{}-{}", writer, i))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_database_concurrent_processes() {
+        let project_dir = temp_project_dir();
+        let writers = 4;
+
+        let children: Vec<std::process::Child> = (0..writers)
+            .map(|writer| {
+                std::process::Command::new(std::env::current_exe().unwrap())
+                    .args([
+                        "--ignored",
+                        "--exact",
+                        "conversation_store_tests::database_writer_process",
+                    ])
+                    .env(WRITER_DIR_ENV, &project_dir)
+                    .env(WRITER_ID_ENV, writer.to_string())
+                    .stdout(std::process::Stdio::null())
+                    .spawn()
+                    .unwrap()
+            })
+            .collect();
+        for mut child in children {
+            assert!(child.wait().unwrap().success());
+        }
+
+        let messages = DatabaseConversationStore::new(&project_dir)
+            .recent_messages("shared", usize::MAX)
+            .unwrap();
+        assert_eq!(messages.len(), writers * WRITER_MESSAGES);
+        for writer in 0..writers {
+            // Each writer's messages are all there, in the order it wrote them
+            let own: Vec<&str> = messages
+                .iter()
+                .map(|message| message.content.as_str())
+                .filter(|content| content.starts_with(&format!("{}-", writer)))
+                .collect();
+            let expected: Vec<String> = (0..WRITER_MESSAGES)
+                .map(|i| format!("{}-{}", writer, i))
+                .collect();
+            assert_eq!(own, expected);
+        }
+
+        std::fs::remove_dir_all(project_dir).unwrap();
+    }
+}
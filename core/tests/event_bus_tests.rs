@@ -0,0 +1,227 @@
+//! Event bus tests
+
+#[cfg(test)]
+mod event_bus_tests {
+    use async_trait::async_trait;
+    use ifm_ruta_core::services::{EventBusImpl, EventQuery};
+    use ifm_ruta_core::traits::{Event, EventBus, EventError, EventListener, EventType};
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, SystemTime};
+
+    /// Listener recording events, optionally failing on every one
+    struct RecordingListener {
+        id: String,
+        fail: bool,
+        events: Mutex<Vec<Event>>,
+    }
+
+    impl RecordingListener {
+        fn new(id: &str, fail: bool) -> Arc<Self> {
+            Arc::new(Self {
+                id: id.to_string(),
+                fail,
+                events: Mutex::new(Vec::new()),
+            })
+        }
+
+        fn count(&self) -> usize {
+            self.events.lock().unwrap().len()
+        }
+    }
+
+    #[async_trait]
+    impl EventListener for RecordingListener {
+        async fn handle_event(&self, event: &Event) -> Result<(), EventError> {
+            self.events.lock().unwrap().push(event.clone());
+            if self.fail {
+                return Err(EventError::HandlingFailed {
+                    message: "listener failure".to_string(),
+                });
+            }
+            Ok(())
+        }
+
+        fn listener_id(&self) -> &str {
+            &self.id
+        }
+    }
+
+    fn event(event_type: EventType, n: u64) -> Event {
+        Event {
+            event_type,
+            data: json!({ "n": n }),
+            timestamp: SystemTime::now(),
+            source: "test".to_string(),
+        }
+    }
+
+    async fn wait_for(condition: impl Fn() -> bool) {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while !condition() && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[test]
+    fn test_event_bus_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<EventBusImpl>();
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_through_shared_bus() {
+        let bus: Arc<dyn EventBus> = Arc::new(EventBusImpl::new());
+        let listener = RecordingListener::new("recorder", false);
+        bus.subscribe(EventType::ToolExecuted, listener.clone())
+            .unwrap();
+
+        for n in 0..3 {
+            bus.publish(event(EventType::ToolExecuted, n)).unwrap();
+        }
+        bus.publish(event(EventType::UIEvent, 99)).unwrap();
+
+        wait_for(|| listener.count() == 3).await;
+        let events = listener.events.lock().unwrap();
+        let order: Vec<_> = events.iter().map(|e| e.data["n"].clone()).collect();
+        assert_eq!(order, vec![json!(0), json!(1), json!(2)]);
+    }
+
+    #[tokio::test]
+    async fn test_failing_listener_does_not_stop_delivery() {
+        let bus = EventBusImpl::new();
+        let failing = RecordingListener::new("failing", true);
+        let healthy = RecordingListener::new("healthy", false);
+        bus.subscribe(EventType::ErrorOccurred, failing.clone())
+            .unwrap();
+        bus.subscribe(EventType::ErrorOccurred, healthy.clone())
+            .unwrap();
+
+        for n in 0..5 {
+            bus.publish(event(EventType::ErrorOccurred, n)).unwrap();
+        }
+
+        wait_for(|| failing.count() == 5 && healthy.count() == 5).await;
+        assert_eq!(failing.count(), 5);
+        assert_eq!(healthy.count(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_stops_delivery() {
+        let bus = EventBusImpl::new();
+        let listener = RecordingListener::new("recorder", false);
+        bus.subscribe(EventType::UIEvent, listener.clone()).unwrap();
+        assert_eq!(bus.listener_count(), 1);
+
+        bus.publish(event(EventType::UIEvent, 1)).unwrap();
+        wait_for(|| listener.count() == 1).await;
+
+        bus.unsubscribe(EventType::UIEvent, "recorder");
+        assert_eq!(bus.listener_count(), 0);
+        bus.publish(event(EventType::UIEvent, 2)).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(listener.count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_publish_from_other_threads() {
+        let bus = Arc::new(EventBusImpl::new());
+        let listener = RecordingListener::new("recorder", false);
+        bus.subscribe(EventType::ProcessSpawned, listener.clone())
+            .unwrap();
+
+        let publishers: Vec<_> = (0..4)
+            .map(|n| {
+                let bus = bus.clone();
+                std::thread::spawn(move || bus.publish(event(EventType::ProcessSpawned, n)))
+            })
+            .collect();
+        for publisher in publishers {
+            publisher.join().unwrap().unwrap();
+        }
+
+        wait_for(|| listener.count() == 4).await;
+        assert_eq!(listener.count(), 4);
+    }
+
+    #[test]
+    fn test_subscribe_requires_runtime() {
+        let bus = EventBusImpl::new();
+        let listener = RecordingListener::new("recorder", false);
+        assert!(bus.subscribe(EventType::UIEvent, listener).is_err());
+        assert!(bus.publish(event(EventType::UIEvent, 1)).is_ok());
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let bus = EventBusImpl::with_history_capacity(3);
+        for n in 0..10 {
+            bus.publish(event(EventType::UIEvent, n)).unwrap();
+        }
+        assert_eq!(bus.history_len(), 3);
+
+        let page = bus.query(&EventQuery::new());
+        let order: Vec<_> = page.events.iter().map(|e| e.data["n"].clone()).collect();
+        assert_eq!(order, vec![json!(9), json!(8), json!(7)]);
+
+        bus.set_history_capacity(1);
+        assert_eq!(bus.history_len(), 1);
+        assert_eq!(bus.query(&EventQuery::new()).events[0].data["n"], 9);
+    }
+
+    #[test]
+    fn test_history_query() {
+        let bus = EventBusImpl::new();
+        let start = SystemTime::now();
+        for n in 0..6 {
+            let event_type = if n < 4 {
+                EventType::ToolExecuted
+            } else {
+                EventType::ErrorOccurred
+            };
+            let mut event = event(event_type, n);
+            event.timestamp = start + Duration::from_secs(n);
+            if n == 3 {
+                event.source = "other".to_string();
+            }
+            bus.publish(event).unwrap();
+        }
+
+        let page = bus.query(&EventQuery::new().event_type(EventType::ToolExecuted));
+        assert_eq!(page.total, 4);
+
+        let page = bus.query(&EventQuery::new().source("other"));
+        assert_eq!(page.total, 1);
+        assert_eq!(page.events[0].data["n"], 3);
+
+        let page = bus.query(
+            &EventQuery::new()
+                .since(start + Duration::from_secs(1))
+                .until(start + Duration::from_secs(5)),
+        );
+        assert_eq!(page.total, 4);
+
+        let page = bus.query(&EventQuery::new().offset(2).limit(2));
+        assert_eq!(page.total, 6);
+        let order: Vec<_> = page.events.iter().map(|e| e.data["n"].clone()).collect();
+        assert_eq!(order, vec![json!(3), json!(2)]);
+    }
+
+    #[test]
+    fn test_event_json_round_trip() {
+        let event = Event::new(EventType::UIEvent, "gui", json!({"action": "attach_file"}));
+        let parsed = Event::from_json(&event.to_json()).unwrap();
+
+        assert_eq!(parsed.event_type, EventType::UIEvent);
+        assert_eq!(parsed.source, "gui");
+        assert_eq!(parsed.data, event.data);
+        // Timestamps keep millisecond precision
+        let drift = event
+            .timestamp
+            .duration_since(parsed.timestamp)
+            .unwrap_or_default();
+        assert!(drift < Duration::from_millis(1));
+
+        assert!(Event::from_json(&json!({"event_type": "UIEvent"})).is_none());
+    }
+}
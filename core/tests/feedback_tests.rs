@@ -0,0 +1,104 @@
+//! Feedback wire format tests
+
+#[cfg(test)]
+mod feedback_tests {
+    use ifm_ruta_core::models::{
+        ConversationHistoryEntry, Feedback, FeedbackMetadata, VersionedFeedback,
+    };
+    use serde_json::{json, Value};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn sample() -> Feedback {
+        let mut feedback = Feedback::new(PathBuf::from("/project"), "Done?".to_string());
+        feedback.set_interactive_feedback("Looks good".to_string());
+        feedback.set_command_logs("$ cargo test\nok\n".to_string());
+        feedback.add_executed_command("cargo test".to_string());
+        feedback.add_attachment(PathBuf::from("/project/.ifm-ruta/a.png"));
+        feedback.set_response_timing(
+            Some(Duration::from_millis(1500)),
+            Duration::from_millis(9000),
+            false,
+        );
+        feedback.set_execution_time(Duration::from_millis(9250));
+        feedback.set_conversation_history(vec![ConversationHistoryEntry {
+            id: "m-1".to_string(),
+            timestamp: feedback.timestamp,
+            role: "user".to_string(),
+            content: "fix it".to_string(),
+            is_current: true,
+        }]);
+        feedback
+    }
+
+    #[test]
+    fn test_versioned_round_trip() {
+        let feedback = sample();
+        let json = feedback.to_versioned_json().unwrap();
+        assert_eq!(Feedback::from_versioned_json(&json).unwrap(), feedback);
+    }
+
+    #[test]
+    fn test_wire_format() {
+        let value: Value = serde_json::from_str(&sample().to_versioned_json().unwrap()).unwrap();
+
+        assert_eq!(value["version"], "1");
+        assert_eq!(value["interactive_feedback"], "Looks good");
+        assert_eq!(value["conversation_history"][0]["is_current"], true);
+        assert!(value["timestamp"].as_str().unwrap().ends_with('Z'));
+        assert_eq!(
+            value["metadata"],
+            json!({
+                "tool_name": "interactive_feedback",
+                "execution_time_ms": 9250,
+                "commands_executed": ["cargo test"],
+                "user_interaction_time_ms": 9000,
+                "time_to_first_keystroke_ms": 1500,
+                "cancelled": false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_deserialize_minimal_v1() {
+        let feedback = Feedback::from_versioned_json(
+            r#"{
+                "version": "1",
+                "project_directory": "/project",
+                "summary": "Done?",
+                "command_logs": "",
+                "interactive_feedback": "",
+                "timestamp": "2026-10-18T09:30:00Z",
+                "metadata": {
+                    "tool_name": "interactive_feedback",
+                    "execution_time_ms": 10,
+                    "commands_executed": [],
+                    "user_interaction_time_ms": 5
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(feedback.conversation_history.is_empty());
+        assert!(feedback.attachments.is_empty());
+        assert_eq!(
+            feedback.metadata,
+            FeedbackMetadata {
+                execution_time: Duration::from_millis(10),
+                user_interaction_time: Duration::from_millis(5),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_unknown_version_is_rejected() {
+        let mut value: Value =
+            serde_json::from_str(&sample().to_versioned_json().unwrap()).unwrap();
+        value["version"] = json!("99");
+        assert!(Feedback::from_versioned_json(&value.to_string()).is_err());
+
+        value.as_object_mut().unwrap().remove("version");
+        assert!(serde_json::from_value::<VersionedFeedback>(value).is_err());
+    }
+}
@@ -253,4 +253,30 @@ mod metrics_tests {
         assert_eq!(stats.error_rate(), 0.0);
         assert_eq!(stats.success_rate(), 100.0);
     }
+
+    #[test]
+    fn test_concurrent_histogram_recording() {
+        let metrics = ToolMetrics::new();
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let metrics = metrics.clone();
+                std::thread::spawn(move || {
+                    for ms in 1..=1000 {
+                        metrics.record_success(Duration::from_micros(ms * 100));
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let stats = metrics.get_stats();
+        assert_eq!(stats.call_count, 8000);
+        assert_eq!(stats.latency.count(), 8000);
+        assert_eq!(stats.min_duration, Duration::from_micros(100));
+        assert_eq!(stats.max_duration, Duration::from_millis(100));
+        assert!(stats.p90_duration >= Duration::from_millis(90));
+        assert!(stats.p90_duration <= Duration::from_millis(96));
+    }
 }
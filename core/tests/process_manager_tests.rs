@@ -0,0 +1,661 @@
+//! Process manager integration tests against real child processes
+
+#[cfg(all(test, unix))]
+mod process_manager_tests {
+    use ifm_ruta_core::services::ProcessManagerImpl;
+    use ifm_ruta_core::traits::{ProcessError, ProcessManager, ProcessStatus};
+    use std::path::Path;
+    use std::time::{Duration, Instant};
+
+    fn sh(script: &str) -> Vec<String> {
+        vec!["-c".to_string(), script.to_string()]
+    }
+
+    // Check whether a pid still refers to a live (non-zombie) process
+    fn is_alive(pid: i32) -> bool {
+        match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => {
+                let state = stat
+                    .rsplit(')')
+                    .next()
+                    .and_then(|rest| rest.split_whitespace().next())
+                    .unwrap_or("");
+                state != "Z" && state != "X"
+            }
+            Err(_) => false,
+        }
+    }
+
+    #[test]
+    fn test_captures_output_and_exit_code() {
+        let manager = ProcessManagerImpl::new();
+        let handle = manager
+            .spawn_process("sh", &sh("echo out; echo err >&2; exit 3"), Path::new("."))
+            .unwrap();
+
+        let result = manager.wait_for_process(&handle).unwrap();
+        assert_eq!(result.exit_code, 3);
+        assert_eq!(result.stdout, "out\n");
+        assert_eq!(result.stderr, "err\n");
+        assert_eq!(
+            manager.process_status(&handle).unwrap(),
+            ProcessStatus::Failed
+        );
+    }
+
+    #[test]
+    fn test_successful_process_status() {
+        let manager = ProcessManagerImpl::new();
+        let handle = manager
+            .spawn_process("sh", &sh("true"), Path::new("."))
+            .unwrap();
+
+        let result = manager.wait_for_process(&handle).unwrap();
+        assert_eq!(result.exit_code, 0);
+        assert_eq!(
+            manager.process_status(&handle).unwrap(),
+            ProcessStatus::Completed
+        );
+    }
+
+    #[test]
+    fn test_runs_in_working_directory() {
+        let manager = ProcessManagerImpl::new();
+        let cwd = std::env::temp_dir().canonicalize().unwrap();
+        let handle = manager.spawn_process("pwd", &[], &cwd).unwrap();
+
+        let result = manager.wait_for_process(&handle).unwrap();
+        assert_eq!(result.stdout.trim(), cwd.to_str().unwrap());
+    }
+
+    #[test]
+    fn test_real_duration() {
+        let manager = ProcessManagerImpl::new();
+        let handle = manager
+            .spawn_process("sh", &sh("sleep 0.3"), Path::new("."))
+            .unwrap();
+
+        let result = manager.wait_for_process(&handle).unwrap();
+        assert!(result.duration >= Duration::from_millis(300));
+        assert!(result.duration < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_partial_output_while_running() {
+        let manager = ProcessManagerImpl::new();
+        let handle = manager
+            .spawn_process("sh", &sh("echo first; sleep 30"), Path::new("."))
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let output = loop {
+            let output = manager.get_process_output(&handle).unwrap();
+            if output.stdout.contains("first") || Instant::now() > deadline {
+                break output;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        };
+
+        assert_eq!(output.stdout, "first\n");
+        assert!(!output.is_complete);
+
+        manager.kill_process(&handle).unwrap();
+        let output_after = manager.wait_for_process(&handle).unwrap();
+        assert_eq!(output_after.stdout, "first\n");
+    }
+
+    #[test]
+    fn test_kill_reports_signal_exit_code() {
+        let manager = ProcessManagerImpl::new();
+        let handle = manager
+            .spawn_process("sh", &sh("sleep 30"), Path::new("."))
+            .unwrap();
+
+        manager.kill_process(&handle).unwrap();
+        let result = manager.wait_for_process(&handle).unwrap();
+
+        assert_eq!(result.exit_code, 128 + 9);
+        assert!(result.duration < Duration::from_secs(10));
+        assert_eq!(
+            manager.process_status(&handle).unwrap(),
+            ProcessStatus::Killed
+        );
+    }
+
+    #[test]
+    fn test_kill_takes_down_process_group() {
+        let manager = ProcessManagerImpl::new();
+        let handle = manager
+            .spawn_process("sh", &sh("sleep 30 & echo $!; wait"), Path::new("."))
+            .unwrap();
+
+        // Wait until the background child reported its pid
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let grandchild_pid = loop {
+            let output = manager.get_process_output(&handle).unwrap();
+            if let Ok(pid) = output.stdout.trim().parse::<i32>() {
+                break pid;
+            }
+            assert!(Instant::now() < deadline, "background pid not reported");
+            std::thread::sleep(Duration::from_millis(20));
+        };
+        assert!(is_alive(grandchild_pid));
+
+        manager.kill_process(&handle).unwrap();
+        manager.wait_for_process(&handle).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while is_alive(grandchild_pid) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(!is_alive(grandchild_pid));
+    }
+
+    #[test]
+    fn test_kill_after_leader_exit_takes_down_descendants() {
+        let manager = ProcessManagerImpl::new();
+        let handle = manager
+            .spawn_process("sh", &sh("sleep 30 & echo $!"), Path::new("."))
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let grandchild_pid = loop {
+            let output = manager.get_process_output(&handle).unwrap();
+            if let Ok(pid) = output.stdout.trim().parse::<i32>() {
+                break pid;
+            }
+            assert!(Instant::now() < deadline, "background pid not reported");
+            std::thread::sleep(Duration::from_millis(20));
+        };
+
+        // The leader has exited but stays unreaped while the pipes are
+        // held open, so its group can still be killed
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(
+            manager.process_status(&handle).unwrap(),
+            ProcessStatus::Running
+        );
+        manager.kill_process(&handle).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while is_alive(grandchild_pid) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(!is_alive(grandchild_pid));
+        assert_eq!(manager.wait_for_process(&handle).unwrap().exit_code, 0);
+    }
+
+    #[test]
+    fn test_kill_after_reap_does_nothing() {
+        let manager = ProcessManagerImpl::new();
+        let handle = manager
+            .spawn_process("sh", &sh("exit 0"), Path::new("."))
+            .unwrap();
+        manager.wait_for_process(&handle).unwrap();
+
+        // The process group id may belong to someone else by now
+        manager.kill_process(&handle).unwrap();
+        assert_eq!(
+            manager.process_status(&handle).unwrap(),
+            ProcessStatus::Completed
+        );
+    }
+
+    #[test]
+    fn test_wait_does_not_hang_on_background_descendants() {
+        let manager = ProcessManagerImpl::new();
+        let handle = manager
+            .spawn_process("sh", &sh("sleep 30 & echo started"), Path::new("."))
+            .unwrap();
+
+        let started = Instant::now();
+        let result = manager.wait_for_process(&handle).unwrap();
+
+        assert_eq!(result.exit_code, 0);
+        assert_eq!(result.stdout, "started\n");
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_reap_exited_processes() {
+        let manager = ProcessManagerImpl::new();
+        let finished = manager
+            .spawn_process("sh", &sh("exit 0"), Path::new("."))
+            .unwrap();
+        let running = manager
+            .spawn_process("sh", &sh("sleep 30"), Path::new("."))
+            .unwrap();
+
+        manager.wait_for_process(&finished).unwrap();
+        assert_eq!(manager.process_count(), 2);
+
+        let reaped = manager.reap_exited();
+        assert_eq!(reaped.len(), 1);
+        assert_eq!(reaped[0].id, finished.id);
+        assert_eq!(reaped[0].status, ProcessStatus::Completed);
+        assert_eq!(manager.process_count(), 1);
+
+        assert!(matches!(
+            manager.get_process_output(&finished),
+            Err(ProcessError::ProcessNotFound { .. })
+        ));
+
+        manager.kill_process(&running).unwrap();
+    }
+
+    #[test]
+    fn test_spawn_missing_binary_fails() {
+        let manager = ProcessManagerImpl::new();
+        let result = manager.spawn_process("ifm-ruta-no-such-binary", &[], Path::new("."));
+
+        assert!(matches!(result, Err(ProcessError::ExecutionFailed { .. })));
+        assert_eq!(manager.process_count(), 0);
+    }
+
+    #[test]
+    fn test_drop_kills_running_processes() {
+        let manager = ProcessManagerImpl::new();
+        let handle = manager
+            .spawn_process("sh", &sh("echo $$; sleep 30"), Path::new("."))
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let pid = loop {
+            let output = manager.get_process_output(&handle).unwrap();
+            if let Ok(pid) = output.stdout.trim().parse::<i32>() {
+                break pid;
+            }
+            assert!(Instant::now() < deadline, "pid not reported");
+            std::thread::sleep(Duration::from_millis(20));
+        };
+
+        drop(manager);
+        assert!(!is_alive(pid));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod process_policy_tests {
+    use ifm_ruta_core::models::SecuritySettings;
+    use ifm_ruta_core::security::ProcessPolicy;
+    use ifm_ruta_core::services::ProcessManagerImpl;
+    use ifm_ruta_core::traits::{
+        Event, EventBus, EventError, EventListener, EventType, ProcessError, ProcessManager,
+    };
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct RecordingBus {
+        events: Mutex<Vec<Event>>,
+    }
+
+    impl EventBus for RecordingBus {
+        fn subscribe(
+            &self,
+            _event_type: EventType,
+            _listener: Arc<dyn EventListener>,
+        ) -> Result<(), EventError> {
+            Ok(())
+        }
+
+        fn unsubscribe(&self, _event_type: EventType, _listener_id: &str) {}
+
+        fn publish(&self, event: Event) -> Result<(), EventError> {
+            self.events.lock().unwrap().push(event);
+            Ok(())
+        }
+    }
+
+    fn project_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ifm-ruta-policy-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    fn manager(allowed: &[&str], project_dir: &Path) -> (ProcessManagerImpl, Arc<RecordingBus>) {
+        let settings = SecuritySettings {
+            allowed_commands: allowed.iter().map(|c| c.to_string()).collect(),
+            sandbox_mode: true,
+            max_process_time: Duration::from_secs(60),
+            max_process_memory: None,
+        };
+        let bus = Arc::new(RecordingBus::default());
+        let manager = ProcessManagerImpl::new()
+            .with_policy(ProcessPolicy::from_security_settings(
+                &settings,
+                project_dir,
+            ))
+            .with_event_bus(bus.clone());
+        (manager, bus)
+    }
+
+    #[test]
+    fn test_denied_command_publishes_error_event() {
+        let dir = project_dir();
+        let (manager, bus) = manager(&["echo"], &dir);
+
+        let result = manager.spawn_process("rm", &["-rf".to_string()], &dir);
+        assert!(matches!(result, Err(ProcessError::PermissionDenied { .. })));
+        assert_eq!(manager.process_count(), 0);
+
+        let events = bus.events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, EventType::ErrorOccurred);
+        assert_eq!(events[0].data["command"], "rm");
+    }
+
+    #[test]
+    fn test_cwd_outside_project_is_denied() {
+        let dir = project_dir();
+        let (manager, bus) = manager(&["ls"], &dir);
+
+        let result = manager.spawn_process("ls", &[], Path::new("/"));
+        assert!(matches!(result, Err(ProcessError::PermissionDenied { .. })));
+        assert_eq!(bus.events.lock().unwrap().len(), 1);
+    }
+
+    const SECRET_ENV: &str = "IFM_RUTA_TEST_SECRET_TOKEN";
+
+    #[test]
+    fn test_allowed_command_runs_with_scrubbed_env() {
+        // Run the check in a child test process that inherits the secret, so
+        // this process's environment is never modified
+        let status = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--ignored",
+                "--exact",
+                "process_policy_tests::scrubbed_env_process",
+            ])
+            .env(SECRET_ENV, "hunter2")
+            .stdout(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    #[ignore = "run as a child process by test_allowed_command_runs_with_scrubbed_env"]
+    fn scrubbed_env_process() {
+        assert_eq!(std::env::var(SECRET_ENV).unwrap(), "hunter2");
+        let dir = project_dir();
+        let (manager, bus) = manager(&["env"], &dir);
+
+        let handle = manager.spawn_process("env", &[], &dir).unwrap();
+        let result = manager.wait_for_process(&handle).unwrap();
+
+        assert_eq!(result.exit_code, 0);
+        assert!(result.stdout.contains("PATH="));
+        assert!(!result.stdout.contains("hunter2"));
+        assert!(bus
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|event| event.event_type != EventType::ErrorOccurred));
+    }
+
+    #[test]
+    fn test_lifecycle_events_published() {
+        let dir = project_dir();
+        let (manager, bus) = manager(&["sh"], &dir);
+
+        std::fs::write(dir.join("exit.sh"), "exit 3\n").unwrap();
+        let handle = manager
+            .spawn_process("sh", &["exit.sh".to_string()], &dir)
+            .unwrap();
+        manager.wait_for_process(&handle).unwrap();
+        // Polling an exited process again must not publish a second event
+        manager.get_process_output(&handle).unwrap();
+
+        let events = bus.events.lock().unwrap();
+        let kinds: Vec<_> = events.iter().map(|e| e.event_type.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![EventType::ProcessSpawned, EventType::ProcessCompleted]
+        );
+        assert_eq!(events[0].data["process_id"], handle.id.as_str());
+        assert_eq!(events[1].data["exit_code"], 3);
+        assert_eq!(events[1].data["status"], "failed");
+        assert!(events[1].data["duration_ms"].is_u64());
+    }
+
+    #[test]
+    fn test_spawn_failure_publishes_error_event() {
+        let dir = project_dir();
+        let (manager, bus) = manager(&["ifm-ruta-no-such-command"], &dir);
+
+        let result = manager.spawn_process("ifm-ruta-no-such-command", &[], &dir);
+        assert!(matches!(result, Err(ProcessError::ExecutionFailed { .. })));
+
+        let events = bus.events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, EventType::ErrorOccurred);
+        assert_eq!(events[0].data["error"], "spawn_failed");
+    }
+
+    #[tokio::test]
+    async fn test_streaming_lifecycle_events_published() {
+        use futures::StreamExt;
+        use ifm_ruta_core::traits::{AsyncProcessManager, ProcessSpec};
+
+        let dir = project_dir();
+        let (manager, bus) = manager(&["echo"], &dir);
+
+        let mut process = manager
+            .spawn_streaming(ProcessSpec::new("echo").arg("hi").cwd(&dir))
+            .await
+            .unwrap();
+        while process.events.next().await.is_some() {}
+
+        let completed = |bus: &RecordingBus| {
+            bus.events
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|e| e.event_type == EventType::ProcessCompleted)
+        };
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while !completed(&bus) && std::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        let events = bus.events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event_type, EventType::ProcessSpawned);
+        assert_eq!(events[0].data["streaming"], true);
+        assert_eq!(events[1].event_type, EventType::ProcessCompleted);
+        assert_eq!(events[1].data["status"], "completed");
+        assert_eq!(events[1].data["exit_code"], 0);
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod resource_limit_tests {
+    use ifm_ruta_core::models::SecuritySettings;
+    use ifm_ruta_core::security::ProcessPolicy;
+    use ifm_ruta_core::services::ProcessManagerImpl;
+    use ifm_ruta_core::traits::{ProcessManager, ResourceLimits};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn sandboxed(limits: ResourceLimits) -> (ProcessManagerImpl, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("ifm-ruta-limits-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let settings = SecuritySettings {
+            allowed_commands: vec!["sh".to_string()],
+            sandbox_mode: true,
+            max_process_time: Duration::from_secs(60),
+            max_process_memory: None,
+        };
+        let policy = ProcessPolicy::from_security_settings(&settings, &dir).with_limits(limits);
+        (ProcessManagerImpl::new().with_policy(policy), dir)
+    }
+
+    // Shell metacharacters are rejected as arguments in sandbox mode, so
+    // scripts are run from files inside the project directory
+    fn script(dir: &std::path::Path, body: &str) -> Vec<String> {
+        std::fs::write(dir.join("script.sh"), body).unwrap();
+        vec!["script.sh".to_string()]
+    }
+
+    #[test]
+    fn test_cpu_hog_is_killed() {
+        let (manager, dir) = sandboxed(ResourceLimits {
+            cpu_time: Some(Duration::from_secs(1)),
+            ..Default::default()
+        });
+
+        let handle = manager
+            .spawn_process("sh", &script(&dir, "while :; do :; done"), &dir)
+            .unwrap();
+        let result = manager.wait_for_process(&handle).unwrap();
+
+        // SIGXCPU at the soft limit, SIGKILL at the hard limit
+        assert!(result.exit_code == 128 + 24 || result.exit_code == 128 + 9);
+        assert!(result.duration < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_memory_hog_is_contained() {
+        let (manager, dir) = sandboxed(ResourceLimits {
+            address_space: Some(64 * 1024 * 1024),
+            ..Default::default()
+        });
+
+        // Build a 256MB string in the shell's memory
+        let handle = manager
+            .spawn_process(
+                "sh",
+                &script(
+                    &dir,
+                    "x=$(head -c 268435456 /dev/zero | tr '\\0' x); echo ${#x}",
+                ),
+                &dir,
+            )
+            .unwrap();
+        let result = manager.wait_for_process(&handle).unwrap();
+
+        assert_ne!(result.exit_code, 0);
+        assert!(!result.stdout.contains("268435456"));
+    }
+
+    #[test]
+    fn test_open_files_are_limited() {
+        let (manager, dir) = sandboxed(ResourceLimits {
+            open_files: Some(32),
+            ..Default::default()
+        });
+
+        let handle = manager
+            .spawn_process("sh", &script(&dir, "ulimit -n"), &dir)
+            .unwrap();
+        let result = manager.wait_for_process(&handle).unwrap();
+
+        assert_eq!(result.stdout.trim(), "32");
+    }
+
+    #[test]
+    fn test_process_limit_is_applied() {
+        let (manager, dir) = sandboxed(ResourceLimits {
+            processes: Some(64),
+            ..Default::default()
+        });
+
+        // The limit is set for root too, even though it is not enforced
+        let handle = manager
+            .spawn_process("sh", &script(&dir, "cat /proc/self/limits"), &dir)
+            .unwrap();
+        let result = manager.wait_for_process(&handle).unwrap();
+
+        let line = result
+            .stdout
+            .lines()
+            .find(|line| line.starts_with("Max processes"))
+            .unwrap();
+        assert_eq!(line.split_whitespace().nth(2), Some("64"));
+    }
+
+    #[test]
+    fn test_fork_bomb_is_contained() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        use std::os::unix::process::CommandExt;
+
+        if std::fs::metadata("/proc/self").unwrap().uid() != 0 {
+            return fork_bomb_process();
+        }
+
+        // RLIMIT_NPROC is not enforced for root, so root runs the check as
+        // nobody, from a copy of this binary that nobody can execute
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o777)).unwrap();
+        let exe = dir.path().join("process_manager_tests");
+        std::fs::copy(std::env::current_exe().unwrap(), &exe).unwrap();
+        let status = std::process::Command::new(&exe)
+            .args([
+                "--ignored",
+                "--exact",
+                "resource_limit_tests::fork_bomb_process",
+            ])
+            .current_dir(dir.path())
+            .env("TMPDIR", dir.path())
+            .uid(65534)
+            .gid(65534)
+            .stdout(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    #[ignore = "run as a child process by test_fork_bomb_is_contained"]
+    fn fork_bomb_process() {
+        let (manager, dir) = sandboxed(ResourceLimits {
+            processes: Some(1),
+            ..Default::default()
+        });
+
+        let handle = manager
+            .spawn_process("sh", &script(&dir, "while :; do sleep 5 & done"), &dir)
+            .unwrap();
+        let result = manager.wait_for_process(&handle).unwrap();
+
+        // The shell gives up once it can no longer fork
+        assert_ne!(result.exit_code, 0);
+        assert!(result.stderr.to_lowercase().contains("fork"));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod process_table_tests {
+    use ifm_ruta_core::services::ProcessManagerImpl;
+    use ifm_ruta_core::traits::{ProcessManager, ProcessStatus};
+    use std::path::Path;
+    use std::time::Duration;
+
+    #[test]
+    fn test_lists_live_resource_usage() {
+        let manager = ProcessManagerImpl::new();
+        let handle = manager
+            .spawn_process("sleep", &["30".to_string()], Path::new("."))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+
+        let processes = manager.list_processes();
+        assert_eq!(processes.len(), 1);
+        let info = &processes[0];
+        assert_eq!(info.handle.id, handle.id);
+        assert_eq!(info.handle.status, ProcessStatus::Running);
+        assert!(info.pid > 0);
+        assert!(info.memory_bytes > 0);
+        assert!(info.elapsed >= Duration::from_millis(100));
+
+        manager.kill_process(&handle).unwrap();
+        manager.wait_for_process(&handle).unwrap();
+
+        let info = &manager.list_processes()[0];
+        assert_eq!(info.handle.status, ProcessStatus::Killed);
+        assert_eq!(info.memory_bytes, 0);
+    }
+}
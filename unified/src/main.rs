@@ -8,25 +8,38 @@ use include_dir::{include_dir, Dir};
 use serde::Deserialize;
 use std::collections::VecDeque;
 use std::io::{self, BufRead};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 use ifm_ruta_core::{
-    models::AppError,
-    services::{ConversationStorage, EventBusImpl, ProcessManagerImpl, SettingsManagerImpl},
-    utils::init_logging,
+    models::{AppError, AppSettings, ProjectSettings, ProjectTrust, RetentionPolicy},
+    security::ProcessPolicy,
+    services::{
+        open_conversation_store, AuditListener, AuditLog, EventBusImpl, EventQuery,
+        MetricsExporter, MetricsStoreListener, ProcessManagerImpl, ResponseAnalytics,
+        SettingsManagerImpl,
+    },
+    traits::{
+        ConversationStore, Event, EventBus, EventType, ProcessHandle, ProcessInfo, ProcessManager,
+        ProcessOutput, ProcessStatus, SettingsManager,
+    },
+    utils::{format_bytes, format_elapsed, init_logging, process_command_line, split_command_line},
 };
 
 // Include fonts directory
 static FONTS_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/fonts");
 
 // Re-export MCP modules from the mcp package
+mod approval;
 mod mcp;
 mod tools;
 
 use mcp::server::MCPRequest;
 use mcp::MCPServer;
-use tools::InteractiveFeedbackTool;
+use tools::{
+    discard_pasted_image, pasted_image_path, GuiFeedbackOutput, InteractiveFeedbackTool,
+    ListProcessesTool, RecentEventsTool, RunCommandTool, ToolMetricsTool, SESSION_ID_ENV,
+};
 
 #[derive(Deserialize, Clone)]
 struct ConversationEntry {
@@ -49,16 +62,22 @@ impl ConversationEntry {
 struct ConversationManager {
     conversations: Arc<Mutex<VecDeque<ConversationEntry>>>,
     max_size: usize,
-    storage: Option<ConversationStorage>,
+    store: Option<Arc<dyn ConversationStore>>,
+    /// Session to show; all sessions when not set
+    session_id: Option<String>,
 }
 
 impl ConversationManager {
-    fn new_with_storage(max_size: usize, project_directory: &Path) -> Self {
-        let storage = ConversationStorage::new(project_directory);
+    fn new_with_store(
+        max_size: usize,
+        store: Arc<dyn ConversationStore>,
+        session_id: Option<String>,
+    ) -> Self {
         let mut manager = Self {
             conversations: Arc::new(Mutex::new(VecDeque::new())),
             max_size,
-            storage: Some(storage),
+            store: Some(store),
+            session_id,
         };
 
         // Load real conversation history
@@ -67,9 +86,15 @@ impl ConversationManager {
     }
 
     fn load_conversation_history(&mut self) {
-        if let Some(ref storage) = self.storage {
-            // Load all conversation sessions from storage
-            match storage.get_project_sessions() {
+        if let Some(ref store) = self.store {
+            // Load the requested session, or all sessions, from storage
+            let sessions = match &self.session_id {
+                Some(session_id) => store
+                    .load_session(session_id)
+                    .map(|session| session.into_iter().collect()),
+                None => store.list_sessions(),
+            };
+            match sessions {
                 Ok(sessions) => {
                     println!("Loaded {} conversation sessions", sessions.len());
                     for session in sessions {
@@ -127,6 +152,13 @@ struct CursorContext {
     request_id: u64,
 }
 
+/// Command currently running in the command section
+struct RunningCommand {
+    handle: ProcessHandle,
+    command_line: String,
+    output: ProcessOutput,
+}
+
 /// Application state for GUI mode
 struct App {
     project_directory: String,
@@ -135,6 +167,21 @@ struct App {
     conversation_manager: ConversationManager,
     cursor_context: Option<CursorContext>,
     error_message: Option<String>,
+    attachments: Vec<PathBuf>,
+    process_manager: ProcessManagerImpl,
+    show_command_section: bool,
+    command_line: String,
+    /// Run `command_line` whenever the window opens for this project
+    auto_execute: bool,
+    running_command: Option<RunningCommand>,
+    command_logs: String,
+    commands_executed: Vec<String>,
+    process_table: Vec<ProcessInfo>,
+    process_table_refreshed_at: Option<std::time::Instant>,
+    /// Events published here are forwarded to the server in the result line
+    event_bus: Arc<EventBusImpl>,
+    opened_at: std::time::Instant,
+    first_keystroke_at: Option<std::time::Instant>,
 }
 
 impl App {
@@ -143,17 +190,217 @@ impl App {
         summary: String,
         cursor_context: Option<CursorContext>,
     ) -> Self {
-        // Use real conversation storage
-        let conversation_manager =
-            ConversationManager::new_with_storage(100, Path::new(&project_directory));
+        let project_settings =
+            ProjectSettings::load(Path::new(&project_directory)).unwrap_or_default();
+        // Only the user can make a command run on open, never the project
+        let trust = ProjectTrust::load(Path::new(&project_directory)).unwrap_or_default();
 
-        Self {
+        let event_bus = Arc::new(EventBusImpl::new());
+
+        // Commands typed in the GUI run under the configured security policy
+        let mut settings_manager = SettingsManagerImpl::new().with_event_bus(event_bus.clone());
+        if let Err(e) = settings_manager.load_settings() {
+            eprintln!("Failed to load settings, using defaults: {}", e);
+        }
+        let policy = ProcessPolicy::from_settings(
+            settings_manager.settings(),
+            Path::new(&project_directory),
+        );
+
+        // Use the configured conversation store, limited to the session of
+        // the feedback request when started by the MCP server
+        let conversation_manager = ConversationManager::new_with_store(
+            100,
+            open_conversation_store(
+                Path::new(&project_directory),
+                &settings_manager.settings().conversations,
+                RetentionPolicy::default(),
+            ),
+            std::env::var(SESSION_ID_ENV).ok(),
+        );
+
+        let mut app = Self {
             project_directory,
             summary,
             feedback: String::new(),
             conversation_manager,
             cursor_context,
             error_message: None,
+            attachments: Vec::new(),
+            process_manager: ProcessManagerImpl::new()
+                .with_policy(policy)
+                .with_event_bus(event_bus.clone()),
+            show_command_section: project_settings.ui_state.show_command_section,
+            command_line: trust
+                .auto_execute_command
+                .clone()
+                .or(project_settings.default_command)
+                .unwrap_or_default(),
+            auto_execute: trust.auto_execute_command.is_some(),
+            running_command: None,
+            command_logs: String::new(),
+            commands_executed: Vec::new(),
+            process_table: Vec::new(),
+            process_table_refreshed_at: None,
+            event_bus,
+            opened_at: std::time::Instant::now(),
+            first_keystroke_at: None,
+        };
+
+        if app.auto_execute && !app.command_line.trim().is_empty() {
+            app.show_command_section = true;
+            app.run_command();
+        }
+
+        app
+    }
+
+    /// Publish a `UIEvent` for a user action
+    fn publish_ui_event(&self, action: &str, data: serde_json::Value) {
+        let mut payload = serde_json::json!({
+            "action": action,
+            "project_directory": self.project_directory,
+        });
+        if let (Some(payload), serde_json::Value::Object(data)) = (payload.as_object_mut(), data) {
+            payload.extend(data);
+        }
+
+        if let Err(e) = self
+            .event_bus
+            .publish(Event::new(EventType::UIEvent, "gui", payload))
+        {
+            eprintln!("Failed to publish UI event: {}", e);
+        }
+    }
+
+    /// Get the events published in this process, oldest first
+    fn published_events(&self) -> Vec<serde_json::Value> {
+        let mut events = self.event_bus.query(&EventQuery::default()).events;
+        events.reverse();
+        events.iter().map(Event::to_json).collect()
+    }
+
+    fn run_command(&mut self) {
+        if self.running_command.is_some() {
+            return;
+        }
+
+        let command_line = self.command_line.trim().to_string();
+        self.publish_ui_event(
+            "run_command",
+            serde_json::json!({ "command_line": command_line }),
+        );
+        let parts = match split_command_line(&command_line) {
+            Ok(parts) => parts,
+            Err(e) => {
+                self.error_message = Some(e);
+                return;
+            }
+        };
+
+        match self.process_manager.spawn_process(
+            &parts[0],
+            &parts[1..],
+            Path::new(&self.project_directory),
+        ) {
+            Ok(handle) => {
+                self.commands_executed.push(command_line.clone());
+                self.error_message = None;
+                self.running_command = Some(RunningCommand {
+                    handle,
+                    command_line,
+                    output: ProcessOutput {
+                        stdout: String::new(),
+                        stderr: String::new(),
+                        is_complete: false,
+                    },
+                });
+            }
+            Err(e) => {
+                self.command_logs
+                    .push_str(&format!("$ {}\n{}\n\n", command_line, e));
+                self.error_message = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Grant or revoke running the current command on open
+    fn save_auto_execute(&mut self) {
+        let command_line = self.command_line.trim();
+        let project_directory = Path::new(&self.project_directory);
+        let mut trust = ProjectTrust::load(project_directory).unwrap_or_default();
+        trust.auto_execute_command =
+            (self.auto_execute && !command_line.is_empty()).then(|| command_line.to_string());
+        self.auto_execute = trust.auto_execute_command.is_some();
+        if let Err(e) = trust.save(project_directory) {
+            self.error_message = Some(format!("Failed to save auto-run command: {}", e));
+        }
+    }
+
+    fn stop_command(&mut self) {
+        if let Some(running) = &self.running_command {
+            self.publish_ui_event(
+                "stop_command",
+                serde_json::json!({ "command_line": running.command_line }),
+            );
+            if let Err(e) = self.process_manager.kill_process(&running.handle) {
+                self.error_message = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Refresh live output and move finished commands into the logs
+    fn poll_command(&mut self) {
+        let Some(running) = &mut self.running_command else {
+            return;
+        };
+
+        match self.process_manager.get_process_output(&running.handle) {
+            Ok(output) => running.output = output,
+            Err(e) => {
+                self.error_message = Some(e.to_string());
+                self.running_command = None;
+                return;
+            }
+        }
+
+        if running.output.is_complete {
+            self.finish_command();
+        }
+    }
+
+    /// Refresh the live process table, at most once per second so CPU
+    /// usage is measured over a meaningful interval
+    fn refresh_process_table(&mut self) {
+        let due = self
+            .process_table_refreshed_at
+            .is_none_or(|at| at.elapsed() >= std::time::Duration::from_secs(1));
+        if due {
+            self.process_table = self.process_manager.list_processes();
+            self.process_table_refreshed_at = Some(std::time::Instant::now());
+        }
+    }
+
+    fn finish_command(&mut self) {
+        let Some(running) = self.running_command.take() else {
+            return;
+        };
+
+        match self.process_manager.wait_for_process(&running.handle) {
+            Ok(result) => {
+                self.command_logs.push_str(&format!(
+                    "$ {}\n{}{}[exit code {} after {:.1}s]\n\n",
+                    running.command_line,
+                    result.stdout,
+                    result.stderr,
+                    result.exit_code,
+                    result.duration.as_secs_f64()
+                ));
+            }
+            Err(e) => {
+                self.command_logs
+                    .push_str(&format!("$ {}\n{}\n\n", running.command_line, e));
+            }
         }
     }
 
@@ -162,26 +409,138 @@ impl App {
             .add_conversation("user".to_string(), feedback);
     }
 
+    fn attach_file(&mut self, path: PathBuf) {
+        if !path.is_file() {
+            self.error_message = Some(format!("Not a file: {}", path.display()));
+            return;
+        }
+
+        if !self.attachments.contains(&path) {
+            self.publish_ui_event("attach_file", serde_json::json!({ "path": path }));
+            self.attachments.push(path);
+        }
+    }
+
+    fn pick_attachments(&mut self) {
+        if let Some(paths) = rfd::FileDialog::new()
+            .set_directory(&self.project_directory)
+            .pick_files()
+        {
+            for path in paths {
+                self.attach_file(path);
+            }
+        }
+    }
+
+    fn paste_clipboard_image(&mut self) {
+        let image = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_image());
+
+        match image {
+            Ok(image) => {
+                let Some(buffer) = image::RgbaImage::from_raw(
+                    image.width as u32,
+                    image.height as u32,
+                    image.bytes.into_owned(),
+                ) else {
+                    self.error_message = Some("Clipboard image has an invalid size".to_string());
+                    return;
+                };
+
+                let path = pasted_image_path();
+
+                match buffer.save(&path) {
+                    Ok(()) => self.attach_file(path),
+                    Err(e) => {
+                        self.error_message = Some(format!("Failed to save pasted image: {}", e))
+                    }
+                }
+            }
+            Err(_) => {
+                self.error_message = Some("Clipboard does not contain an image".to_string());
+            }
+        }
+    }
+
+    /// Milliseconds until the first keystroke and until now
+    fn response_times_ms(&self) -> (Option<u64>, u64) {
+        let since_opened = |at: std::time::Instant| (at - self.opened_at).as_millis() as u64;
+        (
+            self.first_keystroke_at.map(since_opened),
+            since_opened(std::time::Instant::now()),
+        )
+    }
+
     fn submit_feedback(&mut self) {
-        if self.feedback.trim().is_empty() {
+        if self.feedback.trim().is_empty() && self.attachments.is_empty() {
             self.error_message = Some("Please enter your feedback".to_string());
             return;
         }
 
+        // Stop a still running command so its partial output is included
+        if self.running_command.is_some() {
+            self.stop_command();
+            self.finish_command();
+        }
+
+        let (time_to_first_keystroke_ms, time_to_submit_ms) = self.response_times_ms();
+        self.publish_ui_event(
+            "submit_feedback",
+            serde_json::json!({
+                "feedback_length": self.feedback.chars().count(),
+                "attachments": self.attachments.len(),
+                "commands_executed": self.commands_executed.len(),
+                "time_to_first_keystroke_ms": time_to_first_keystroke_ms,
+                "time_to_submit_ms": time_to_submit_ms,
+            }),
+        );
+
         // Add user feedback to conversation history
         let feedback = std::mem::take(&mut self.feedback);
         self.add_user_feedback(feedback.clone());
 
         // Output feedback to stdout for MCP to capture
-        println!("{}", feedback);
+        let output = GuiFeedbackOutput {
+            feedback,
+            attachments: std::mem::take(&mut self.attachments),
+            command_logs: std::mem::take(&mut self.command_logs),
+            commands_executed: std::mem::take(&mut self.commands_executed),
+            time_to_first_keystroke_ms,
+            time_to_submit_ms: Some(time_to_submit_ms),
+            cancelled: false,
+            events: self.published_events(),
+        };
+        println!("{}", output.to_line());
 
         // Close application
         std::process::exit(0);
     }
 
     fn cancel_feedback(&mut self) {
+        if self.running_command.is_some() {
+            self.stop_command();
+        }
+        let (time_to_first_keystroke_ms, time_to_submit_ms) = self.response_times_ms();
+        self.publish_ui_event(
+            "cancel_feedback",
+            serde_json::json!({
+                "time_to_first_keystroke_ms": time_to_first_keystroke_ms,
+                "time_to_submit_ms": time_to_submit_ms,
+            }),
+        );
+
+        for attachment in &self.attachments {
+            discard_pasted_image(attachment);
+        }
+
         // Output empty feedback
-        println!();
+        let output = GuiFeedbackOutput {
+            time_to_first_keystroke_ms,
+            time_to_submit_ms: Some(time_to_submit_ms),
+            cancelled: true,
+            events: self.published_events(),
+            ..Default::default()
+        };
+        println!("{}", output.to_line());
 
         // Close application
         std::process::exit(0);
@@ -288,6 +647,18 @@ impl App {
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        // Closing the window with the title bar button cancels the feedback
+        if ctx.input(|i| i.viewport().close_requested()) {
+            self.cancel_feedback();
+        }
+
+        // Stream output of a running command
+        if self.running_command.is_some() {
+            self.poll_command();
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+        self.refresh_process_table();
+
         // Left panel - Conversation history
         eframe::egui::SidePanel::left("conversation_panel")
             .resizable(true)
@@ -510,6 +881,124 @@ impl eframe::App for App {
                             ui.add_space(10.0);
                         }
 
+                        // Command section
+                        eframe::egui::CollapsingHeader::new(eframe::egui::RichText::new("⚡ Run a command")
+                            .size(14.0)
+                            .color(eframe::egui::Color32::from_rgb(255, 180, 80))
+                            .strong())
+                            .default_open(self.show_command_section)
+                            .show(ui, |ui| {
+                                let is_running = self.running_command.is_some();
+
+                                ui.horizontal(|ui| {
+                                    let input = ui.add_enabled(
+                                        !is_running,
+                                        eframe::egui::TextEdit::singleline(&mut self.command_line)
+                                            .hint_text("e.g. cargo test")
+                                            .font(eframe::egui::TextStyle::Monospace)
+                                            .desired_width(ui.available_width() - 160.0),
+                                    );
+                                    let submitted = input.lost_focus()
+                                        && ui.input(|i| i.key_pressed(eframe::egui::Key::Enter));
+
+                                    if is_running {
+                                        if ui.button("■ Stop").clicked() {
+                                            self.stop_command();
+                                        }
+                                        ui.spinner();
+                                    } else if ui.button("▶ Run").clicked() || submitted {
+                                        self.run_command();
+                                    }
+                                });
+
+                                if ui
+                                    .checkbox(&mut self.auto_execute, "Run this command whenever feedback opens for this project")
+                                    .changed()
+                                {
+                                    self.save_auto_execute();
+                                }
+
+                                ui.add_space(6.0);
+
+                                if let Some(running) = &self.running_command {
+                                    ui.label(eframe::egui::RichText::new(format!("$ {}", running.command_line))
+                                        .size(12.0)
+                                        .monospace()
+                                        .color(eframe::egui::Color32::from_gray(180)));
+                                }
+
+                                eframe::egui::ScrollArea::vertical()
+                                    .id_source("command_output")
+                                    .max_height(200.0)
+                                    .stick_to_bottom(true)
+                                    .show(ui, |ui| {
+                                        if !self.command_logs.is_empty() {
+                                            ui.label(eframe::egui::RichText::new(&self.command_logs)
+                                                .size(12.0)
+                                                .monospace());
+                                        }
+                                        if let Some(running) = &self.running_command {
+                                            ui.label(eframe::egui::RichText::new(&running.output.stdout)
+                                                .size(12.0)
+                                                .monospace());
+                                            if !running.output.stderr.is_empty() {
+                                                ui.label(eframe::egui::RichText::new(&running.output.stderr)
+                                                    .size(12.0)
+                                                    .monospace()
+                                                    .color(eframe::egui::Color32::from_rgb(255, 120, 120)));
+                                            }
+                                        }
+                                    });
+
+                                if !self.process_table.is_empty() {
+                                    ui.add_space(6.0);
+                                    eframe::egui::CollapsingHeader::new(eframe::egui::RichText::new(format!("Processes ({})", self.process_table.len()))
+                                        .size(12.0)
+                                        .color(eframe::egui::Color32::from_gray(180)))
+                                        .default_open(true)
+                                        .show(ui, |ui| {
+                                            eframe::egui::Grid::new("process_table")
+                                                .striped(true)
+                                                .spacing([12.0, 4.0])
+                                                .show(ui, |ui| {
+                                                    for header in ["PID", "Status", "CPU", "Memory", "Elapsed", "Command"] {
+                                                        ui.label(eframe::egui::RichText::new(header).size(11.0).strong());
+                                                    }
+                                                    ui.end_row();
+
+                                                    for info in &self.process_table {
+                                                        let status_color = match info.handle.status {
+                                                            ProcessStatus::Running => eframe::egui::Color32::from_rgb(100, 200, 255),
+                                                            ProcessStatus::Completed => eframe::egui::Color32::from_rgb(100, 255, 100),
+                                                            ProcessStatus::Failed | ProcessStatus::Killed => eframe::egui::Color32::from_rgb(255, 120, 120),
+                                                        };
+                                                        ui.label(eframe::egui::RichText::new(info.pid.to_string()).size(11.0).monospace());
+                                                        ui.label(eframe::egui::RichText::new(info.handle.status.to_string()).size(11.0).color(status_color));
+                                                        ui.label(eframe::egui::RichText::new(format!("{:.1}%", info.cpu_usage)).size(11.0).monospace());
+                                                        ui.label(eframe::egui::RichText::new(format_bytes(info.memory_bytes)).size(11.0).monospace());
+                                                        ui.label(eframe::egui::RichText::new(format_elapsed(info.elapsed)).size(11.0).monospace());
+                                                        ui.label(eframe::egui::RichText::new(process_command_line(info)).size(11.0).monospace());
+                                                        ui.end_row();
+                                                    }
+                                                });
+                                        });
+                                }
+
+                                if !self.command_logs.is_empty() && !is_running {
+                                    ui.horizontal(|ui| {
+                                        ui.label(eframe::egui::RichText::new("Command output will be sent with your feedback")
+                                            .size(10.0)
+                                            .color(eframe::egui::Color32::from_gray(120)));
+                                        if ui.small_button("Clear").clicked() {
+                                            self.command_logs.clear();
+                                            self.commands_executed.clear();
+                                        }
+                                    });
+                                }
+                            });
+
+                        ui.add_space(10.0);
+
                         // Feedback input section with improved styling
                         let feedback_frame = eframe::egui::Frame::group(ui.style())
                             .fill(eframe::egui::Color32::from_rgba_premultiplied(20, 20, 20, 200))
@@ -545,10 +1034,13 @@ impl eframe::App for App {
                                     .desired_width(ui.available_width())
                                     .desired_rows(8);
 
-                                ui.add_sized(
+                                let response = ui.add_sized(
                                     [ui.available_width(), 200.0],
                                     text_edit
                                 );
+                                if response.changed() && self.first_keystroke_at.is_none() {
+                                    self.first_keystroke_at = Some(std::time::Instant::now());
+                                }
 
                                 ui.add_space(8.0);
 
@@ -564,6 +1056,45 @@ impl eframe::App for App {
                                             .color(eframe::egui::Color32::from_gray(120)));
                                     });
                                 });
+
+                                ui.add_space(12.0);
+
+                                // Attachments
+                                ui.horizontal(|ui| {
+                                    ui.label(eframe::egui::RichText::new("📎 Attachments")
+                                        .size(13.0)
+                                        .color(eframe::egui::Color32::from_rgb(100, 150, 255))
+                                        .strong());
+
+                                    if ui.button("Attach files…").clicked() {
+                                        self.pick_attachments();
+                                    }
+
+                                    if ui.button("📋 Paste image").clicked() {
+                                        self.paste_clipboard_image();
+                                    }
+                                });
+
+                                if self.attachments.is_empty() {
+                                    ui.label(eframe::egui::RichText::new("Drop files here or paste a screenshot from the clipboard")
+                                        .size(10.0)
+                                        .color(eframe::egui::Color32::from_gray(120)));
+                                }
+
+                                let mut removed = None;
+                                for (index, attachment) in self.attachments.iter().enumerate() {
+                                    ui.horizontal(|ui| {
+                                        if ui.small_button("✖").clicked() {
+                                            removed = Some(index);
+                                        }
+                                        ui.label(eframe::egui::RichText::new(attachment.display().to_string())
+                                            .size(11.0)
+                                            .color(eframe::egui::Color32::from_gray(200)));
+                                    });
+                                }
+                                if let Some(index) = removed {
+                                    discard_pasted_image(&self.attachments.remove(index));
+                                }
                             });
                         });
                     });
@@ -626,6 +1157,14 @@ impl eframe::App for App {
                 });
             });
 
+            // Drag-and-drop attachments
+            let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
+            for file in dropped_files {
+                if let Some(path) = file.path {
+                    self.attach_file(path);
+                }
+            }
+
             // Keyboard shortcuts
             if ctx.input(|i| i.key_pressed(eframe::egui::Key::Enter) && i.modifiers.ctrl) {
                 self.submit_feedback();
@@ -645,24 +1184,177 @@ async fn run_mcp_server() -> Result<(), AppError> {
     init_logging(tracing::Level::INFO)?;
 
     // Initialize core services
-    let settings_manager = std::sync::Arc::new(SettingsManagerImpl::new());
-    let process_manager = std::sync::Arc::new(ProcessManagerImpl::new());
-    #[allow(clippy::arc_with_non_send_sync)]
-    let event_bus = std::sync::Arc::new(EventBusImpl::new());
+    let event_bus = Arc::new(EventBusImpl::new());
+    let mut settings_manager = SettingsManagerImpl::new().with_event_bus(event_bus.clone());
+    if let Err(e) = settings_manager.load_settings() {
+        tracing::warn!("Failed to load settings, using defaults: {}", e);
+    }
+    let settings = settings_manager.settings().clone();
+    event_bus.set_history_capacity(settings.performance.event_history_size);
+    let settings_manager = std::sync::Arc::new(settings_manager);
+    let process_manager =
+        std::sync::Arc::new(ProcessManagerImpl::new().with_event_bus(event_bus.clone()));
+
+    // Create async MCP server (Phase 1), recording every tool call in the
+    // audit log of its project
+    let audit_log = AuditListener::new(
+        std::env::current_dir()?,
+        settings.performance.log_rotation_size as u64,
+    );
+    let server = MCPServer::new(settings_manager, process_manager.clone(), event_bus.clone())
+        .with_audit_log(Arc::new(audit_log));
+
+    // Persist tool metrics per project, in daily rollups, continuing the
+    // counters of previous runs in each project once it is used
+    let metrics_listener =
+        MetricsStoreListener::new(std::env::current_dir()?).with_registry(server.metrics());
+    if let Err(e) = event_bus.subscribe(EventType::ToolExecuted, Arc::new(metrics_listener)) {
+        tracing::warn!("Failed to enable metrics persistence: {}", e);
+    }
 
-    // Create async MCP server (Phase 1)
-    let server = MCPServer::new(settings_manager, process_manager, event_bus);
+    // Move aside conversation files corrupted by a crash of a previous run;
+    // the store is dropped right away so it never holds the database open
+    let recovered = open_conversation_store(
+        &std::env::current_dir()?,
+        &settings.conversations,
+        RetentionPolicy::default(),
+    )
+    .recover();
+    match recovered {
+        Ok(report) if !report.quarantined.is_empty() => tracing::warn!(
+            "Quarantined {} corrupt conversation sessions",
+            report.quarantined.len()
+        ),
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Failed to recover conversations: {}", e),
+    }
 
     // Register legacy tool (will be migrated in Phase 2)
     server
-        .register_tool(Box::new(InteractiveFeedbackTool::new()))
+        .register_tool(Box::new(
+            InteractiveFeedbackTool::new()
+                .with_retention(settings.retention.clone())
+                .with_conversation_settings(settings.conversations.clone())
+                .with_event_bus(event_bus.clone()),
+        ))
+        .await;
+    server
+        .register_async_tool(
+            "list_processes",
+            std::sync::Arc::new(ListProcessesTool::new(process_manager.clone())),
+        )
+        .await;
+    server
+        .register_async_tool(
+            "recent_events",
+            std::sync::Arc::new(RecentEventsTool::new(event_bus.clone())),
+        )
+        .await;
+    server
+        .register_async_tool(
+            "run_command",
+            std::sync::Arc::new(RunCommandTool::new(
+                process_manager.clone(),
+                settings.clone(),
+            )),
+        )
+        .await;
+    server
+        .register_async_tool(
+            "tool_metrics",
+            std::sync::Arc::new(ToolMetricsTool::new(server.metrics())),
+        )
         .await;
 
-    // Run the server with stdin/stdout like Go
-    let stdin = io::stdin();
-    let lines = stdin.lock().lines();
+    // Expose metrics for scraping
+    let exporter = Arc::new(
+        MetricsExporter::new(server.metrics())
+            .with_event_bus(event_bus.clone())
+            .with_process_manager(process_manager.clone()),
+    );
+    if let Some(address) = &settings.metrics.http_listen_address {
+        match exporter.clone().serve_http(address) {
+            Ok(bound) => tracing::info!("Serving metrics on http://{}/metrics", bound),
+            Err(e) => tracing::warn!("Failed to serve metrics on {}: {}", address, e),
+        }
+    }
+    let textfile = (!settings.metrics.textfile_interval.is_zero())
+        .then(|| MetricsExporter::textfile_path(&std::env::current_dir().unwrap_or_default()));
+    if let Some(path) = &textfile {
+        exporter
+            .clone()
+            .spawn_textfile_writer(path.clone(), settings.metrics.textfile_interval);
+    }
+
+    // Run the server with stdin/stdout like Go. Stdin is read on its own
+    // thread so that notifications, e.g. a cancellation, are seen while a
+    // request is still running
+    let (lines_tx, mut lines) = tokio::sync::mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            if lines_tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let result: Result<(), AppError> = async {
+        // Requests that arrived while another one was running
+        let mut pending = VecDeque::new();
+        let mut stdin_open = true;
+        loop {
+            let request = match pending.pop_front() {
+                Some(request) => request,
+                None if stdin_open => match next_request(&mut lines).await? {
+                    Some(request) => request,
+                    None => break,
+                },
+                None => break,
+            };
+
+            // Handle request and send response (async version - Phase 1)
+            let handling = server.handle_request(request);
+            tokio::pin!(handling);
+            let response = loop {
+                tokio::select! {
+                    biased;
+                    response = &mut handling => break response?,
+                    next = next_request(&mut lines), if stdin_open => match next? {
+                        Some(next) if next.id.is_none() => {
+                            server.handle_request(next).await?;
+                        }
+                        Some(next) => pending.push_back(next),
+                        None => stdin_open = false,
+                    },
+                }
+            };
+            if let Some(response) = response {
+                println!("{}", serde_json::to_string(&response)?);
+            }
+            // Notifications don't get responses (per JSON-RPC 2.0 spec)
+        }
+        Ok(())
+    }
+    .await;
+
+    // Stdout belongs to the protocol, so the summary goes to stderr
+    eprintln!("Tool metrics at shutdown:\n{}", server.metrics().summary());
+    if let Some(path) = &textfile {
+        if let Err(e) = exporter.write_textfile(path) {
+            tracing::warn!("Failed to write metrics to {}: {}", path.display(), e);
+        }
+    }
 
-    for line in lines {
+    result
+}
+
+/// Read the next JSON-RPC message from the stdin reader, skipping blank lines
+///
+/// Returns `None` once stdin is closed.
+async fn next_request(
+    lines: &mut tokio::sync::mpsc::UnboundedReceiver<io::Result<String>>,
+) -> Result<Option<MCPRequest>, AppError> {
+    while let Some(line) = lines.recv().await {
         let line = line?;
         let line = line.trim();
         if line.is_empty() {
@@ -670,18 +1362,178 @@ async fn run_mcp_server() -> Result<(), AppError> {
         }
 
         // Parse JSON request like Go
-        let request: MCPRequest = serde_json::from_str(line)?;
+        return Ok(Some(serde_json::from_str(line)?));
+    }
+    Ok(None)
+}
+
+/// Verify the audit log hash chain of a project, exiting with an error
+/// status if it was tampered with
+fn verify_audit_log(project_directory: &Path) -> Result<(), AppError> {
+    let report = AuditLog::verify(project_directory)?;
+    match &report.broken {
+        None => {
+            println!(
+                "Audit log OK: {} records in {} files",
+                report.records, report.files
+            );
+            Ok(())
+        }
+        Some(broken) => {
+            eprintln!(
+                "Audit log broken at {}:{}: {}",
+                broken.file.display(),
+                broken.line,
+                broken.reason
+            );
+            eprintln!("{} records verified before the break", report.records);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Load the application settings, falling back to the defaults
+fn load_app_settings() -> AppSettings {
+    let mut settings_manager = SettingsManagerImpl::new();
+    if let Err(e) = settings_manager.load_settings() {
+        eprintln!("Failed to load settings, using defaults: {}", e);
+    }
+    settings_manager.settings().clone()
+}
+
+/// Open the conversation store of a project selected by the settings, with
+/// the retention policy of the project
+fn project_conversation_store(
+    project_directory: &Path,
+) -> (Arc<dyn ConversationStore>, RetentionPolicy) {
+    let settings = load_app_settings();
+    let policy = ProjectSettings::load(project_directory)
+        .unwrap_or_default()
+        .retention_policy(&settings);
+    let store = open_conversation_store(project_directory, &settings.conversations, policy.clone());
+    (store, policy)
+}
+
+/// Print human response-time analytics of interactive feedback, per
+/// project and per hour of day
+fn print_feedback_analytics(project_directories: &[String], as_json: bool) -> Result<(), AppError> {
+    let settings = load_app_settings();
+    let analytics = project_directories
+        .iter()
+        .map(|dir| ResponseAnalytics::load(Path::new(dir), &settings.conversations))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if as_json {
+        let projects: Vec<serde_json::Value> = analytics.iter().map(|a| a.to_json()).collect();
+        println!("{}", serde_json::to_string_pretty(&projects)?);
+    } else {
+        let reports: Vec<String> = analytics.iter().map(|a| a.render()).collect();
+        print!("{}", reports.join("\n"));
+    }
+    Ok(())
+}
+
+/// Enforce the conversation retention policy of a project, or only report
+/// what would be removed
+fn compact_conversations(project_directory: &Path, dry_run: bool) -> Result<(), AppError> {
+    let (store, policy) = project_conversation_store(project_directory);
+    let report = store.compact(&policy, dry_run)?;
+    print!("{}", report.render());
+    Ok(())
+}
+
+/// Quarantine corrupt conversation sessions of a project and list
+/// everything in the quarantine
+fn recover_conversations(project_directory: &Path) -> Result<(), AppError> {
+    let (store, _) = project_conversation_store(project_directory);
+    let report = store.recover()?;
+    for quarantined in &report.quarantined {
+        println!(
+            "Quarantined session {}: {}",
+            quarantined.session_id, quarantined.reason
+        );
+    }
+    if report.removed_temp_files > 0 {
+        println!(
+            "Removed {} temporary files of interrupted writes",
+            report.removed_temp_files
+        );
+    }
 
-        // Handle request and send response (async version - Phase 1)
-        if let Some(response) = server.handle_request(request).await? {
-            println!("{}", serde_json::to_string(&response)?);
+    let quarantined = store.quarantined_sessions()?;
+    if quarantined.is_empty() {
+        println!("No quarantined sessions");
+    } else {
+        println!("Quarantined session files:");
+        for path in quarantined {
+            println!("  {}", path.display());
         }
-        // Notifications don't get responses (per JSON-RPC 2.0 spec)
     }
+    Ok(())
+}
 
+/// Convert the conversation sessions of a project to the current format of
+/// its store: JSONL logs for the files backend, while the database backend
+/// imports the file sessions
+fn migrate_conversations(project_directory: &Path) -> Result<(), AppError> {
+    let (store, _) = project_conversation_store(project_directory);
+    let migrated = store.migrate()?;
+    println!("Migrated {} sessions", migrated);
     Ok(())
 }
 
+/// Configure fonts and style shared by all GUI windows
+fn configure_egui(ctx: &eframe::egui::Context) {
+    // Configure fonts for Vietnamese support
+    let mut fonts = eframe::egui::FontDefinitions::default();
+
+    // Load Noto Sans font for Vietnamese support
+    if let Some(font_data) = FONTS_DIR.get_file("NotoSans-Regular.ttf") {
+        fonts.font_data.insert(
+            "noto_sans".to_owned(),
+            eframe::egui::FontData::from_static(font_data.contents()),
+        );
+
+        // Use Noto Sans as primary font
+        fonts
+            .families
+            .get_mut(&eframe::egui::FontFamily::Proportional)
+            .unwrap()
+            .insert(0, "noto_sans".to_owned());
+    }
+
+    ctx.set_fonts(fonts);
+
+    // Set light theme
+    ctx.set_visuals(eframe::egui::Visuals::light());
+
+    // Increase text and button sizes
+    ctx.style_mut(|style| {
+        // Increase font sizes
+        style.text_styles.insert(
+            eframe::egui::TextStyle::Heading,
+            eframe::egui::FontId::new(24.0, eframe::egui::FontFamily::Proportional),
+        );
+        style.text_styles.insert(
+            eframe::egui::TextStyle::Body,
+            eframe::egui::FontId::new(16.0, eframe::egui::FontFamily::Proportional),
+        );
+        style.text_styles.insert(
+            eframe::egui::TextStyle::Button,
+            eframe::egui::FontId::new(16.0, eframe::egui::FontFamily::Proportional),
+        );
+        style.text_styles.insert(
+            eframe::egui::TextStyle::Small,
+            eframe::egui::FontId::new(14.0, eframe::egui::FontFamily::Proportional),
+        );
+
+        // Increase button and spacing sizes
+        style.spacing.button_padding = eframe::egui::vec2(12.0, 8.0);
+        style.spacing.item_spacing = eframe::egui::vec2(8.0, 6.0);
+        style.spacing.window_margin = eframe::egui::Margin::same(12.0);
+    });
+}
+
 /// Run the GUI application
 fn run_gui_app(project_directory: String, summary: String, cursor_context: Option<CursorContext>) {
     println!("egui GUI started with project: {}", project_directory);
@@ -707,55 +1559,7 @@ fn run_gui_app(project_directory: String, summary: String, cursor_context: Optio
         "Interactive Feedback MCP",
         options,
         Box::new(|cc| {
-            // Configure fonts for Vietnamese support
-            let mut fonts = eframe::egui::FontDefinitions::default();
-
-            // Load Noto Sans font for Vietnamese support
-            if let Some(font_data) = FONTS_DIR.get_file("NotoSans-Regular.ttf") {
-                fonts.font_data.insert(
-                    "noto_sans".to_owned(),
-                    eframe::egui::FontData::from_static(font_data.contents()),
-                );
-
-                // Use Noto Sans as primary font
-                fonts
-                    .families
-                    .get_mut(&eframe::egui::FontFamily::Proportional)
-                    .unwrap()
-                    .insert(0, "noto_sans".to_owned());
-            }
-
-            cc.egui_ctx.set_fonts(fonts);
-
-            // Set light theme
-            cc.egui_ctx.set_visuals(eframe::egui::Visuals::light());
-
-            // Increase text and button sizes
-            cc.egui_ctx.style_mut(|style| {
-                // Increase font sizes
-                style.text_styles.insert(
-                    eframe::egui::TextStyle::Heading,
-                    eframe::egui::FontId::new(24.0, eframe::egui::FontFamily::Proportional),
-                );
-                style.text_styles.insert(
-                    eframe::egui::TextStyle::Body,
-                    eframe::egui::FontId::new(16.0, eframe::egui::FontFamily::Proportional),
-                );
-                style.text_styles.insert(
-                    eframe::egui::TextStyle::Button,
-                    eframe::egui::FontId::new(16.0, eframe::egui::FontFamily::Proportional),
-                );
-                style.text_styles.insert(
-                    eframe::egui::TextStyle::Small,
-                    eframe::egui::FontId::new(14.0, eframe::egui::FontFamily::Proportional),
-                );
-
-                // Increase button and spacing sizes
-                style.spacing.button_padding = eframe::egui::vec2(12.0, 8.0);
-                style.spacing.item_spacing = eframe::egui::vec2(8.0, 6.0);
-                style.spacing.window_margin = eframe::egui::Margin::same(12.0);
-            });
-
+            configure_egui(&cc.egui_ctx);
             Ok(Box::new(app))
         }),
     );
@@ -772,6 +1576,50 @@ fn main() -> Result<(), AppError> {
         return Ok(());
     }
 
+    // Check if verifying an audit log
+    if args.len() > 2 && args[1] == "--verify-audit" {
+        verify_audit_log(Path::new(&args[2]))?;
+        return Ok(());
+    }
+
+    // Check if reporting response-time analytics
+    if args.len() > 2 && args[1] == "--feedback-analytics" {
+        let as_json = args.iter().any(|arg| arg == "--json");
+        let project_directories: Vec<String> = args[2..]
+            .iter()
+            .filter(|arg| *arg != "--json")
+            .cloned()
+            .collect();
+        print_feedback_analytics(&project_directories, as_json)?;
+        return Ok(());
+    }
+
+    // Check if enforcing the conversation retention policy
+    if args.len() > 2 && args[1] == "--compact-conversations" {
+        let dry_run = args.iter().any(|arg| arg == "--dry-run");
+        compact_conversations(Path::new(&args[2]), dry_run)?;
+        return Ok(());
+    }
+
+    // Check if recovering corrupt conversation files
+    if args.len() > 2 && args[1] == "--recover-conversations" {
+        recover_conversations(Path::new(&args[2]))?;
+        return Ok(());
+    }
+
+    // Check if converting conversations to JSONL logs
+    if args.len() > 2 && args[1] == "--migrate-conversations" {
+        migrate_conversations(Path::new(&args[2]))?;
+        return Ok(());
+    }
+
+    // Check if running as command approval window
+    if args.len() > 3 && args[1] == "--approve-command" {
+        let reason = args.get(4).cloned().unwrap_or_default();
+        approval::run_approval_app(args[2].clone(), args[3].clone(), reason);
+        return Ok(());
+    }
+
     // Check if running as GUI with arguments
     if args.len() > 1 {
         let project_directory = args[1].clone();
@@ -796,6 +1644,30 @@ fn main() -> Result<(), AppError> {
         "  {} <project_dir> [summary]         # Run as GUI application",
         args[0]
     );
+    println!(
+        "  {} --approve-command <project_dir> <command> [reason]  # Ask to approve a command",
+        args[0]
+    );
+    println!(
+        "  {} --verify-audit <project_dir>    # Check the audit log hash chain",
+        args[0]
+    );
+    println!(
+        "  {} --feedback-analytics <project_dir>... [--json]  # Report human response times",
+        args[0]
+    );
+    println!(
+        "  {} --compact-conversations <project_dir> [--dry-run]  # Apply the retention policy",
+        args[0]
+    );
+    println!(
+        "  {} --recover-conversations <project_dir>  # Quarantine corrupt conversations",
+        args[0]
+    );
+    println!(
+        "  {} --migrate-conversations <project_dir>  # Convert conversations to the current format",
+        args[0]
+    );
     println!(
         "  {}                                 # Show this help",
         args[0]
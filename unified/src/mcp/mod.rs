@@ -1,5 +1,6 @@
 //! MCP protocol implementation
 
+pub mod progress;
 pub mod protocol;
 pub mod sampling;
 pub mod server;
@@ -0,0 +1,108 @@
+//! Progress notifications for long running tool calls (MCP `notifications/progress`)
+
+use serde_json::{json, Value};
+use std::io::Write;
+use std::sync::Arc;
+
+/// Sink receiving progress notifications
+pub type ProgressSink = Arc<dyn Fn(Value) + Send + Sync>;
+
+/// Reports progress for a single tool call
+///
+/// Notifications are only sent when the client asked for them by passing a
+/// `progressToken` in the request `_meta`.
+pub struct ProgressNotifier {
+    token: Option<Value>,
+    progress: u64,
+    sink: ProgressSink,
+}
+
+impl ProgressNotifier {
+    /// Create a notifier writing notifications to stdout
+    pub fn new(token: Option<Value>) -> Self {
+        Self::with_sink(
+            token,
+            Arc::new(|notification| {
+                let mut stdout = std::io::stdout().lock();
+                let _ = writeln!(stdout, "{}", notification);
+                let _ = stdout.flush();
+            }),
+        )
+    }
+
+    /// Create a notifier with a custom sink
+    pub fn with_sink(token: Option<Value>, sink: ProgressSink) -> Self {
+        Self {
+            token,
+            progress: 0,
+            sink,
+        }
+    }
+
+    /// Get the progress token from the request `_meta` of a tool call
+    pub fn token_from_meta(meta: &Value) -> Option<Value> {
+        meta.get("progressToken").cloned()
+    }
+
+    /// Report one step of progress with a message
+    pub fn notify(&mut self, message: &str) {
+        let Some(token) = &self.token else {
+            return;
+        };
+
+        self.progress += 1;
+        (self.sink)(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/progress",
+            "params": {
+                "progressToken": token,
+                "progress": self.progress,
+                "message": message
+            }
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn recording_notifier(token: Option<Value>) -> (ProgressNotifier, Arc<Mutex<Vec<Value>>>) {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let recorded = sent.clone();
+        let notifier = ProgressNotifier::with_sink(
+            token,
+            Arc::new(move |notification| recorded.lock().unwrap().push(notification)),
+        );
+        (notifier, sent)
+    }
+
+    #[test]
+    fn test_notifications_carry_token_and_progress() {
+        let (mut notifier, sent) = recording_notifier(Some(json!("tok-1")));
+        notifier.notify("compiling");
+        notifier.notify("testing");
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0]["method"], "notifications/progress");
+        assert_eq!(sent[1]["params"]["progressToken"], "tok-1");
+        assert_eq!(sent[1]["params"]["progress"], 2);
+        assert_eq!(sent[1]["params"]["message"], "testing");
+    }
+
+    #[test]
+    fn test_no_notifications_without_token() {
+        let (mut notifier, sent) = recording_notifier(None);
+        notifier.notify("ignored");
+        assert!(sent.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_token_from_meta() {
+        let meta = json!({"progressToken": 7});
+        assert_eq!(ProgressNotifier::token_from_meta(&meta), Some(json!(7)));
+        assert_eq!(ProgressNotifier::token_from_meta(&json!({})), None);
+    }
+}
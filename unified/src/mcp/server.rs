@@ -1,12 +1,17 @@
 //! MCP server implementation - Async version (Phase 1, Task 1.2)
 
 use serde_json::json;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::{Notify, RwLock};
 
 use ifm_ruta_core::{
     models::AppError,
-    traits::{AsyncTool, EventBus, ProcessManager, SettingsManager, Tool},
+    services::{
+        cap_arguments, cap_content, hash_json, summarize_result, AuditListener, MetricsRegistry,
+    },
+    traits::{AsyncTool, Event, EventBus, EventType, ProcessManager, SettingsManager, Tool},
 };
 
 /// Re-export protocol types from protocol module
@@ -21,14 +26,15 @@ pub use super::protocol::MCPResponse;
 /// MCP Error struct (JSON-RPC 2.0)
 pub use super::protocol::MCPError as ProtocolError;
 
+/// Key in the forwarded `_meta` holding the connection's session id
+pub const SESSION_META_KEY: &str = "ifm-ruta/sessionId";
+
 /// Async MCP server for handling concurrent requests
 pub struct MCPServer {
     /// Store for legacy synchronous tools (to be migrated)
-    #[allow(clippy::arc_with_non_send_sync)]
     tools: Arc<RwLock<std::collections::HashMap<String, Box<dyn Tool>>>>,
 
     /// Store for async tools (MCP 1.0)
-    #[allow(clippy::arc_with_non_send_sync)]
     async_tools: Arc<RwLock<std::collections::HashMap<String, Arc<dyn AsyncTool>>>>,
 
     #[allow(dead_code)]
@@ -37,11 +43,39 @@ pub struct MCPServer {
     #[allow(dead_code)]
     process_manager: Arc<dyn ProcessManager>,
 
-    #[allow(dead_code)]
     event_bus: Arc<dyn EventBus>,
+
+    /// Per-tool call metrics
+    metrics: Arc<MetricsRegistry>,
+
+    /// Unique id of this client connection
+    connection_id: String,
+
+    /// Client name reported in `initialize`
+    client_name: RwLock<Option<String>>,
+
+    /// Cancellation signals of the running tool calls, by request id
+    cancellations: Mutex<HashMap<String, Arc<Notify>>>,
+
+    /// Audit log every tool call is written to before it is answered
+    audit: Option<Arc<AuditListener>>,
+}
+
+/// Cancellation signal of a running tool call, unregistered on drop
+struct CancellationGuard<'a> {
+    server: &'a MCPServer,
+    key: Option<String>,
+    notify: Arc<Notify>,
+}
+
+impl Drop for CancellationGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = &self.key {
+            self.server.cancellations.lock().unwrap().remove(key);
+        }
+    }
 }
 
-#[allow(clippy::arc_with_non_send_sync)]
 impl MCPServer {
     /// Create a new async MCP server
     pub fn new(
@@ -55,9 +89,48 @@ impl MCPServer {
             settings_manager,
             process_manager,
             event_bus,
+            metrics: Arc::new(MetricsRegistry::new()),
+            connection_id: uuid::Uuid::new_v4().simple().to_string(),
+            client_name: RwLock::new(None),
+            cancellations: Mutex::new(HashMap::new()),
+            audit: None,
         }
     }
 
+    /// Write every tool call to the audit log before answering it
+    pub fn with_audit_log(mut self, audit: Arc<AuditListener>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Get the conversation session id of this connection
+    ///
+    /// Combines the client name from `initialize` with a per-connection id,
+    /// e.g. `cursor-1a2b3c4d`.
+    pub async fn session_id(&self) -> String {
+        let client: String = self
+            .client_name
+            .read()
+            .await
+            .as_deref()
+            .unwrap_or("mcp")
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        format!("{}-{}", client.trim_matches('-'), &self.connection_id[..8])
+    }
+
+    /// Get the per-tool call metrics
+    pub fn metrics(&self) -> Arc<MetricsRegistry> {
+        self.metrics.clone()
+    }
+
     /// Register a legacy synchronous tool (deprecated)
     #[allow(dead_code)]
     pub async fn register_tool(&self, tool: Box<dyn Tool>) {
@@ -86,6 +159,16 @@ impl MCPServer {
                     tracing::debug!("Received notifications/initialized");
                     return Ok(None);
                 }
+                "notifications/cancelled" => {
+                    if let Some(request_id) = request
+                        .params
+                        .as_ref()
+                        .and_then(|params| params.get("requestId"))
+                    {
+                        self.cancel(request_id);
+                    }
+                    return Ok(None);
+                }
                 _ => {
                     tracing::debug!("Received unknown notification: {}", request.method);
                     return Ok(None);
@@ -97,7 +180,8 @@ impl MCPServer {
         let response = match request.method.as_str() {
             "initialize" => self.handle_initialize(request).await,
             "tools/list" => self.handle_tools_list(request).await,
-            "tools/call" => self.handle_tool_call(request).await,
+            // Cancelled tool calls get no response
+            "tools/call" => return self.handle_tool_call(request).await,
             "resources/list" => self.handle_resources_list(request).await,
             "sampling" => self.handle_sampling(request).await,
             _ => Ok(MCPResponse {
@@ -119,6 +203,16 @@ impl MCPServer {
     async fn handle_initialize(&self, request: MCPRequest) -> Result<MCPResponse, AppError> {
         tracing::info!("Handling initialize request");
 
+        let client_name = request
+            .params
+            .as_ref()
+            .and_then(|params| params.pointer("/clientInfo/name"))
+            .and_then(|name| name.as_str())
+            .filter(|name| !name.trim().is_empty());
+        if let Some(name) = client_name {
+            *self.client_name.write().await = Some(name.to_string());
+        }
+
         Ok(MCPResponse {
             jsonrpc: "2.0".to_string(),
             id: request.id,
@@ -179,8 +273,62 @@ impl MCPServer {
         })
     }
 
+    /// Add the request `_meta` to the arguments of a legacy tool
+    ///
+    /// Tools whose schema rejects additional properties get the arguments
+    /// unchanged.
+    fn with_meta(
+        input_schema: &serde_json::Value,
+        mut arguments: serde_json::Value,
+        meta: serde_json::Value,
+    ) -> serde_json::Value {
+        if input_schema.get("additionalProperties") == Some(&json!(false)) {
+            return arguments;
+        }
+        if let Some(args) = arguments.as_object_mut() {
+            args.insert("_meta".to_string(), meta);
+        }
+        arguments
+    }
+
+    /// Cancel the running tool call with the given request id
+    pub fn cancel(&self, request_id: &serde_json::Value) {
+        match self
+            .cancellations
+            .lock()
+            .unwrap()
+            .get(&request_id.to_string())
+        {
+            Some(notify) => {
+                tracing::info!("Cancelling request {}", request_id);
+                notify.notify_one();
+            }
+            None => tracing::debug!("Ignoring cancellation of unknown request {}", request_id),
+        }
+    }
+
+    /// Register the cancellation signal of a tool call
+    fn track_cancellation(&self, request_id: Option<&serde_json::Value>) -> CancellationGuard<'_> {
+        let notify = Arc::new(Notify::new());
+        let key = request_id.map(|id| id.to_string());
+        if let Some(key) = &key {
+            self.cancellations
+                .lock()
+                .unwrap()
+                .insert(key.clone(), notify.clone());
+        }
+        CancellationGuard {
+            server: self,
+            key,
+            notify,
+        }
+    }
+
     /// Handle tools/call request
-    async fn handle_tool_call(&self, request: MCPRequest) -> Result<MCPResponse, AppError> {
+    ///
+    /// Returns no response when the request was cancelled.
+    async fn handle_tool_call(&self, request: MCPRequest) -> Result<Option<MCPResponse>, AppError> {
+        let cancellation = self.track_cancellation(request.id.as_ref());
         let params = request.params.unwrap_or(json!({}));
         let tool_name = params
             .get("name")
@@ -188,89 +336,190 @@ impl MCPServer {
             .ok_or_else(|| AppError::InternalError(anyhow::anyhow!("Missing tool name")))?;
 
         let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+        let arguments_hash = hash_json(&arguments);
+        let project_directory = arguments
+            .get("projectDirectory")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+
+        // Request metadata (e.g. the progress token) and the connection's
+        // session id, forwarded to the tool
+        let mut meta = params
+            .get("_meta")
+            .and_then(|meta| meta.as_object())
+            .cloned()
+            .unwrap_or_default();
+        meta.insert(SESSION_META_KEY.to_string(), json!(self.session_id().await));
+        let meta = json!(meta);
 
         tracing::info!("Executing tool: {}", tool_name);
-
-        // Try async tools first
-        {
-            let async_tools = self.async_tools.read().await;
-            if let Some(tool) = async_tools.get(tool_name) {
-                match tool.execute(arguments).await {
-                    Ok(result) => {
-                        return Ok(MCPResponse {
-                            jsonrpc: "2.0".to_string(),
-                            id: request.id,
-                            result: Some(json!({
-                                "content": [{
-                                    "type": "text",
-                                    "text": result.content
-                                }]
-                            })),
-                            error: None,
-                        });
-                    }
-                    Err(e) => {
-                        return Ok(MCPResponse {
-                            jsonrpc: "2.0".to_string(),
-                            id: request.id,
-                            result: None,
-                            error: Some(ProtocolError {
-                                code: -32603,
-                                message: format!("Tool execution error: {}", e),
-                                data: None,
-                            }),
-                        });
-                    }
+        let started_at = Instant::now();
+
+        // Try async tools first, then fall back to legacy tools
+        let async_tool = self.async_tools.read().await.get(tool_name).cloned();
+        let mut cancelled = false;
+        let outcome = if let Some(tool) = async_tool {
+            // Dropping the tool future on cancellation stops its work
+            let result = tokio::select! {
+                result = tool.execute_with_meta(arguments.clone(), meta) => Some(result),
+                _ = cancellation.notify.notified() => None,
+            };
+            match result {
+                None => {
+                    cancelled = true;
+                    Err("Request cancelled".to_string())
                 }
+                Some(Ok(result)) => Ok((
+                    json!({
+                        "content": [{
+                            "type": "text",
+                            "text": &result.content
+                        }],
+                        "isError": result.is_error
+                    }),
+                    result.is_error,
+                    result.content,
+                )),
+                Some(Err(e)) => Err(e.to_string()),
             }
-        }
-
-        // Fall back to legacy tools
-        {
+        } else {
             let legacy_tools = self.tools.read().await;
-            if let Some(tool) = legacy_tools.get(tool_name) {
-                match tool.execute(arguments) {
-                    Ok(tool_result) => {
-                        let result_json = serde_json::to_string(&tool_result)?;
-                        return Ok(MCPResponse {
-                            jsonrpc: "2.0".to_string(),
-                            id: request.id,
-                            result: Some(json!({
-                                "content": [{
-                                    "type": "text",
-                                    "text": result_json
-                                }]
-                            })),
-                            error: None,
-                        });
-                    }
-                    Err(e) => {
-                        return Ok(MCPResponse {
-                            jsonrpc: "2.0".to_string(),
-                            id: request.id,
-                            result: None,
-                            error: Some(ProtocolError {
-                                code: -32603,
-                                message: format!("Tool execution error: {}", e),
-                                data: None,
-                            }),
-                        });
+            match legacy_tools.get(tool_name) {
+                Some(tool) => {
+                    match tool.execute(Self::with_meta(
+                        &tool.input_schema(),
+                        arguments.clone(),
+                        meta,
+                    )) {
+                        // Tools that already return MCP content blocks are passed through
+                        Ok(tool_result) => {
+                            let is_error = tool_result
+                                .get("isError")
+                                .and_then(|v| v.as_bool())
+                                .unwrap_or(false);
+                            if tool_result
+                                .get("content")
+                                .is_some_and(|content| content.is_array())
+                            {
+                                let text = tool_result["content"][0]["text"]
+                                    .as_str()
+                                    .unwrap_or_default()
+                                    .to_string();
+                                Ok((tool_result, is_error, text))
+                            } else {
+                                let text = serde_json::to_string(&tool_result)?;
+                                Ok((
+                                    json!({
+                                        "content": [{
+                                            "type": "text",
+                                            "text": text
+                                        }]
+                                    }),
+                                    is_error,
+                                    text,
+                                ))
+                            }
+                        }
+                        Err(e) => Err(e.to_string()),
                     }
                 }
+                None => {
+                    self.publish(
+                        EventType::ErrorOccurred,
+                        json!({
+                            "error": "tool_not_found",
+                            "tool": tool_name,
+                        }),
+                    );
+
+                    return Ok(Some(MCPResponse {
+                        jsonrpc: "2.0".to_string(),
+                        id: request.id,
+                        result: None,
+                        error: Some(ProtocolError {
+                            code: -32601,
+                            message: format!("Tool not found: {}", tool_name),
+                            data: None,
+                        }),
+                    }));
+                }
             }
-        }
+        };
 
-        // Tool not found
-        Ok(MCPResponse {
-            jsonrpc: "2.0".to_string(),
-            id: request.id,
-            result: None,
-            error: Some(ProtocolError {
-                code: -32601,
-                message: format!("Tool not found: {}", tool_name),
-                data: None,
+        let duration = started_at.elapsed();
+        self.metrics
+            .record(tool_name, duration, matches!(outcome, Ok((_, false, _))));
+        let executed = Event::new(
+            EventType::ToolExecuted,
+            "mcp_server",
+            json!({
+                "tool": tool_name,
+                "project_directory": project_directory,
+                "arguments_hash": arguments_hash,
+                "arguments": cap_arguments(&arguments),
+                "result_summary": outcome.as_ref().ok().map(|(_, _, text)| summarize_result(text)),
+                "result": outcome.as_ref().ok().map(|(_, _, text)| cap_content(text)),
+                "duration_ms": duration.as_millis() as u64,
+                "duration_us": duration.as_micros() as u64,
+                "success": outcome.is_ok(),
+                "is_error": !matches!(outcome, Ok((_, false, _))),
+                "error": outcome.as_ref().err(),
             }),
-        })
+        );
+        // Written here rather than by a bus listener, which may drop events
+        // when it lags and loses the ones pending at shutdown
+        if let Some(audit) = &self.audit {
+            if let Err(e) = audit.record(&executed) {
+                tracing::error!("Failed to write audit record: {}", e);
+            }
+        }
+        self.publish_event(executed);
+
+        if cancelled {
+            return Ok(None);
+        }
+
+        match outcome {
+            Ok((result, _, _)) => Ok(Some(MCPResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request.id,
+                result: Some(result),
+                error: None,
+            })),
+            Err(message) => {
+                self.publish(
+                    EventType::ErrorOccurred,
+                    json!({
+                        "error": "tool_failed",
+                        "tool": tool_name,
+                        "reason": message,
+                    }),
+                );
+
+                Ok(Some(MCPResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id: request.id,
+                    result: None,
+                    error: Some(ProtocolError {
+                        code: -32603,
+                        message: format!("Tool execution error: {}", message),
+                        data: None,
+                    }),
+                }))
+            }
+        }
+    }
+
+    /// Publish a server event, logging instead of failing the request
+    fn publish(&self, event_type: EventType, data: serde_json::Value) {
+        self.publish_event(Event::new(event_type, "mcp_server", data));
+    }
+
+    /// Publish a built server event, logging instead of failing the request
+    fn publish_event(&self, event: Event) {
+        if let Err(e) = self.event_bus.publish(event) {
+            tracing::error!("Failed to publish server event: {}", e);
+        }
     }
 
     /// Handle resources/list request (MCP 1.0)
@@ -314,9 +563,230 @@ impl MCPServer {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use ifm_ruta_core::{
+        services::{EventBusImpl, ProcessManagerImpl, SettingsManagerImpl},
+        traits::{MCPResult, ToolMetadata, ToolResponse},
+    };
+
     #[tokio::test]
     async fn test_initialize_request() {
         // This test would require the full core/traits setup
         // Documented here for completeness
     }
+
+    /// Tool echoing its arguments and request metadata, failing when asked to
+    struct EchoTool;
+
+    #[async_trait]
+    impl AsyncTool for EchoTool {
+        async fn execute(&self, args: serde_json::Value) -> MCPResult<ToolResponse> {
+            self.execute_with_meta(args, json!({})).await
+        }
+
+        async fn execute_with_meta(
+            &self,
+            args: serde_json::Value,
+            meta: serde_json::Value,
+        ) -> MCPResult<ToolResponse> {
+            if args["hang"].as_bool().unwrap_or(false) {
+                futures::future::pending::<()>().await;
+            }
+            if args["fail"].as_bool().unwrap_or(false) {
+                return Err(ifm_ruta_core::traits::MCPError::ExecutionError(
+                    "asked to fail".to_string(),
+                ));
+            }
+            Ok(ToolResponse {
+                content: json!({"arguments": args, "meta": meta}).to_string(),
+                is_error: false,
+            })
+        }
+
+        fn metadata(&self) -> ToolMetadata {
+            ToolMetadata {
+                name: "echo".to_string(),
+                description: "Echo the arguments".to_string(),
+                input_schema: json!({"type": "object", "additionalProperties": false}),
+                version: "1.0.0".to_string(),
+            }
+        }
+    }
+
+    fn call(arguments: serde_json::Value) -> MCPRequest {
+        call_with_meta(arguments, json!({}))
+    }
+
+    fn call_with_meta(arguments: serde_json::Value, meta: serde_json::Value) -> MCPRequest {
+        serde_json::from_value(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": {"name": "echo", "arguments": arguments, "_meta": meta}
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_publishes_events() {
+        let event_bus = Arc::new(EventBusImpl::new());
+        let mut receiver = event_bus.receiver();
+        let server = MCPServer::new(
+            Arc::new(SettingsManagerImpl::new()),
+            Arc::new(ProcessManagerImpl::new()),
+            event_bus,
+        );
+        server.register_async_tool("echo", Arc::new(EchoTool)).await;
+
+        server.handle_request(call(json!({}))).await.unwrap();
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::ToolExecuted);
+        assert_eq!(event.data["tool"], "echo");
+        assert_eq!(event.data["success"], true);
+        assert!(event.data["duration_ms"].is_u64());
+        assert_eq!(event.data["arguments_hash"], hash_json(&json!({})));
+        assert_eq!(event.data["arguments"], json!({}));
+        assert!(event.data["result"]
+            .as_str()
+            .unwrap()
+            .contains(SESSION_META_KEY));
+        assert!(event.data["result_summary"]
+            .as_str()
+            .unwrap()
+            .contains(SESSION_META_KEY));
+
+        server
+            .handle_request(call(json!({"fail": true})))
+            .await
+            .unwrap();
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(event.data["success"], false);
+        assert_eq!(event.data["error"], "Execution error: asked to fail");
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::ErrorOccurred);
+        assert_eq!(event.data["error"], "tool_failed");
+
+        let stats = server.metrics().tool("echo").get_stats();
+        assert_eq!(stats.call_count, 2);
+        assert_eq!(stats.error_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_is_audited_before_the_response() {
+        let default_dir = tempfile::TempDir::new().unwrap();
+        let project = tempfile::TempDir::new().unwrap();
+        let server = MCPServer::new(
+            Arc::new(SettingsManagerImpl::new()),
+            Arc::new(ProcessManagerImpl::new()),
+            Arc::new(EventBusImpl::new()),
+        )
+        .with_audit_log(Arc::new(AuditListener::new(
+            default_dir.path().to_path_buf(),
+            0,
+        )));
+        server.register_async_tool("echo", Arc::new(EchoTool)).await;
+
+        server
+            .handle_request(call(json!({"projectDirectory": project.path()})))
+            .await
+            .unwrap();
+
+        let report = ifm_ruta_core::services::AuditLog::verify(project.path()).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.records, 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_tool_call_gets_no_response() {
+        let event_bus = Arc::new(EventBusImpl::new());
+        let mut receiver = event_bus.receiver();
+        let server = MCPServer::new(
+            Arc::new(SettingsManagerImpl::new()),
+            Arc::new(ProcessManagerImpl::new()),
+            event_bus,
+        );
+        server.register_async_tool("echo", Arc::new(EchoTool)).await;
+        let cancelled: MCPRequest = serde_json::from_value(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/cancelled",
+            "params": {"requestId": 1, "reason": "user aborted"}
+        }))
+        .unwrap();
+
+        let (response, _) =
+            tokio::join!(server.handle_request(call(json!({"hang": true}))), async {
+                tokio::task::yield_now().await;
+                server.handle_request(cancelled).await.unwrap()
+            });
+        assert!(response.unwrap().is_none());
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(event.event_type, EventType::ToolExecuted);
+        assert_eq!(event.data["error"], "Request cancelled");
+        assert!(server.cancellations.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_session_id_follows_connection() {
+        let server = MCPServer::new(
+            Arc::new(SettingsManagerImpl::new()),
+            Arc::new(ProcessManagerImpl::new()),
+            Arc::new(EventBusImpl::new()),
+        );
+        server.register_async_tool("echo", Arc::new(EchoTool)).await;
+        assert!(server.session_id().await.starts_with("mcp-"));
+
+        let initialize: MCPRequest = serde_json::from_value(json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "initialize",
+            "params": {"clientInfo": {"name": "Cursor IDE", "version": "1.0"}}
+        }))
+        .unwrap();
+        server.handle_request(initialize).await.unwrap();
+        let session_id = server.session_id().await;
+        assert!(session_id.starts_with("cursor-ide-"));
+        assert_eq!(session_id.len(), "cursor-ide-".len() + 8);
+
+        let response = server
+            .handle_request(call_with_meta(
+                json!({"command": "ls"}),
+                json!({"progressToken": 7}),
+            ))
+            .await
+            .unwrap()
+            .unwrap();
+        let echoed: serde_json::Value = serde_json::from_str(
+            response.result.unwrap()["content"][0]["text"]
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(echoed["meta"][SESSION_META_KEY], session_id);
+        assert_eq!(echoed["meta"]["progressToken"], 7);
+        // The arguments still match the tool's schema
+        assert_eq!(echoed["arguments"], json!({"command": "ls"}));
+
+        // Another connection gets another session
+        let other = MCPServer::new(
+            Arc::new(SettingsManagerImpl::new()),
+            Arc::new(ProcessManagerImpl::new()),
+            Arc::new(EventBusImpl::new()),
+        );
+        assert_ne!(other.session_id().await, server.session_id().await);
+    }
+
+    #[test]
+    fn test_legacy_tools_get_meta_only_if_schema_allows() {
+        let meta = json!({SESSION_META_KEY: "cursor-1a2b3c4d"});
+        let open = MCPServer::with_meta(&json!({"type": "object"}), json!({}), meta.clone());
+        assert_eq!(open["_meta"], meta);
+
+        let strict = MCPServer::with_meta(
+            &json!({"type": "object", "additionalProperties": false}),
+            json!({"prompt": "hi"}),
+            meta,
+        );
+        assert_eq!(strict, json!({"prompt": "hi"}));
+    }
 }
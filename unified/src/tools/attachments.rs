@@ -0,0 +1,242 @@
+//! Feedback attachments - GUI output format and MCP content blocks
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// Maximum size of a single attachment (10MB)
+pub const MAX_ATTACHMENT_SIZE: usize = 10 * 1024 * 1024;
+
+/// File name prefix of clipboard images saved by the GUI
+const PASTED_IMAGE_PREFIX: &str = "ifm-ruta-paste-";
+
+/// Get a new temporary path for a pasted clipboard image
+pub fn pasted_image_path() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "{}{}.png",
+        PASTED_IMAGE_PREFIX,
+        uuid::Uuid::new_v4().simple()
+    ))
+}
+
+/// Check whether a path is a temporary pasted image, owned by the feedback
+/// request and deleted once stored or discarded
+pub fn is_pasted_image(path: &Path) -> bool {
+    path.parent() == Some(std::env::temp_dir().as_path())
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(PASTED_IMAGE_PREFIX))
+}
+
+/// Delete an attachment if it is a temporary pasted image
+pub fn discard_pasted_image(path: &Path) {
+    if is_pasted_image(path) {
+        if let Err(e) = std::fs::remove_file(path) {
+            tracing::warn!("Failed to delete pasted image {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Result printed by the GUI process on its last stdout line
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GuiFeedbackOutput {
+    pub feedback: String,
+    #[serde(default)]
+    pub attachments: Vec<PathBuf>,
+    #[serde(default)]
+    pub command_logs: String,
+    #[serde(default)]
+    pub commands_executed: Vec<String>,
+    /// Milliseconds from opening the window until the user started typing
+    #[serde(default)]
+    pub time_to_first_keystroke_ms: Option<u64>,
+    /// Milliseconds from opening the window until it was submitted or cancelled
+    #[serde(default)]
+    pub time_to_submit_ms: Option<u64>,
+    #[serde(default)]
+    pub cancelled: bool,
+    /// Events published in the GUI process, oldest first, as `Event::to_json`
+    #[serde(default)]
+    pub events: Vec<serde_json::Value>,
+}
+
+impl GuiFeedbackOutput {
+    /// Parse the GUI stdout
+    ///
+    /// Falls back to treating the whole output as plain feedback text when
+    /// the last line is not a JSON result (older GUI builds).
+    pub fn parse(stdout: &str) -> Self {
+        let last_line = stdout.lines().rev().find(|line| !line.trim().is_empty());
+
+        if let Some(line) = last_line {
+            if let Ok(output) = serde_json::from_str::<GuiFeedbackOutput>(line.trim()) {
+                return output;
+            }
+        }
+
+        Self {
+            feedback: stdout.trim().to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Serialize to a single stdout line
+    pub fn to_line(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| self.feedback.clone())
+    }
+}
+
+/// Guess the MIME type of an attachment from its extension
+pub fn mime_type_for_path(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        "json" => "application/json",
+        "md" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "csv" => "text/csv",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "txt" | "log" | "rs" | "toml" | "yaml" | "yml" | "py" | "js" | "ts" | "go" | "sh" => {
+            "text/plain"
+        }
+        _ => "application/octet-stream",
+    }
+}
+
+/// Build an MCP content block for an attachment
+///
+/// Raster images become `image` blocks; everything else is embedded as a
+/// `resource` block, as text when it is valid UTF-8 and as a blob otherwise.
+pub fn attachment_content_block(path: &Path, data: &[u8]) -> Value {
+    let mime_type = mime_type_for_path(path);
+    let encoded = || base64::engine::general_purpose::STANDARD.encode(data);
+
+    if mime_type.starts_with("image/") && mime_type != "image/svg+xml" {
+        return json!({
+            "type": "image",
+            "data": encoded(),
+            "mimeType": mime_type
+        });
+    }
+
+    let uri = format!("file://{}", path.display());
+    match std::str::from_utf8(data) {
+        Ok(text) if mime_type != "application/octet-stream" || !text.contains('\0') => json!({
+            "type": "resource",
+            "resource": {
+                "uri": uri,
+                "mimeType": if mime_type == "application/octet-stream" { "text/plain" } else { mime_type },
+                "text": text
+            }
+        }),
+        _ => json!({
+            "type": "resource",
+            "resource": {
+                "uri": uri,
+                "mimeType": mime_type,
+                "blob": encoded()
+            }
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json_output() {
+        let stdout = "egui GUI started with project: /tmp\n{\"feedback\":\"looks good\",\"attachments\":[\"/tmp/a.png\"]}\n";
+        let output = GuiFeedbackOutput::parse(stdout);
+        assert_eq!(output.feedback, "looks good");
+        assert_eq!(output.attachments, vec![PathBuf::from("/tmp/a.png")]);
+    }
+
+    #[test]
+    fn test_pasted_image_paths() {
+        let path = pasted_image_path();
+        assert!(is_pasted_image(&path));
+        assert!(!is_pasted_image(&PathBuf::from(
+            "/home/user/ifm-ruta-paste-1.png"
+        )));
+        assert!(!is_pasted_image(
+            &std::env::temp_dir().join("screenshot.png")
+        ));
+
+        std::fs::write(&path, b"png").unwrap();
+        discard_pasted_image(&path);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_parse_plain_output() {
+        let output = GuiFeedbackOutput::parse("plain feedback\n");
+        assert_eq!(output.feedback, "plain feedback");
+        assert!(output.attachments.is_empty());
+    }
+
+    #[test]
+    fn test_output_round_trip() {
+        let output = GuiFeedbackOutput {
+            feedback: "line one\nline two".to_string(),
+            attachments: vec![PathBuf::from("/tmp/log.txt")],
+            command_logs: "$ cargo test\nok\n".to_string(),
+            commands_executed: vec!["cargo test".to_string()],
+            time_to_first_keystroke_ms: Some(1200),
+            time_to_submit_ms: Some(8400),
+            cancelled: false,
+            events: vec![serde_json::json!({"event_type": "UIEvent"})],
+        };
+        let parsed = GuiFeedbackOutput::parse(&output.to_line());
+        assert_eq!(parsed.feedback, output.feedback);
+        assert_eq!(parsed.attachments, output.attachments);
+        assert_eq!(parsed.command_logs, output.command_logs);
+        assert_eq!(parsed.commands_executed, output.commands_executed);
+        assert_eq!(parsed.time_to_first_keystroke_ms, Some(1200));
+        assert_eq!(parsed.time_to_submit_ms, Some(8400));
+        assert_eq!(parsed.events, output.events);
+    }
+
+    #[test]
+    fn test_parse_output_without_timings() {
+        let output = GuiFeedbackOutput::parse("{\"feedback\":\"\"}");
+        assert_eq!(output.time_to_submit_ms, None);
+        assert!(!output.cancelled);
+    }
+
+    #[test]
+    fn test_image_content_block() {
+        let block = attachment_content_block(Path::new("shot.PNG"), &[0x89, 0x50]);
+        assert_eq!(block["type"], "image");
+        assert_eq!(block["mimeType"], "image/png");
+        assert_eq!(block["data"], "iVA=");
+    }
+
+    #[test]
+    fn test_text_resource_block() {
+        let block = attachment_content_block(Path::new("/tmp/build.log"), b"error: oops");
+        assert_eq!(block["type"], "resource");
+        assert_eq!(block["resource"]["mimeType"], "text/plain");
+        assert_eq!(block["resource"]["text"], "error: oops");
+    }
+
+    #[test]
+    fn test_binary_resource_block() {
+        let block = attachment_content_block(Path::new("/tmp/data.bin"), &[0xff, 0x00, 0xfe]);
+        assert_eq!(block["type"], "resource");
+        assert!(block["resource"]["blob"].is_string());
+    }
+}
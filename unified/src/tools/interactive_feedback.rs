@@ -1,56 +1,154 @@
 //! Interactive feedback tool implementation
 
 use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ifm_ruta_core::models::{
+    ConversationHistoryEntry, ConversationSettings, Feedback, ProjectSettings, RetentionPolicy,
+};
+use ifm_ruta_core::security::InputValidator;
+use ifm_ruta_core::services::{open_conversation_store, ConversationMessage, ConversationStorage};
+use ifm_ruta_core::traits::{ConversationStore, Event, EventBus, Tool, ToolError, ValidationError};
+
+use super::attachments::{
+    attachment_content_block, discard_pasted_image, GuiFeedbackOutput, MAX_ATTACHMENT_SIZE,
+};
+use crate::mcp::server::SESSION_META_KEY;
+
+/// Environment variable passing the conversation session id to the GUI
+pub const SESSION_ID_ENV: &str = "IFM_RUTA_SESSION_ID";
+
+/// Number of history messages returned unless `historyLimit` is given
+const DEFAULT_HISTORY_LIMIT: usize = 10;
+
+/// How the conversation history is returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HistoryFormat {
+    /// Structured entries in `conversation_history`
+    Json,
+    /// A separate markdown text block after the feedback
+    Markdown,
+}
 
-use ifm_ruta_core::traits::{Tool, ToolError, ValidationError};
+impl HistoryFormat {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "json" => Some(Self::Json),
+            "markdown" => Some(Self::Markdown),
+            _ => None,
+        }
+    }
+}
 
 /// Interactive feedback tool
-pub struct InteractiveFeedbackTool;
+pub struct InteractiveFeedbackTool {
+    /// Session used when neither the agent nor the connection provides one
+    fallback_session_id: String,
+    /// Application retention policy, overridable per project
+    retention: RetentionPolicy,
+    /// Backend and format of the conversation store
+    conversations: ConversationSettings,
+    /// Bus the events of the GUI process are republished on
+    event_bus: Option<Arc<dyn EventBus>>,
+}
 
 impl InteractiveFeedbackTool {
     /// Create a new interactive feedback tool
     pub fn new() -> Self {
-        Self
+        Self {
+            fallback_session_id: format!(
+                "session-{}",
+                &uuid::Uuid::new_v4().simple().to_string()[..8]
+            ),
+            retention: RetentionPolicy::default(),
+            conversations: ConversationSettings::default(),
+            event_bus: None,
+        }
     }
 
-    // Removed unused methods: get_conversation_history and create_feedback_with_history
+    /// Republish the events of the GUI process on an event bus
+    pub fn with_event_bus(mut self, event_bus: Arc<dyn EventBus>) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
 
-    /// Create conversation history in Go format
-    fn create_conversation_history_format(
-        &self,
-        previous_user_request: &str,
-        prompt: &str,
-    ) -> Result<Value, ToolError> {
-        use chrono::Utc;
-        use uuid::Uuid;
+    /// Store conversations in the configured backend and format
+    pub fn with_conversation_settings(mut self, conversations: ConversationSettings) -> Self {
+        self.conversations = conversations;
+        self
+    }
 
-        let conversation_history = json!([
-            {
-                "id": Uuid::new_v4().to_string(),
-                "timestamp": Utc::now().to_rfc3339(),
-                "role": "user",
-                "content": previous_user_request,
-                "is_current": false
-            },
-            {
-                "id": Uuid::new_v4().to_string(),
-                "timestamp": Utc::now().to_rfc3339(),
-                "role": "assistant",
-                "content": prompt,
-                "is_current": false
-            }
-        ]);
+    /// Enforce a conversation retention policy when saving conversations
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Open the conversation store of a project
+    ///
+    /// The retention policy of the project overrides the application one.
+    fn conversation_store(&self, project_directory: &str) -> Arc<dyn ConversationStore> {
+        let project_directory = Path::new(project_directory);
+        let project_settings = ProjectSettings::load(project_directory).unwrap_or_default();
+        open_conversation_store(
+            project_directory,
+            &self.conversations,
+            self.retention.with_overrides(&project_settings.retention),
+        )
+    }
 
-        Ok(conversation_history)
+    /// Get the conversation session of a call
+    ///
+    /// An explicit `sessionId` from the agent wins over the session of the
+    /// client connection forwarded by the server in `_meta`.
+    fn session_id(&self, input: &Value) -> String {
+        input
+            .get("sessionId")
+            .and_then(|v| v.as_str())
+            .or_else(|| {
+                input
+                    .get("_meta")
+                    .and_then(|meta| meta.get(SESSION_META_KEY))
+                    .and_then(|v| v.as_str())
+                    .filter(|id| ConversationStorage::is_valid_session_id(id))
+            })
+            .unwrap_or(&self.fallback_session_id)
+            .to_string()
+    }
+
+    /// Load the last `limit` messages of the conversation from storage
+    ///
+    /// Messages saved by the current call are flagged `is_current`.
+    fn load_conversation_history(
+        &self,
+        project_directory: &str,
+        session_id: &str,
+        current_ids: &[String],
+        limit: usize,
+    ) -> Result<Vec<ConversationHistoryEntry>, ToolError> {
+        let messages = self
+            .conversation_store(project_directory)
+            .recent_messages(session_id, limit)
+            .map_err(|e| ToolError::ExecutionError {
+                message: format!("Failed to load conversation history: {}", e),
+            })?;
+
+        Ok(messages
+            .into_iter()
+            .map(|message| history_entry(message, current_ids))
+            .collect())
     }
 
     /// Run interactive feedback with egui GUI (Rust native)
     fn run_interactive_feedback_with_gui(
         &self,
         project_directory: &str,
+        session_id: &str,
         prompt: &str,
-    ) -> Result<String, ToolError> {
+    ) -> Result<GuiFeedbackOutput, ToolError> {
         // Use the current unified executable for GUI mode
         let current_exe = std::env::current_exe().map_err(|e| ToolError::ExecutionError {
             message: format!("Failed to get executable path: {}", e),
@@ -60,6 +158,7 @@ impl InteractiveFeedbackTool {
         let output = Command::new(current_exe)
             .arg(project_directory)
             .arg(prompt)
+            .env(SESSION_ID_ENV, session_id)
             .output()
             .map_err(|e| ToolError::ExecutionError {
                 message: format!("Failed to run GUI: {}", e),
@@ -74,60 +173,125 @@ impl InteractiveFeedbackTool {
             });
         }
 
-        let user_feedback = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        Ok(user_feedback)
+        Ok(GuiFeedbackOutput::parse(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
+
+    /// Publish the events reported by the GUI process on the server's bus
+    fn republish_gui_events(&self, events: &[Value]) {
+        let Some(event_bus) = &self.event_bus else {
+            return;
+        };
+        for event in events {
+            match Event::from_json(event) {
+                Some(event) => {
+                    if let Err(e) = event_bus.publish(event) {
+                        tracing::warn!("Failed to republish GUI event: {}", e);
+                    }
+                }
+                None => tracing::warn!("Ignoring malformed GUI event: {}", event),
+            }
+        }
+    }
+
+    /// Save attachments next to the current conversation and build content blocks
+    fn store_attachments(
+        &self,
+        project_directory: &str,
+        session_id: &str,
+        attachments: &[PathBuf],
+    ) -> Result<Vec<(PathBuf, Value)>, ToolError> {
+        let store = self.conversation_store(project_directory);
+        let mut stored = Vec::new();
+
+        for attachment in attachments {
+            let data = std::fs::read(attachment).map_err(|e| ToolError::ExecutionError {
+                message: format!("Failed to read attachment {}: {}", attachment.display(), e),
+            })?;
+
+            InputValidator::check_buffer_size(data.len(), MAX_ATTACHMENT_SIZE)
+                .map_err(|message| ToolError::ExecutionError { message })?;
+
+            let file_name = attachment
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("attachment");
+
+            let stored_path = store
+                .save_attachment(session_id, file_name, &data)
+                .map_err(|e| ToolError::ExecutionError {
+                    message: format!("Failed to save attachment: {}", e),
+                })?;
+
+            // The stored copy replaces a pasted image's temporary file
+            discard_pasted_image(attachment);
+
+            let block = attachment_content_block(&stored_path, &data);
+            stored.push((stored_path, block));
+        }
+
+        Ok(stored)
     }
 
-    /// Save real conversation to storage - append to current conversation only
+    /// Save real conversation to storage - append to the call's session
+    ///
+    /// Other sessions are pruned according to the retention policy of the
+    /// project.
     fn save_real_conversation(
         &self,
         project_directory: &str,
+        session_id: &str,
         previous_user_request: &str,
         prompt: &str,
-    ) -> Result<(), ToolError> {
-        use ifm_ruta_core::services::ConversationStorage;
-        use std::path::Path;
-
+    ) -> Result<Vec<String>, ToolError> {
         // Setup project directory with .gitignore and README
         self.setup_project_directory(project_directory)?;
 
-        let storage = ConversationStorage::new(Path::new(project_directory));
-        storage
-            .initialize()
-            .map_err(|e| ToolError::ExecutionError {
-                message: format!("Failed to initialize storage: {}", e),
-            })?;
-
-        // Clean up old conversation files, keep only current conversation
-        let _ = storage.cleanup_old_sessions(1); // Keep only 1 session (current)
-
-        // Use fixed session ID for current conversation only
-        let session_id = "current-conversation";
+        let store = self.conversation_store(project_directory);
+        let mut added = Vec::new();
 
         // Add user message if not empty
         if !previous_user_request.is_empty() {
-            storage
-                .add_message(session_id, "user", previous_user_request)
-                .map_err(|e| ToolError::ExecutionError {
-                    message: format!("Failed to add user message: {}", e),
-                })?;
+            added.push(
+                store
+                    .add_message(session_id, "user", previous_user_request)
+                    .map_err(|e| ToolError::ExecutionError {
+                        message: format!("Failed to add user message: {}", e),
+                    })?,
+            );
         }
 
         // Add assistant message
-        storage
-            .add_message(session_id, "assistant", prompt)
-            .map_err(|e| ToolError::ExecutionError {
-                message: format!("Failed to add assistant message: {}", e),
-            })?;
+        added.push(
+            store
+                .add_message(session_id, "assistant", prompt)
+                .map_err(|e| ToolError::ExecutionError {
+                    message: format!("Failed to add assistant message: {}", e),
+                })?,
+        );
 
-        println!("Added messages to current conversation: {}", session_id);
-        Ok(())
+        tracing::debug!("Added messages to conversation: {}", session_id);
+        Ok(added)
+    }
+
+    /// Record the user's feedback and how long it took in the conversation
+    fn save_feedback(
+        &self,
+        project_directory: &str,
+        session_id: &str,
+        feedback: &Feedback,
+    ) -> Result<(), ToolError> {
+        self.conversation_store(project_directory)
+            .record_feedback(session_id, feedback)
+            .map_err(|e| ToolError::ExecutionError {
+                message: format!("Failed to record feedback: {}", e),
+            })
     }
 
     /// Setup project directory with .gitignore and README
     fn setup_project_directory(&self, project_directory: &str) -> Result<(), ToolError> {
         use std::fs;
-        use std::path::Path;
 
         let project_path = Path::new(project_directory);
 
@@ -184,7 +348,6 @@ Do not manually modify the files in this directory.
     /// Setup .gitignore to exclude .ifm-ruta directory
     fn setup_gitignore(&self, project_directory: &str) -> Result<(), ToolError> {
         use std::fs;
-        use std::path::Path;
 
         let gitignore_path = Path::new(project_directory).join(".gitignore");
         let ifm_ruta_ignore = ".ifm-ruta/\n";
@@ -246,6 +409,20 @@ impl Tool for InteractiveFeedbackTool {
                 "previousUserRequest": {
                     "type": "string",
                     "description": "The previous user request that triggered this interactive feedback"
+                },
+                "historyLimit": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "Number of most recent conversation messages to return (default 10)"
+                },
+                "sessionId": {
+                    "type": "string",
+                    "description": "Conversation session to record this exchange in (letters, digits, '-', '_' and '.'); defaults to the session of the client connection"
+                },
+                "historyFormat": {
+                    "type": "string",
+                    "enum": ["json", "markdown"],
+                    "description": "Return the history as structured entries (json, default) or as a markdown text block (markdown)"
                 }
             },
             "required": ["projectDirectory", "prompt", "previousUserRequest"]
@@ -278,22 +455,91 @@ impl Tool for InteractiveFeedbackTool {
                 field: "previousUserRequest".to_string(),
             })?;
 
+        let history_limit = input
+            .get("historyLimit")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_HISTORY_LIMIT, |limit| limit as usize);
+        let history_format = input
+            .get("historyFormat")
+            .and_then(|v| v.as_str())
+            .and_then(HistoryFormat::parse)
+            .unwrap_or(HistoryFormat::Json);
+
+        let session_id = self.session_id(&input);
+
+        let started_at = Instant::now();
+
         // Save real conversation to storage
-        self.save_real_conversation(project_directory, previous_user_request, prompt)?;
+        let current_ids = self.save_real_conversation(
+            project_directory,
+            &session_id,
+            previous_user_request,
+            prompt,
+        )?;
+        let history = self.load_conversation_history(
+            project_directory,
+            &session_id,
+            &current_ids,
+            history_limit,
+        )?;
 
         // Run interactive feedback with Python GUI like Go implementation
-        let user_feedback = self.run_interactive_feedback_with_gui(project_directory, prompt)?;
-
-        // Create conversation history in Go format
-        let conversation_history =
-            self.create_conversation_history_format(previous_user_request, prompt)?;
-
-        // Return the result in Go format
-        Ok(json!({
-            "command_logs": "",
-            "interactive_feedback": user_feedback,
-            "conversation_history": conversation_history
-        }))
+        let gui_started_at = Instant::now();
+        let gui_output =
+            self.run_interactive_feedback_with_gui(project_directory, &session_id, prompt)?;
+        self.republish_gui_events(&gui_output.events);
+
+        // Older GUI builds do not report timings; fall back to the window lifetime
+        let mut feedback = Feedback::new(PathBuf::from(project_directory), prompt.to_string());
+        feedback.set_interactive_feedback(gui_output.feedback.clone());
+        feedback.set_command_logs(gui_output.command_logs.clone());
+        for command in &gui_output.commands_executed {
+            feedback.add_executed_command(command.clone());
+        }
+        feedback.set_response_timing(
+            gui_output
+                .time_to_first_keystroke_ms
+                .map(Duration::from_millis),
+            gui_output
+                .time_to_submit_ms
+                .map(Duration::from_millis)
+                .unwrap_or_else(|| gui_started_at.elapsed()),
+            gui_output.cancelled,
+        );
+
+        // Save attachments with the conversation and encode them as content blocks
+        let mut attachment_blocks = Vec::new();
+        for (stored_path, block) in
+            self.store_attachments(project_directory, &session_id, &gui_output.attachments)?
+        {
+            feedback.add_attachment(stored_path);
+            attachment_blocks.push(block);
+        }
+
+        let history_markdown = match history_format {
+            HistoryFormat::Json => {
+                feedback.set_conversation_history(history);
+                None
+            }
+            HistoryFormat::Markdown => Some(render_history_markdown(&history)),
+        };
+        feedback.set_execution_time(started_at.elapsed());
+        self.save_feedback(project_directory, &session_id, &feedback)?;
+
+        // Versioned feedback, the markdown history if requested, then one
+        // content block per attachment
+        let text = feedback
+            .to_versioned_json()
+            .map_err(|e| ToolError::ExecutionError {
+                message: e.to_string(),
+            })?;
+        let mut content = vec![json!({ "type": "text", "text": text })];
+        if let Some(markdown) = history_markdown {
+            content.push(json!({ "type": "text", "text": markdown }));
+        }
+        content.extend(attachment_blocks);
+
+        Ok(json!({ "content": content }))
     }
 
     fn validate_input(&self, input: &Value) -> Result<(), ValidationError> {
@@ -356,6 +602,198 @@ impl Tool for InteractiveFeedbackTool {
             }
         }
 
+        // Check the optional history settings
+        if let Some(limit) = obj.get("historyLimit") {
+            if !limit.is_u64() {
+                return Err(ValidationError::InvalidType {
+                    field: "historyLimit".to_string(),
+                    expected: "non-negative integer".to_string(),
+                });
+            }
+        }
+
+        if let Some(session_id) = obj.get("sessionId") {
+            if !session_id
+                .as_str()
+                .is_some_and(ConversationStorage::is_valid_session_id)
+            {
+                return Err(ValidationError::InvalidInput {
+                    message: "sessionId must be 1-128 letters, digits, '-', '_' or '.' and not start with '.'".to_string(),
+                });
+            }
+        }
+
+        if let Some(format) = obj.get("historyFormat") {
+            if format.as_str().and_then(HistoryFormat::parse).is_none() {
+                return Err(ValidationError::InvalidInput {
+                    message: "historyFormat must be \"json\" or \"markdown\"".to_string(),
+                });
+            }
+        }
+
         Ok(())
     }
 }
+
+/// Convert a stored message into a history entry
+fn history_entry(message: ConversationMessage, current_ids: &[String]) -> ConversationHistoryEntry {
+    ConversationHistoryEntry {
+        is_current: current_ids.contains(&message.id),
+        timestamp: chrono::DateTime::parse_from_rfc3339(&message.timestamp)
+            .map(|timestamp| timestamp.with_timezone(&chrono::Utc))
+            .unwrap_or_default(),
+        id: message.id,
+        role: message.role,
+        content: message.content,
+    }
+}
+
+/// Render conversation history as markdown, one section per message
+fn render_history_markdown(history: &[ConversationHistoryEntry]) -> String {
+    if history.is_empty() {
+        return "## Conversation history\n\nNo messages.\n".to_string();
+    }
+
+    let mut markdown = String::from("## Conversation history\n");
+    for entry in history {
+        markdown.push_str(&format!(
+            "\n### {} — {}{}\n\n{}\n",
+            entry.role,
+            entry
+                .timestamp
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            if entry.is_current { " (current)" } else { "" },
+            entry.content.trim_end(),
+        ));
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, role: &str, content: &str) -> ConversationMessage {
+        ConversationMessage {
+            id: id.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: "2026-10-18T09:30:00+00:00".to_string(),
+            response: None,
+            feedback: None,
+        }
+    }
+
+    #[test]
+    fn test_history_entry_flags_current_messages() {
+        let current = vec!["b".to_string()];
+        let old = history_entry(message("a", "user", "first"), &current);
+        let new = history_entry(message("b", "assistant", "second"), &current);
+
+        assert_eq!(old.id, "a");
+        assert!(!old.is_current);
+        assert!(new.is_current);
+        assert_eq!(new.timestamp.to_rfc3339(), "2026-10-18T09:30:00+00:00");
+    }
+
+    #[test]
+    fn test_render_history_markdown() {
+        let current = vec!["b".to_string()];
+        let history = vec![
+            history_entry(message("a", "user", "fix the build"), &current),
+            history_entry(message("b", "assistant", "Fixed.\n"), &current),
+        ];
+
+        let markdown = render_history_markdown(&history);
+        assert!(markdown.contains("### user — 2026-10-18T09:30:00Z\n\nfix the build\n"));
+        assert!(markdown.contains("### assistant — 2026-10-18T09:30:00Z (current)\n\nFixed.\n"));
+        assert!(render_history_markdown(&[]).contains("No messages."));
+    }
+
+    #[test]
+    fn test_validate_history_settings() {
+        let tool = InteractiveFeedbackTool::new();
+        let input = |extra: Value| {
+            let mut input = json!({
+                "projectDirectory": "/tmp",
+                "prompt": "Done?",
+                "previousUserRequest": "fix it",
+            });
+            input
+                .as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            input
+        };
+
+        assert!(tool
+            .validate_input(&input(
+                json!({"historyLimit": 5, "historyFormat": "markdown"})
+            ))
+            .is_ok());
+        assert!(tool
+            .validate_input(&input(json!({"historyLimit": -1})))
+            .is_err());
+        assert!(tool
+            .validate_input(&input(json!({"historyFormat": "html"})))
+            .is_err());
+        assert!(tool
+            .validate_input(&input(json!({"sessionId": "cursor-chat_1.2"})))
+            .is_ok());
+        assert!(tool
+            .validate_input(&input(json!({"sessionId": "../escape"})))
+            .is_err());
+    }
+
+    #[test]
+    fn test_session_id_resolution() {
+        let tool = InteractiveFeedbackTool::new();
+        let connection = json!({"_meta": {SESSION_META_KEY: "cursor-1a2b3c4d"}});
+
+        assert_eq!(tool.session_id(&connection), "cursor-1a2b3c4d");
+        assert_eq!(
+            tool.session_id(&json!({
+                "sessionId": "agent-chosen",
+                "_meta": {SESSION_META_KEY: "cursor-1a2b3c4d"},
+            })),
+            "agent-chosen"
+        );
+
+        // Without either, every call of this tool shares one session
+        let fallback = tool.session_id(&json!({}));
+        assert!(fallback.starts_with("session-"));
+        assert_eq!(tool.session_id(&json!({})), fallback);
+        assert_eq!(
+            tool.session_id(&json!({"_meta": {SESSION_META_KEY: "../x"}})),
+            fallback
+        );
+    }
+
+    #[test]
+    fn test_gui_events_are_republished() {
+        use ifm_ruta_core::services::{EventBusImpl, EventQuery};
+        use ifm_ruta_core::traits::EventType;
+
+        let event_bus = Arc::new(EventBusImpl::new());
+        let tool = InteractiveFeedbackTool::new().with_event_bus(event_bus.clone());
+
+        let spawned = Event::new(
+            EventType::ProcessSpawned,
+            "process_manager",
+            json!({"pid": 42}),
+        );
+        let output = GuiFeedbackOutput::parse(
+            &GuiFeedbackOutput {
+                events: vec![spawned.to_json(), json!({"malformed": true})],
+                ..Default::default()
+            }
+            .to_line(),
+        );
+        tool.republish_gui_events(&output.events);
+
+        let page = event_bus.query(&EventQuery::default());
+        assert_eq!(page.total, 1);
+        assert_eq!(page.events[0].event_type, EventType::ProcessSpawned);
+        assert_eq!(page.events[0].data["pid"], 42);
+    }
+}
@@ -0,0 +1,108 @@
+//! List processes tool - live table of the processes the server launched
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use ifm_ruta_core::{
+    services::ProcessManagerImpl,
+    traits::{AsyncTool, MCPResult, ProcessManager, ProcessStatus, ToolMetadata, ToolResponse},
+    utils::{process_info_json, render_process_table},
+};
+
+/// List processes tool
+pub struct ListProcessesTool {
+    process_manager: Arc<ProcessManagerImpl>,
+}
+
+impl ListProcessesTool {
+    /// Create a new list processes tool
+    pub fn new(process_manager: Arc<ProcessManagerImpl>) -> Self {
+        Self { process_manager }
+    }
+}
+
+#[async_trait]
+impl AsyncTool for ListProcessesTool {
+    async fn execute(&self, args: Value) -> MCPResult<ToolResponse> {
+        let include_exited = args
+            .get("includeExited")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        let processes: Vec<_> = self
+            .process_manager
+            .list_processes()
+            .into_iter()
+            .filter(|info| include_exited || info.handle.status == ProcessStatus::Running)
+            .collect();
+
+        let result = json!({
+            "processes": processes.iter().map(process_info_json).collect::<Vec<_>>(),
+            "table": render_process_table(&processes),
+        });
+
+        Ok(ToolResponse {
+            content: serde_json::to_string_pretty(&result).unwrap_or_default(),
+            is_error: false,
+        })
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata {
+            name: "list_processes".to_string(),
+            description: "List the processes launched by this server with their status, CPU usage, memory and elapsed time".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "includeExited": {
+                        "type": "boolean",
+                        "description": "Include processes that already exited (default: true)"
+                    }
+                },
+                "additionalProperties": false
+            }),
+            version: "1.0.0".to_string(),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn test_lists_running_and_exited_processes() {
+        let process_manager = Arc::new(ProcessManagerImpl::new());
+        let running = process_manager
+            .spawn_process("sleep", &["30".to_string()], Path::new("."))
+            .unwrap();
+        let exited = process_manager
+            .spawn_process("true", &[], Path::new("."))
+            .unwrap();
+        process_manager.wait_for_process(&exited).unwrap();
+
+        let tool = ListProcessesTool::new(process_manager.clone());
+
+        let all: Value =
+            serde_json::from_str(&tool.execute(json!({})).await.unwrap().content).unwrap();
+        assert_eq!(all["processes"].as_array().unwrap().len(), 2);
+
+        let only_running: Value = serde_json::from_str(
+            &tool
+                .execute(json!({"includeExited": false}))
+                .await
+                .unwrap()
+                .content,
+        )
+        .unwrap();
+        let processes = only_running["processes"].as_array().unwrap();
+        assert_eq!(processes.len(), 1);
+        assert_eq!(processes[0]["command"], "sleep 30");
+        assert_eq!(processes[0]["status"], "running");
+        assert!(only_running["table"].as_str().unwrap().contains("sleep 30"));
+
+        process_manager.kill_process(&running).unwrap();
+    }
+}
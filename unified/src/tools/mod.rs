@@ -1,7 +1,17 @@
 //! MCP tools implementation
 
+pub mod attachments;
 pub mod interactive_feedback;
+pub mod list_processes;
+pub mod recent_events;
+pub mod run_command;
 pub mod schemas; // NEW for Phase 1: Tool schemas
+pub mod tool_metrics;
 
 // Re-export
+pub use attachments::*;
 pub use interactive_feedback::*;
+pub use list_processes::*;
+pub use recent_events::*;
+pub use run_command::*;
+pub use tool_metrics::*;
@@ -0,0 +1,205 @@
+//! Recent events tool - inspect the event history for troubleshooting
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use ifm_ruta_core::{
+    services::{EventBusImpl, EventQuery},
+    traits::{AsyncTool, EventType, MCPError, MCPResult, ToolMetadata, ToolResponse},
+};
+
+/// Events returned when no limit is given
+const DEFAULT_LIMIT: usize = 50;
+
+/// Recent events tool
+pub struct RecentEventsTool {
+    event_bus: Arc<EventBusImpl>,
+}
+
+impl RecentEventsTool {
+    /// Create a new recent events tool
+    pub fn new(event_bus: Arc<EventBusImpl>) -> Self {
+        Self { event_bus }
+    }
+
+    /// Build the history query from the tool arguments
+    fn query(args: &Value) -> MCPResult<EventQuery> {
+        let mut query = EventQuery::new()
+            .offset(Self::count(args, "offset")?.unwrap_or(0))
+            .limit(Self::count(args, "limit")?.unwrap_or(DEFAULT_LIMIT));
+
+        if let Some(event_type) = args.get("eventType") {
+            let event_type: EventType =
+                serde_json::from_value(event_type.clone()).map_err(|_| {
+                    MCPError::InvalidParams(format!("Unknown eventType: {}", event_type))
+                })?;
+            query = query.event_type(event_type);
+        }
+        if let Some(source) = args.get("source").and_then(|v| v.as_str()) {
+            query = query.source(source);
+        }
+        if let Some(since) = Self::time(args, "since")? {
+            query = query.since(since);
+        }
+        if let Some(until) = Self::time(args, "until")? {
+            query = query.until(until);
+        }
+
+        Ok(query)
+    }
+
+    fn count(args: &Value, field: &str) -> MCPResult<Option<usize>> {
+        match args.get(field) {
+            None => Ok(None),
+            Some(value) => value
+                .as_u64()
+                .map(|n| Some(n as usize))
+                .ok_or_else(|| MCPError::InvalidParams(format!("{} must be a count", field))),
+        }
+    }
+
+    fn time(args: &Value, field: &str) -> MCPResult<Option<SystemTime>> {
+        let Some(value) = args.get(field) else {
+            return Ok(None);
+        };
+        value
+            .as_str()
+            .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+            .map(|time| Some(SystemTime::from(time)))
+            .ok_or_else(|| {
+                MCPError::InvalidParams(format!("{} must be an RFC 3339 timestamp", field))
+            })
+    }
+}
+
+#[async_trait]
+impl AsyncTool for RecentEventsTool {
+    async fn execute(&self, args: Value) -> MCPResult<ToolResponse> {
+        let query = Self::query(&args)?;
+        let page = self.event_bus.query(&query);
+
+        let result = json!({
+            "total": page.total,
+            "offset": query.offset,
+            "events": page.events.iter().map(|event| event.to_json()).collect::<Vec<_>>(),
+        });
+
+        Ok(ToolResponse {
+            content: serde_json::to_string_pretty(&result).unwrap_or_default(),
+            is_error: false,
+        })
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata {
+            name: "recent_events".to_string(),
+            description: "List recent server events (tool calls, processes, settings changes, errors), newest first, for troubleshooting".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "eventType": {
+                        "type": "string",
+                        "enum": ["ToolExecuted", "ProcessSpawned", "ProcessCompleted", "SettingsChanged", "ErrorOccurred", "UIEvent"],
+                        "description": "Only events of this type"
+                    },
+                    "source": {
+                        "type": "string",
+                        "description": "Only events from this source (e.g. mcp_server, process_manager)"
+                    },
+                    "since": {
+                        "type": "string",
+                        "description": "Only events at or after this RFC 3339 timestamp"
+                    },
+                    "until": {
+                        "type": "string",
+                        "description": "Only events before this RFC 3339 timestamp"
+                    },
+                    "offset": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "description": "Number of matching events to skip (default: 0)"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "description": "Maximum number of events to return (default: 50)"
+                    }
+                },
+                "additionalProperties": false
+            }),
+            version: "1.0.0".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ifm_ruta_core::traits::{Event, EventBus};
+
+    fn bus() -> Arc<EventBusImpl> {
+        let bus = Arc::new(EventBusImpl::new());
+        for n in 0..5 {
+            let (event_type, source) = if n % 2 == 0 {
+                (EventType::ToolExecuted, "mcp_server")
+            } else {
+                (EventType::ProcessSpawned, "process_manager")
+            };
+            bus.publish(Event::new(event_type, source, json!({ "n": n })))
+                .unwrap();
+        }
+        bus
+    }
+
+    async fn run(tool: &RecentEventsTool, args: Value) -> Value {
+        let response = tool.execute(args).await.unwrap();
+        serde_json::from_str(&response.content).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_filters_and_paginates() {
+        let tool = RecentEventsTool::new(bus());
+
+        let result = run(&tool, json!({"eventType": "ToolExecuted", "limit": 2})).await;
+        assert_eq!(result["total"], 3);
+        let numbers: Vec<_> = result["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["data"]["n"].clone())
+            .collect();
+        assert_eq!(numbers, vec![json!(4), json!(2)]);
+
+        let result = run(&tool, json!({"source": "process_manager", "offset": 1})).await;
+        assert_eq!(result["events"].as_array().unwrap().len(), 1);
+        assert_eq!(result["events"][0]["data"]["n"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_time_range() {
+        let tool = RecentEventsTool::new(bus());
+
+        let result = run(&tool, json!({"since": "2999-01-01T00:00:00Z"})).await;
+        assert_eq!(result["total"], 0);
+
+        let result = run(&tool, json!({"until": "2999-01-01T00:00:00Z"})).await;
+        assert_eq!(result["total"], 5);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments() {
+        let tool = RecentEventsTool::new(bus());
+        for args in [
+            json!({"eventType": "Nope"}),
+            json!({"since": "yesterday"}),
+            json!({"limit": -1}),
+        ] {
+            assert!(matches!(
+                tool.execute(args).await,
+                Err(MCPError::InvalidParams(_))
+            ));
+        }
+    }
+}
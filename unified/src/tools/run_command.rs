@@ -0,0 +1,575 @@
+//! Run command tool - agent proposed commands gated by user approval
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use ifm_ruta_core::{
+    models::{AppSettings, ProjectTrust},
+    security::ProcessPolicy,
+    services::ProcessManagerImpl,
+    traits::{
+        AsyncProcessManager, AsyncTool, MCPError, MCPResult, ProcessEvent, ProcessHandle,
+        ProcessManager, ProcessSpec, ToolMetadata, ToolResponse,
+    },
+    utils::split_command_line,
+};
+
+use crate::mcp::progress::ProgressNotifier;
+
+/// Decision printed by the approval GUI on its last stdout line
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CommandApproval {
+    pub approved: bool,
+    /// The command to run, possibly edited by the user
+    pub command: String,
+    /// Remember the command as approved for the project
+    #[serde(default)]
+    pub remember: bool,
+}
+
+impl CommandApproval {
+    /// Parse the approval GUI stdout
+    ///
+    /// Anything other than a JSON decision on the last line is a rejection.
+    pub fn parse(stdout: &str) -> Self {
+        stdout
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .and_then(|line| serde_json::from_str(line.trim()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Serialize to a single stdout line
+    pub fn to_line(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Asks the user whether a proposed command may run
+#[async_trait]
+pub trait CommandApprover: Send + Sync {
+    /// Request approval for a command, returning the user's decision
+    async fn request_approval(
+        &self,
+        project_directory: &Path,
+        command: &str,
+        reason: &str,
+    ) -> Result<CommandApproval, String>;
+}
+
+/// Approver showing the approval window of the GUI executable
+pub struct GuiCommandApprover;
+
+#[async_trait]
+impl CommandApprover for GuiCommandApprover {
+    async fn request_approval(
+        &self,
+        project_directory: &Path,
+        command: &str,
+        reason: &str,
+    ) -> Result<CommandApproval, String> {
+        let current_exe =
+            std::env::current_exe().map_err(|e| format!("Failed to get executable path: {}", e))?;
+
+        let output = tokio::process::Command::new(current_exe)
+            .arg("--approve-command")
+            .arg(project_directory)
+            .arg(command)
+            .arg(reason)
+            .output()
+            .await
+            .map_err(|e| format!("Failed to run approval GUI: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "Approval GUI failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        Ok(CommandApproval::parse(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
+}
+
+/// Kills a running command unless it exited
+///
+/// The tool future is dropped when its request is cancelled, which must not
+/// leave the command running.
+struct KillOnDrop<'a> {
+    process_manager: &'a ProcessManagerImpl,
+    handle: ProcessHandle,
+    exited: bool,
+}
+
+impl KillOnDrop<'_> {
+    /// Kill the process group of the command
+    fn kill(&self) {
+        if let Err(e) = self.process_manager.kill_process(&self.handle) {
+            tracing::warn!("Failed to kill '{}': {}", self.handle.command, e);
+        }
+    }
+}
+
+impl Drop for KillOnDrop<'_> {
+    fn drop(&mut self) {
+        if !self.exited {
+            self.kill();
+        }
+    }
+}
+
+/// Run command tool
+pub struct RunCommandTool {
+    process_manager: Arc<ProcessManagerImpl>,
+    settings: AppSettings,
+    approver: Arc<dyn CommandApprover>,
+    /// User-level file remembering approved commands per project
+    trust_path: PathBuf,
+}
+
+impl RunCommandTool {
+    /// Create a new run command tool asking for approval in the GUI
+    pub fn new(process_manager: Arc<ProcessManagerImpl>, settings: AppSettings) -> Self {
+        Self::with_approver(process_manager, settings, Arc::new(GuiCommandApprover))
+    }
+
+    /// Create a new run command tool with a custom approver
+    pub fn with_approver(
+        process_manager: Arc<ProcessManagerImpl>,
+        settings: AppSettings,
+        approver: Arc<dyn CommandApprover>,
+    ) -> Self {
+        Self {
+            process_manager,
+            settings,
+            approver,
+            trust_path: ProjectTrust::trust_path(),
+        }
+    }
+
+    /// Get the decision for a command, asking the user unless it was
+    /// approved for the project before
+    ///
+    /// Approvals are kept in the user-level trust file, never in the project,
+    /// so the project cannot approve commands for itself.
+    async fn approve(
+        &self,
+        project_directory: &Path,
+        command: &str,
+        reason: &str,
+    ) -> MCPResult<CommandApproval> {
+        let mut trust =
+            ProjectTrust::load_from(&self.trust_path, project_directory).unwrap_or_default();
+        if trust.is_command_approved(command) {
+            return Ok(CommandApproval {
+                approved: true,
+                command: command.to_string(),
+                remember: false,
+            });
+        }
+
+        let approval = self
+            .approver
+            .request_approval(project_directory, command, reason)
+            .await
+            .map_err(MCPError::ExecutionError)?;
+
+        if approval.approved && approval.remember {
+            trust.approve_command(&approval.command);
+            if let Err(e) = trust.save_to(&self.trust_path, project_directory) {
+                tracing::warn!("Failed to remember approved command: {}", e);
+            }
+        }
+
+        Ok(approval)
+    }
+}
+
+#[async_trait]
+impl AsyncTool for RunCommandTool {
+    async fn execute(&self, args: Value) -> MCPResult<ToolResponse> {
+        self.execute_with_meta(args, json!({})).await
+    }
+
+    async fn execute_with_meta(&self, args: Value, meta: Value) -> MCPResult<ToolResponse> {
+        self.validate_input(&args)?;
+
+        let project_directory = Path::new(args["projectDirectory"].as_str().unwrap_or_default());
+        let proposed = args["command"].as_str().unwrap_or_default().trim();
+        let reason = args.get("reason").and_then(|v| v.as_str()).unwrap_or("");
+
+        let approval = self.approve(project_directory, proposed, reason).await?;
+        if !approval.approved {
+            let result = json!({
+                "approved": false,
+                "command": proposed,
+                "message": "The user rejected the command"
+            });
+            return Ok(ToolResponse {
+                content: result.to_string(),
+                is_error: true,
+            });
+        }
+
+        let command = approval.command.trim();
+        let parts = split_command_line(command).map_err(MCPError::InvalidParams)?;
+        let spec = ProcessSpec::new(&parts[0])
+            .args(parts[1..].iter().cloned())
+            .cwd(project_directory);
+
+        // Approved commands still run under the security policy
+        let policy = ProcessPolicy::from_settings(&self.settings, project_directory);
+        let spec = self
+            .process_manager
+            .authorize_with(&policy, spec)
+            .map_err(|e| MCPError::ExecutionError(e.to_string()))?;
+
+        let timeout = args
+            .get("timeoutSeconds")
+            .and_then(|v| v.as_u64())
+            .map(Duration::from_secs)
+            .unwrap_or(self.settings.security.max_process_time);
+
+        let mut process = self
+            .process_manager
+            .spawn_streaming(spec)
+            .await
+            .map_err(|e| MCPError::ExecutionError(e.to_string()))?;
+        let mut guard = KillOnDrop {
+            process_manager: &self.process_manager,
+            handle: process.handle.clone(),
+            exited: false,
+        };
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut progress = ProgressNotifier::new(ProgressNotifier::token_from_meta(&meta));
+        let mut stdout = String::new();
+        let mut stderr = String::new();
+        let mut exit = None;
+        let mut timed_out = false;
+        loop {
+            let event = if timed_out {
+                process.events.next().await
+            } else {
+                match tokio::time::timeout_at(deadline, process.events.next()).await {
+                    Ok(event) => event,
+                    Err(_) => {
+                        // Keep reading after the kill to collect the exit status
+                        timed_out = true;
+                        guard.kill();
+                        continue;
+                    }
+                }
+            };
+            let Some(event) = event else {
+                break;
+            };
+            match event {
+                ProcessEvent::Stdout(line) => {
+                    progress.notify(&line);
+                    stdout.push_str(&line);
+                    stdout.push('\n');
+                }
+                ProcessEvent::Stderr(line) => {
+                    progress.notify(&line);
+                    stderr.push_str(&line);
+                    stderr.push('\n');
+                }
+                ProcessEvent::Exited {
+                    exit_code,
+                    duration,
+                } => exit = Some((exit_code, duration)),
+            }
+        }
+
+        guard.exited = true;
+
+        let (exit_code, duration) = exit.ok_or_else(|| {
+            MCPError::ExecutionError("Process ended without an exit status".to_string())
+        })?;
+
+        let result = json!({
+            "approved": true,
+            "command": command,
+            "edited": command != proposed,
+            "exit_code": exit_code,
+            "timed_out": timed_out,
+            "stdout": stdout,
+            "stderr": stderr,
+            "duration_ms": duration.as_millis() as u64
+        });
+
+        Ok(ToolResponse {
+            content: result.to_string(),
+            is_error: timed_out || exit_code != 0,
+        })
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata {
+            name: "run_command".to_string(),
+            description: "Propose a command to run in the project directory. The user approves, edits or rejects it before it runs; output is streamed as progress".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "projectDirectory": {
+                        "type": "string",
+                        "description": "The project directory the command runs in"
+                    },
+                    "command": {
+                        "type": "string",
+                        "description": "The command line to run (no shell expansion)"
+                    },
+                    "reason": {
+                        "type": "string",
+                        "description": "Why the command should run, shown to the user"
+                    },
+                    "timeoutSeconds": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Wall-clock limit after which the command is killed (defaults to security.max_process_time)"
+                    }
+                },
+                "required": ["projectDirectory", "command"],
+                "additionalProperties": false
+            }),
+            version: "1.0.0".to_string(),
+        }
+    }
+
+    fn validate_input(&self, args: &Value) -> MCPResult<()> {
+        for field in ["projectDirectory", "command"] {
+            match args.get(field).and_then(|v| v.as_str()) {
+                Some(value) if !value.trim().is_empty() => {}
+                _ => {
+                    return Err(MCPError::InvalidParams(format!(
+                        "Missing required field: {}",
+                        field
+                    )))
+                }
+            }
+        }
+        if let Some(timeout) = args.get("timeoutSeconds") {
+            if !matches!(timeout.as_u64(), Some(seconds) if seconds > 0) {
+                return Err(MCPError::InvalidParams(
+                    "timeoutSeconds must be a positive integer".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use ifm_ruta_core::models::ProjectSettings;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    /// Approver replaying a fixed decision and recording requests
+    struct FixedApprover {
+        decision: CommandApproval,
+        requests: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl CommandApprover for FixedApprover {
+        async fn request_approval(
+            &self,
+            _project_directory: &Path,
+            command: &str,
+            _reason: &str,
+        ) -> Result<CommandApproval, String> {
+            self.requests.lock().unwrap().push(command.to_string());
+            Ok(self.decision.clone())
+        }
+    }
+
+    fn tool(
+        decision: CommandApproval,
+        trust_dir: &TempDir,
+    ) -> (RunCommandTool, Arc<FixedApprover>) {
+        tool_with_settings(decision, trust_dir, AppSettings::default())
+    }
+
+    fn tool_with_settings(
+        decision: CommandApproval,
+        trust_dir: &TempDir,
+        settings: AppSettings,
+    ) -> (RunCommandTool, Arc<FixedApprover>) {
+        let approver = Arc::new(FixedApprover {
+            decision,
+            requests: Mutex::new(Vec::new()),
+        });
+        let mut tool = RunCommandTool::with_approver(
+            Arc::new(ProcessManagerImpl::new()),
+            settings,
+            approver.clone(),
+        );
+        tool.trust_path = trust_dir.path().join("trusted_projects.toml");
+        (tool, approver)
+    }
+
+    #[test]
+    fn test_parse_approval() {
+        let approval = CommandApproval {
+            approved: true,
+            command: "cargo test".to_string(),
+            remember: true,
+        };
+        let stdout = format!("approval GUI started\n{}\n", approval.to_line());
+        assert_eq!(CommandApproval::parse(&stdout), approval);
+        assert!(!CommandApproval::parse("window closed").approved);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_command_does_not_run() {
+        let project = TempDir::new().unwrap();
+        let trust_dir = TempDir::new().unwrap();
+        let dir = project.path();
+        let (tool, _) = tool(CommandApproval::default(), &trust_dir);
+
+        let response = tool
+            .execute(json!({"projectDirectory": dir, "command": "touch created"}))
+            .await
+            .unwrap();
+
+        let result: Value = serde_json::from_str(&response.content).unwrap();
+        assert_eq!(result["approved"], false);
+        assert!(!dir.join("created").exists());
+    }
+
+    #[tokio::test]
+    async fn test_runs_edited_command_and_remembers_it() {
+        let project = TempDir::new().unwrap();
+        let trust_dir = TempDir::new().unwrap();
+        let dir = project.path();
+        let (tool, approver) = tool(
+            CommandApproval {
+                approved: true,
+                command: "echo edited".to_string(),
+                remember: true,
+            },
+            &trust_dir,
+        );
+
+        let response = tool
+            .execute(json!({"projectDirectory": dir, "command": "echo proposed"}))
+            .await
+            .unwrap();
+
+        let result: Value = serde_json::from_str(&response.content).unwrap();
+        assert!(!response.is_error);
+        assert_eq!(result["stdout"], "edited\n");
+        assert_eq!(result["edited"], true);
+        assert_eq!(result["exit_code"], 0);
+        assert!(ProjectTrust::load_from(&tool.trust_path, dir)
+            .unwrap()
+            .is_command_approved("echo edited"));
+        assert!(!ProjectSettings::settings_path(dir).exists());
+
+        // Remembered commands run without asking again
+        tool.execute(json!({"projectDirectory": dir, "command": "echo edited"}))
+            .await
+            .unwrap();
+        assert_eq!(approver.requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_approved_command_still_subject_to_policy() {
+        let project = TempDir::new().unwrap();
+        let trust_dir = TempDir::new().unwrap();
+        let dir = project.path();
+        let (tool, _) = tool(
+            CommandApproval {
+                approved: true,
+                command: "echo $(whoami)".to_string(),
+                remember: false,
+            },
+            &trust_dir,
+        );
+
+        let result = tool
+            .execute(json!({"projectDirectory": dir, "command": "echo hi"}))
+            .await;
+        assert!(matches!(result, Err(MCPError::ExecutionError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_failing_command_is_error() {
+        let project = TempDir::new().unwrap();
+        let trust_dir = TempDir::new().unwrap();
+        let dir = project.path();
+        let (tool, _) = tool(
+            CommandApproval {
+                approved: true,
+                command: "ls no-such-file".to_string(),
+                remember: false,
+            },
+            &trust_dir,
+        );
+
+        let response = tool
+            .execute(json!({"projectDirectory": dir, "command": "ls no-such-file"}))
+            .await
+            .unwrap();
+        assert!(response.is_error);
+    }
+
+    #[tokio::test]
+    async fn test_command_is_killed_after_timeout() {
+        let project = TempDir::new().unwrap();
+        let trust_dir = TempDir::new().unwrap();
+        let mut settings = AppSettings::default();
+        settings.security.allowed_commands.push("sleep".to_string());
+        let (tool, _) = tool_with_settings(
+            CommandApproval {
+                approved: true,
+                command: "sleep 30".to_string(),
+                remember: false,
+            },
+            &trust_dir,
+            settings,
+        );
+
+        let started_at = std::time::Instant::now();
+        let response = tool
+            .execute(json!({
+                "projectDirectory": project.path(),
+                "command": "sleep 30",
+                "timeoutSeconds": 1
+            }))
+            .await
+            .unwrap();
+
+        let result: Value = serde_json::from_str(&response.content).unwrap();
+        assert!(response.is_error);
+        assert_eq!(result["timed_out"], true);
+        assert!(started_at.elapsed() < Duration::from_secs(20));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_timeout_rejected() {
+        let project = TempDir::new().unwrap();
+        let trust_dir = TempDir::new().unwrap();
+        let (tool, _) = tool(CommandApproval::default(), &trust_dir);
+
+        let result = tool
+            .execute(json!({
+                "projectDirectory": project.path(),
+                "command": "echo hi",
+                "timeoutSeconds": 0
+            }))
+            .await;
+        assert!(matches!(result, Err(MCPError::InvalidParams(_))));
+    }
+}
@@ -21,6 +21,21 @@ pub fn interactive_feedback_schema() -> Value {
             "previousUserRequest": {
                 "type": "string",
                 "description": "The previous user request that triggered this feedback"
+            },
+            "sessionId": {
+                "type": "string",
+                "pattern": "^[A-Za-z0-9_-][A-Za-z0-9._-]{0,127}$",
+                "description": "Conversation session to record this exchange in"
+            },
+            "historyLimit": {
+                "type": "integer",
+                "minimum": 0,
+                "description": "Number of most recent conversation messages to return"
+            },
+            "historyFormat": {
+                "type": "string",
+                "enum": ["json", "markdown"],
+                "description": "Format of the returned conversation history"
             }
         },
         "required": ["projectDirectory", "prompt"],
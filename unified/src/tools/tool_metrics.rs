@@ -0,0 +1,196 @@
+//! Tool metrics tool - call counts, error rates and durations per tool
+
+use async_trait::async_trait;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use ifm_ruta_core::{
+    services::{DailyRollup, MetricsRegistry, MetricsStore, ToolMetricsSnapshot},
+    traits::{AsyncTool, MCPError, MCPResult, ToolMetadata, ToolResponse},
+};
+
+/// Days covered by the persisted metrics unless `days` is given
+const DEFAULT_DAYS: u32 = 7;
+
+/// Most days the persisted metrics can cover, about ten years
+const MAX_DAYS: u32 = 3660;
+
+/// Tool metrics tool
+pub struct ToolMetricsTool {
+    metrics: Arc<MetricsRegistry>,
+}
+
+impl ToolMetricsTool {
+    /// Create a new tool metrics tool
+    pub fn new(metrics: Arc<MetricsRegistry>) -> Self {
+        Self { metrics }
+    }
+}
+
+#[async_trait]
+impl AsyncTool for ToolMetricsTool {
+    async fn execute(&self, args: Value) -> MCPResult<ToolResponse> {
+        let only = args.get("tool").and_then(|v| v.as_str());
+
+        let result = match args.get("projectDirectory").and_then(|v| v.as_str()) {
+            Some(project_directory) => {
+                let days = match args.get("days") {
+                    None => DEFAULT_DAYS,
+                    Some(days) => days
+                        .as_u64()
+                        .filter(|days| *days > 0)
+                        .map(|days| days.min(MAX_DAYS as u64) as u32)
+                        .ok_or_else(|| {
+                            MCPError::InvalidParams("days must be a positive integer".to_string())
+                        })?,
+                };
+                let rollups = MetricsStore::open(Path::new(project_directory))
+                    .last_days(days)
+                    .map_err(|e| MCPError::ExecutionError(e.to_string()))?;
+
+                json!({
+                    "projectDirectory": project_directory,
+                    "days": days,
+                    "tools": tools_json(&DailyRollup::merge(&rollups), only),
+                    "daily": rollups
+                        .iter()
+                        .map(|rollup| json!({
+                            "date": rollup.date.to_string(),
+                            "tools": tools_json(&rollup.tools, only),
+                        }))
+                        .collect::<Vec<_>>(),
+                })
+            }
+            None => {
+                let tools: Map<String, Value> = self
+                    .metrics
+                    .snapshot()
+                    .into_iter()
+                    .filter(|(name, _)| only.is_none_or(|only| only == name))
+                    .map(|(name, stats)| (name, stats.to_json()))
+                    .collect();
+
+                json!({
+                    "tools": tools,
+                    "summary": self.metrics.summary(),
+                })
+            }
+        };
+
+        Ok(ToolResponse {
+            content: serde_json::to_string_pretty(&result).unwrap_or_default(),
+            is_error: false,
+        })
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata {
+            name: "tool_metrics".to_string(),
+            description: "Show call counts, error rates and durations of the tools served since the server started, or the persisted daily metrics of a project".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "tool": {
+                        "type": "string",
+                        "description": "Only show the metrics of this tool"
+                    },
+                    "projectDirectory": {
+                        "type": "string",
+                        "description": "Show the persisted metrics of this project instead of the live ones"
+                    },
+                    "days": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": MAX_DAYS,
+                        "description": "Number of days, including today, covered by the persisted metrics (default 7)"
+                    }
+                },
+                "additionalProperties": false
+            }),
+            version: "1.0.0".to_string(),
+        }
+    }
+}
+
+/// Render per-tool snapshots as statistics
+fn tools_json(tools: &BTreeMap<String, ToolMetricsSnapshot>, only: Option<&str>) -> Value {
+    Value::Object(
+        tools
+            .iter()
+            .filter(|(name, _)| only.is_none_or(|only| only == name.as_str()))
+            .map(|(name, snapshot)| (name.clone(), snapshot.stats().to_json()))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_reports_recorded_calls() {
+        let metrics = Arc::new(MetricsRegistry::new());
+        metrics.record("interactive_feedback", Duration::from_secs(2), true);
+        metrics.record("run_command", Duration::from_millis(20), false);
+        let tool = ToolMetricsTool::new(metrics);
+
+        let response = tool.execute(json!({})).await.unwrap();
+        let result: Value = serde_json::from_str(&response.content).unwrap();
+        assert_eq!(result["tools"]["interactive_feedback"]["call_count"], 1);
+        assert_eq!(result["tools"]["run_command"]["error_count"], 1);
+
+        let response = tool.execute(json!({"tool": "run_command"})).await.unwrap();
+        let result: Value = serde_json::from_str(&response.content).unwrap();
+        assert_eq!(result["tools"].as_object().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reports_persisted_days() {
+        let dir = std::env::temp_dir().join(format!("ifm-ruta-metrics-{}", uuid::Uuid::new_v4()));
+        let store = MetricsStore::open(&dir);
+        let now = chrono::Local::now();
+        store
+            .record("interactive_feedback", Duration::from_secs(40), true, now)
+            .unwrap();
+        store
+            .record("interactive_feedback", Duration::from_secs(20), true, now)
+            .unwrap();
+        store
+            .record("run_command", Duration::from_millis(5), true, now)
+            .unwrap();
+        let tool = ToolMetricsTool::new(Arc::new(MetricsRegistry::new()));
+
+        let response = tool
+            .execute(json!({
+                "projectDirectory": dir.to_string_lossy(),
+                "tool": "interactive_feedback",
+            }))
+            .await
+            .unwrap();
+        let result: Value = serde_json::from_str(&response.content).unwrap();
+        assert_eq!(result["days"], 7);
+        let feedback = &result["tools"]["interactive_feedback"];
+        assert_eq!(feedback["call_count"], 2);
+        assert_eq!(feedback["average_duration_ms"], 30000.0);
+        assert_eq!(result["tools"].as_object().unwrap().len(), 1);
+        assert_eq!(result["daily"].as_array().unwrap().len(), 1);
+
+        assert!(tool
+            .execute(json!({"projectDirectory": dir.to_string_lossy(), "days": 0}))
+            .await
+            .is_err());
+
+        let response = tool
+            .execute(json!({"projectDirectory": dir.to_string_lossy(), "days": u64::MAX}))
+            .await
+            .unwrap();
+        let result: Value = serde_json::from_str(&response.content).unwrap();
+        assert_eq!(result["days"], MAX_DAYS);
+        assert_eq!(result["daily"].as_array().unwrap().len(), 1);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}